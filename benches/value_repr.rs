@@ -0,0 +1,49 @@
+//! Times the bench scripts with the release `latte` binary. Run it once per Value representation and
+//! compare the two reports:
+//!
+//!     cargo bench --bench value_repr
+//!     cargo bench --bench value_repr --features nan_boxing
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+fn run_script(path: &Path) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_latte"))
+        .arg(path)
+        .stdout(Stdio::null())
+        .status()
+        .expect("failed to start latte");
+    let elapsed = start.elapsed();
+    assert!(status.success(), "{} exited with {}", path.display(), status);
+    elapsed
+}
+
+fn main() {
+    let representation = if cfg!(feature = "nan_boxing") { "nan_boxing (8-byte Value)" } else { "tagged union (16-byte Value)" };
+    println!("Value representation: {}", representation);
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/scripts");
+    let mut scripts: Vec<_> = std::fs::read_dir(&dir)
+        .expect("missing benches/scripts")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lat"))
+        .collect();
+    scripts.sort();
+
+    for script in scripts {
+        // One warm-up run, then the fastest and median of RUNS.
+        run_script(&script);
+        let mut times: Vec<Duration> = (0..RUNS).map(|_| run_script(&script)).collect();
+        times.sort();
+        println!(
+            "{:<20} min {:>8.1} ms   median {:>8.1} ms",
+            script.file_stem().unwrap().to_string_lossy(),
+            times[0].as_secs_f64() * 1000.0,
+            times[RUNS / 2].as_secs_f64() * 1000.0,
+        );
+    }
+}
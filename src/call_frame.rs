@@ -7,6 +7,7 @@ pub struct CallFrame {
     stack_base: NonNull<Value>,
     stack_base_offset: usize,
     stack_top_pos: usize,
+    missing_args: Vec<u8>, // parameter slots left to their default value by the caller
 }
 
 impl CallFrame {
@@ -16,7 +17,8 @@ impl CallFrame {
             ip: 0,
             stack_base,
             stack_base_offset,
-            stack_top_pos: 0
+            stack_top_pos: 0,
+            missing_args: Vec::new(),
         }
     }
 
@@ -39,8 +41,13 @@ impl CallFrame {
         unsafe { &mut *(self.callalbe_object as *mut ObjectClosure) }
     }
 
+    #[inline(always)]
+    pub fn callable_object(&self) -> *mut Object {
+        self.callalbe_object
+    }
+
     pub fn object_type(&self) -> ObjectType {
-        (unsafe { &*self.callalbe_object} ).obj_type.clone()
+        (unsafe { &*self.callalbe_object} ).obj_type
     }
 
     #[inline(always)]
@@ -69,6 +76,15 @@ impl CallFrame {
         }
     }
 
+    pub fn set_missing_args(&mut self, slots: Vec<u8>) {
+        self.missing_args = slots;
+    }
+
+    #[inline(always)]
+    pub fn is_missing_arg(&self, slot: u8) -> bool {
+        self.missing_args.contains(&slot)
+    }
+
     #[inline(always)]
     pub fn ip(&mut self) -> &mut usize {
         &mut self.ip
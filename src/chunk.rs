@@ -1,7 +1,13 @@
 use strum_macros::{EnumString, Display};
-use crate::value::{Value, ValueArray};
+use crate::value::{make_nil_value, Value, ValueArray};
 use std::mem::size_of;
 use crate::objects::object::GcSize;
+use crate::objects::object_struct::ObjectStructType;
+
+// Per-field flags in the StructType operands.
+pub const STRUCT_FIELD_DEFAULT: u8 = 1;
+pub const STRUCT_FIELD_READONLY: u8 = 2;
+pub const STRUCT_FIELD_PRIVATE: u8 = 4;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
@@ -22,6 +28,7 @@ pub enum OpCode {
     Print,
     Pop,
     DefineGlobal,
+    DefineConstGlobal,
     GetGlobal,
     SetGlobal,
     GetLocal,
@@ -33,17 +40,35 @@ pub enum OpCode {
     Jump,
     Loop,
     Call,
+    CallNamed,
+    ArgDefault,
     Closure,
     CloseUpvalue,
     Return,
     ImplementTrait,
     ImplRegister,
+    IsTrait,
     StructType,
     StructInstantiate,
     StructInstantiateStack,
     GetField,
     SetField,
     Invoke,
+    SuperInvoke,
+    BuildList,
+    BuildMap,
+    BuildTuple,
+    GetIndex,
+    GetTupleItem,
+    SetIndex,
+    ForIter,
+    BuildRange,
+    EnumType,
+    MatchVariant,
+    MatchFail,
+    DestructureStruct,
+    DestructureList,
+    DestructureTuple,
     //Unknown(u8),
 }
 
@@ -66,6 +91,7 @@ const OPCODE_ARRAY: [Option<OpCode>; 256] = {
     arr[OpCode::Print as u8 as usize] = Some(OpCode::Print);
     arr[OpCode::Pop as u8 as usize] = Some(OpCode::Pop);
     arr[OpCode::DefineGlobal as u8 as usize] = Some(OpCode::DefineGlobal);
+    arr[OpCode::DefineConstGlobal as u8 as usize] = Some(OpCode::DefineConstGlobal);
     arr[OpCode::GetGlobal as u8 as usize] = Some(OpCode::GetGlobal);
     arr[OpCode::SetGlobal as u8 as usize] = Some(OpCode::SetGlobal);
     arr[OpCode::GetLocal as u8 as usize] = Some(OpCode::GetLocal);
@@ -77,17 +103,35 @@ const OPCODE_ARRAY: [Option<OpCode>; 256] = {
     arr[OpCode::Jump as u8 as usize] = Some(OpCode::Jump);
     arr[OpCode::Loop as u8 as usize] = Some(OpCode::Loop);
     arr[OpCode::Call as u8 as usize] = Some(OpCode::Call);
+    arr[OpCode::CallNamed as u8 as usize] = Some(OpCode::CallNamed);
+    arr[OpCode::ArgDefault as u8 as usize] = Some(OpCode::ArgDefault);
     arr[OpCode::Closure as u8 as usize] = Some(OpCode::Closure);
     arr[OpCode::CloseUpvalue as u8 as usize] = Some(OpCode::CloseUpvalue);
     arr[OpCode::Return as u8 as usize] = Some(OpCode::Return);
     arr[OpCode::ImplementTrait as u8 as usize] = Some(OpCode::ImplementTrait);
     arr[OpCode::ImplRegister as u8 as usize] = Some(OpCode::ImplRegister);
+    arr[OpCode::IsTrait as u8 as usize] = Some(OpCode::IsTrait);
     arr[OpCode::StructType as u8 as usize] = Some(OpCode::StructType);
     arr[OpCode::StructInstantiate as u8 as usize] = Some(OpCode::StructInstantiate);
     arr[OpCode::StructInstantiateStack as u8 as usize] = Some(OpCode::StructInstantiateStack);
     arr[OpCode::GetField as u8 as usize] = Some(OpCode::GetField);
     arr[OpCode::SetField as u8 as usize] = Some(OpCode::SetField);
     arr[OpCode::Invoke as u8 as usize] = Some(OpCode::Invoke);
+    arr[OpCode::SuperInvoke as u8 as usize] = Some(OpCode::SuperInvoke);
+    arr[OpCode::BuildList as u8 as usize] = Some(OpCode::BuildList);
+    arr[OpCode::BuildMap as u8 as usize] = Some(OpCode::BuildMap);
+    arr[OpCode::BuildTuple as u8 as usize] = Some(OpCode::BuildTuple);
+    arr[OpCode::GetIndex as u8 as usize] = Some(OpCode::GetIndex);
+    arr[OpCode::GetTupleItem as u8 as usize] = Some(OpCode::GetTupleItem);
+    arr[OpCode::SetIndex as u8 as usize] = Some(OpCode::SetIndex);
+    arr[OpCode::ForIter as u8 as usize] = Some(OpCode::ForIter);
+    arr[OpCode::BuildRange as u8 as usize] = Some(OpCode::BuildRange);
+    arr[OpCode::EnumType as u8 as usize] = Some(OpCode::EnumType);
+    arr[OpCode::MatchVariant as u8 as usize] = Some(OpCode::MatchVariant);
+    arr[OpCode::MatchFail as u8 as usize] = Some(OpCode::MatchFail);
+    arr[OpCode::DestructureStruct as u8 as usize] = Some(OpCode::DestructureStruct);
+    arr[OpCode::DestructureList as u8 as usize] = Some(OpCode::DestructureList);
+    arr[OpCode::DestructureTuple as u8 as usize] = Some(OpCode::DestructureTuple);
     arr
 };
 
@@ -112,16 +156,34 @@ impl OpCode {
     }
 }
 
+// Inline cache of one GetField, SetField or Invoke instruction: the struct type it last saw, with the
+// field slot or method resolved for it. A method entry only holds while the VM's cache epoch, which
+// moves whenever an impl is registered, still equals `epoch`.
+#[derive(Clone, Copy)]
+pub struct InlineCache {
+    pub struct_type: *const ObjectStructType,
+    pub slot: usize,
+    pub method: Value,
+    pub epoch: u64,
+}
+
+impl Default for InlineCache {
+    fn default() -> Self {
+        InlineCache { struct_type: std::ptr::null(), slot: 0, method: make_nil_value(), epoch: 0 }
+    }
+}
+
 #[derive(Clone)]
 pub struct Chunk {
     code: Vec<u8>,
     lines: Vec<usize>,
     constants: ValueArray,
+    inline_caches: Vec<InlineCache>,
 }
 
 impl Chunk {
     pub fn new() -> Self {
-        Chunk{code:vec![], constants:vec![], lines:vec![]}
+        Chunk{code:vec![], constants:vec![], lines:vec![], inline_caches:vec![]}
     }
 
     pub fn write(&mut self, byte: u8, line: usize) {
@@ -158,6 +220,19 @@ impl Chunk {
         self.code.len()
     }
 
+    pub fn add_inline_cache(&mut self) -> usize {
+        self.inline_caches.push(InlineCache::default());
+        self.inline_caches.len() - 1
+    }
+
+    pub fn inline_cache(&self, index: usize) -> &InlineCache {
+        &self.inline_caches[index]
+    }
+
+    pub fn inline_cache_mut(&mut self, index: usize) -> &mut InlineCache {
+        &mut self.inline_caches[index]
+    }
+
     // For garbage collection - iterate over constants
     pub fn iter_constants(&self) -> impl Iterator<Item = &Value> {
         self.constants.iter()
@@ -172,7 +247,8 @@ impl GcSize for Chunk {
         let code_bytes = self.code.capacity() * size_of::<u8>();
         let line_bytes = self.lines.capacity() * size_of::<usize>();
         let constants_bytes = self.constants.capacity() * size_of::<Value>();
-        self.shallow_size() + code_bytes + line_bytes + constants_bytes
+        let cache_bytes = self.inline_caches.capacity() * size_of::<InlineCache>();
+        self.shallow_size() + code_bytes + line_bytes + constants_bytes + cache_bytes
     }
 }
 
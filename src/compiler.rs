@@ -1,5 +1,5 @@
-use crate::{chunk::{self, Chunk, OpCode}, objects::{object_function::{ObjectFunction}, object_manager::{ObjectManager}}, scanner::{Scanner, Token, TokenType}, table::Table, value::{*}};
-use std::{f64, io::Write, mem};
+use crate::{chunk::{self, Chunk, OpCode}, objects::{object_function::{ObjectFunction}, object_manager::{ObjectManager}}, scanner::{Scanner, Token, TokenType}, globals::Globals, table::Table, value::{*}};
+use std::{collections::{HashMap, HashSet}, f64, io::Write, mem};
 
 pub struct Parser<'a> {
     current: Token<'a>,
@@ -10,17 +10,49 @@ pub struct Parser<'a> {
     compilers: Vec<Compiler<'a>>,
     object_manager: &'a mut ObjectManager,
     intern_strings: &'a mut Table,
-    // Tracks whether the most recently compiled top-level expression (since last expression() call)
-    // produced a stack-allocated struct literal result. Used to forbid returning it directly.
-    last_expr_stack_struct: bool,
+    // The VM's global slots; names seen here get the slot the global opcodes will use.
+    globals: &'a mut Globals,
+    // One frame per expression being parsed, holding the escape groups its value may belong to.
+    value_frames: Vec<ValueFrame>,
     // When true, force struct literals to emit heap allocation opcode (used by 'new').
     force_heap_struct_literal: bool,
+    // Enum name -> (variant name, payload arity) for enums declared so far; used to check match arms.
+    enum_variants: HashMap<String, Vec<(String, usize)>>,
+    // Global constants declared so far, so reassignments can be rejected before running.
+    constant_globals: HashSet<String>,
+    // Trait of the impl whose methods are being compiled; `super.method()` resolves against its supertraits.
+    impl_trait: Option<Token<'a>>,
 }
 
 struct Local<'a> {
     name: Token<'a>,
     depth: i32,
     captured: bool,
+    constant: bool,
+    // Escape group of the values stored in the variable.
+    group: usize,
+}
+
+// Struct literals are compiled to allocate on the stack unless their value can outlive the frame. Values
+// that can end up in the same place (a literal and the locals it is stored in) share a group; once any
+// of them escapes -- returned, captured, stored in a global, heap field or collection, or passed to a
+// call -- the group's literals are patched to allocate on the heap. The values held in a group's fields
+// form a group of their own, which escapes along with it.
+struct EscapeGroup {
+    parent: usize,
+    // Offsets of the group's StructInstantiateStack instructions.
+    sites: Vec<usize>,
+    escaped: bool,
+    fields: Option<usize>,
+}
+
+// The escape groups of the expression being parsed (`result`) and, while an infix operator is parsed,
+// of its left operand (`left`). Whatever the operator doesn't take from `left` escapes.
+#[derive(Default)]
+struct ValueFrame {
+    compiler: usize,
+    left: Vec<usize>,
+    result: Vec<usize>,
 }
 
 #[derive(Clone)]
@@ -29,9 +61,13 @@ struct Upvalue {
     is_local: bool,
 }
 
+// (method name constant, function constant, upvalue descriptors) for one impl method.
+type ImplMethodEntry = (u8, u8, Vec<(bool, u8)>);
+
 #[derive(PartialEq)]
 enum FunctionType {
     Function,
+    Lambda,
     Script,
 }
 
@@ -41,6 +77,7 @@ struct Compiler<'a> {
     locals: Vec<Local<'a>>,
     upvalues: Vec<Upvalue>,
     scope_depth: i32,
+    escape_groups: Vec<EscapeGroup>,
 }
 
 impl<'a> Compiler<'a> {
@@ -50,9 +87,73 @@ impl<'a> Compiler<'a> {
             function_type,
             locals: vec![],
             upvalues: vec![],
-            scope_depth: 0
+            scope_depth: 0,
+            escape_groups: vec![],
+        }
+    }
+
+    fn new_escape_group(&mut self) -> usize {
+        let group = self.escape_groups.len();
+        self.escape_groups.push(EscapeGroup { parent: group, sites: Vec::new(), escaped: false, fields: None });
+        group
+    }
+
+    // The group of the values stored in the fields of the group's structs.
+    fn fields_group(&mut self, group: usize) -> usize {
+        let group = self.find_escape_group(group);
+        if let Some(fields) = self.escape_groups[group].fields {
+            return fields;
+        }
+        let fields = self.new_escape_group();
+        self.escape_groups[group].fields = Some(fields);
+        if self.escape_groups[group].escaped {
+            self.escape(fields);
         }
-    }    
+        fields
+    }
+
+    fn find_escape_group(&mut self, mut group: usize) -> usize {
+        while self.escape_groups[group].parent != group {
+            let parent = self.escape_groups[group].parent;
+            self.escape_groups[group].parent = self.escape_groups[parent].parent;
+            group = parent;
+        }
+        group
+    }
+
+    // Turn the group's stack literals into heap ones; later sites joining the group start on the heap.
+    fn escape(&mut self, group: usize) {
+        let group = self.find_escape_group(group);
+        if self.escape_groups[group].escaped {
+            return;
+        }
+        self.escape_groups[group].escaped = true;
+        for site in mem::take(&mut self.escape_groups[group].sites) {
+            unsafe { (*self.function).chunk.write_by_offset(site, OpCode::StructInstantiate.to_byte()); }
+        }
+        if let Some(fields) = self.escape_groups[group].fields {
+            self.escape(fields);
+        }
+    }
+
+    fn join(&mut self, group: usize, other: usize) {
+        let (group, other) = (self.find_escape_group(group), self.find_escape_group(other));
+        if group == other {
+            return;
+        }
+        self.escape_groups[other].parent = group;
+        let sites = mem::take(&mut self.escape_groups[other].sites);
+        self.escape_groups[group].sites.extend(sites);
+        let escaped = mem::replace(&mut self.escape_groups[group].escaped, false) || self.escape_groups[other].escaped;
+        match (self.escape_groups[group].fields, self.escape_groups[other].fields) {
+            (Some(fields), Some(other_fields)) => self.join(fields, other_fields),
+            (None, Some(other_fields)) => self.escape_groups[group].fields = Some(other_fields),
+            _ => {}
+        }
+        if escaped {
+            self.escape(group);
+        }
+    }
 }
 
 #[repr(u8)]
@@ -60,10 +161,12 @@ impl<'a> Compiler<'a> {
 enum Precedence {
     None,
     Assignment, // =
+    Conditional, // ?:
     Or,         // ||
     And,        // &&
     Equality,   // ==
     Comparison, // < > <= >=
+    Range,      // .. ..=
     Term,       // + -
     Factor,     // * /
     Unary,      // ! -
@@ -76,15 +179,17 @@ impl From<u8> for Precedence {
         match value {
             0 => Precedence::None,
             1 => Precedence::Assignment,
-            2 => Precedence::Or,
-            3 => Precedence::And,
-            4 => Precedence::Equality,
-            5 => Precedence::Comparison,
-            6 => Precedence::Term,
-            7 => Precedence::Factor,
-            8 => Precedence::Unary,
-            9 => Precedence::Call,
-            10 => Precedence::Primary,
+            2 => Precedence::Conditional,
+            3 => Precedence::Or,
+            4 => Precedence::And,
+            5 => Precedence::Equality,
+            6 => Precedence::Comparison,
+            7 => Precedence::Range,
+            8 => Precedence::Term,
+            9 => Precedence::Factor,
+            10 => Precedence::Unary,
+            11 => Precedence::Call,
+            12 => Precedence::Primary,
             _ => unreachable!("Invalid precedence value: {}", value),
         }
     }
@@ -200,6 +305,15 @@ const RULES: [ParseRule; TokenType::Eof as usize + 1] = {
         None, 
         Some(|parser, _can_assign| parser.binary()), 
         Precedence::Comparison);
+    rules[TokenType::Super as usize] = ParseRule::new(
+        Some(|parser, _can_assign| parser.super_call()),
+        None,
+        Precedence::None);
+    // x is Trait: whether x's type has an impl of the trait
+    rules[TokenType::Is as usize] = ParseRule::new(
+        None,
+        Some(|parser, _can_assign| parser.is_trait()),
+        Precedence::Comparison);
 
     rules[TokenType::Identifier as usize] = ParseRule::new(
         Some(|parser, can_assign| parser.variable(can_assign)), 
@@ -224,11 +338,50 @@ const RULES: [ParseRule; TokenType::Eof as usize + 1] = {
         Some(|parser, can_assign| parser.or(can_assign)), 
         Precedence::Or);
 
+    // anonymous functions: fn (params) { body } and |params| expression
+    rules[TokenType::Fn as usize] = ParseRule::new(
+        Some(|parser, _can_assign| parser.lambda()),
+        None,
+        Precedence::None);
+    rules[TokenType::Pipe as usize] = ParseRule::new(
+        Some(|parser, _can_assign| parser.short_lambda()),
+        None,
+        Precedence::None);
+
+    // collection literals: [a, b] and {"key": value}; postfix [index] for element access
+    rules[TokenType::LeftBracket as usize] = ParseRule::new(
+        Some(|parser, _can_assign| parser.list_literal()),
+        Some(|parser, can_assign| parser.index(can_assign)),
+        Precedence::Call);
+    rules[TokenType::LeftBrace as usize] = ParseRule::new(
+        Some(|parser, _can_assign| parser.map_literal()),
+        None,
+        Precedence::None);
+
+    rules[TokenType::DotDot as usize] = ParseRule::new(
+        None,
+        Some(|parser, _can_assign| parser.range()),
+        Precedence::Range);
+    rules[TokenType::DotDotEqual as usize] = ParseRule::new(
+        None,
+        Some(|parser, _can_assign| parser.range()),
+        Precedence::Range);
+
+    rules[TokenType::Question as usize] = ParseRule::new(
+        None,
+        Some(|parser, _can_assign| parser.conditional()),
+        Precedence::Conditional);
+    // 'if' in expression position yields the value of the taken branch
+    rules[TokenType::If as usize] = ParseRule::new(
+        Some(|parser, _can_assign| parser.if_expression()),
+        None,
+        Precedence::None);
+
     rules
 };
 
 impl<'a> Parser<'a> {
-    pub fn new(object_manager: &'a mut ObjectManager, intern_strings: &'a mut Table) -> Self {
+    pub fn new(object_manager: &'a mut ObjectManager, intern_strings: &'a mut Table, globals: &'a mut Globals) -> Self {
         let mut parser = Parser{
             current: Token{token_type: TokenType::Eof, value: "", line: 0},
             previous: Token{token_type: TokenType::Eof, value: "", line: 0},
@@ -238,8 +391,12 @@ impl<'a> Parser<'a> {
             compilers: vec![],
             object_manager,
             intern_strings,
-            last_expr_stack_struct: false,
+            globals,
+            value_frames: Vec::new(),
             force_heap_struct_literal: false,
+            enum_variants: HashMap::new(),
+            constant_globals: HashSet::new(),
+            impl_trait: None,
         };
         parser.init_compiler(FunctionType::Script);
         parser
@@ -263,7 +420,7 @@ impl<'a> Parser<'a> {
             return None;
         }
 
-        return self.end_compiler();
+        self.end_compiler()
     }
 
     fn specific_compiler(&self, compiler_index: usize) -> &Compiler<'a> {
@@ -290,11 +447,11 @@ impl<'a> Parser<'a> {
         unsafe { &mut *self.current_compiler_mut().function }
     }
 
-    fn current_chunk(&self) -> &Box<Chunk> {
+    fn current_chunk(&self) -> &Chunk {
         &self.current_function().chunk
     }
 
-    fn current_chunk_mut(&mut self) -> &mut Box<Chunk> {
+    fn current_chunk_mut(&mut self) -> &mut Chunk {
         &mut self.current_function_mut().chunk
         //&mut (*self.compiler.function.get_mut().chunk.as_mut())
         //self.chunk.as_mut().expect("Chunk is None")
@@ -337,6 +494,14 @@ impl<'a> Parser<'a> {
         self.current.token_type == token_type
     }
 
+    // One extra token of lookahead: scan past `current` on a copy of the scanner.
+    fn check_next(&self, token_type: TokenType) -> bool {
+        match &self.scanner {
+            Some(scanner) => scanner.as_ref().clone().scan_token().token_type == token_type,
+            None => false,
+        }
+    }
+
     fn consume(&mut self, token_type: TokenType, message: &'a str) {
         if self.current.token_type == token_type {
             self.advance();
@@ -375,11 +540,19 @@ impl<'a> Parser<'a> {
         // When compiling a function declaration, we call init_compiler() right after
         // we parse the function’s name. That means we can grab the name right then
         // from the previous token.
-        if compiler.function_type != FunctionType::Script {
-            unsafe { (*compiler.function).name = self.previous.value.to_string(); }
+        // Anonymous functions have no name token; give them a fixed display name instead.
+        match compiler.function_type {
+            FunctionType::Function => unsafe { (*compiler.function).name = self.previous.value.to_string(); },
+            FunctionType::Lambda => unsafe { (*compiler.function).name = "lambda".to_string(); },
+            FunctionType::Script => {}
+        }
+        // Functions nested in an impl method keep its access to the type's private fields.
+        if let Some(enclosing) = self.compilers.last() {
+            unsafe { (*compiler.function).method_of = (*enclosing.function).method_of.clone(); }
         }
 
         // the compiler sets aside stack slot zero that stores the function being called
+        let group = compiler.new_escape_group();
         compiler.locals.push(Local {
             name: Token {
                 token_type: TokenType::Eof,
@@ -387,7 +560,9 @@ impl<'a> Parser<'a> {
                 line: 0,
             }, 
             depth: 0,
-            captured: false });
+            captured: false,
+            constant: false,
+            group });
         self.compilers.push(compiler);
     }
 
@@ -416,7 +591,7 @@ impl<'a> Parser<'a> {
         }
 
         let constant_index = self.current_chunk_mut().add_constant(value);
-        if constant_index > u8::max_value().into() {
+        if constant_index > u8::MAX.into() {
             self.error("Too many constants in one chunk.");
             return 0;
         }
@@ -424,10 +599,7 @@ impl<'a> Parser<'a> {
     }
 
     fn number(&mut self) {
-        let value = match self.previous.value.parse::<f64>() {
-            Ok(num) => num,
-            Err(_) => 0.0,
-        };
+        let value = self.previous.value.parse::<f64>().unwrap_or(0.0);
         self.emit_constant(make_numer_value(value));
     }
 
@@ -449,8 +621,8 @@ impl<'a> Parser<'a> {
         // }
         let literal = &self.previous.value[1..self.previous.value.len() - 1];
         let value = make_string_value(
-            &mut self.object_manager,
-            &mut self.intern_strings,
+            self.object_manager,
+            self.intern_strings,
             literal
         );
         self.emit_constant(value);
@@ -463,8 +635,12 @@ impl<'a> Parser<'a> {
             self.impl_declaration();
         } else if self.match_token(TokenType::Struct) {
             self.struct_declaration();
+        } else if self.match_token(TokenType::Enum) {
+            self.enum_declaration();
         } else if self.match_token(TokenType::Var) {
-            self.variable_declaration();
+            self.variable_declaration(false);
+        } else if self.match_token(TokenType::Const) || self.match_token(TokenType::Let) {
+            self.variable_declaration(true);
         } else if self.match_token(TokenType::Fn) {
             self.function_declaration();
         } else {
@@ -476,27 +652,152 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn variable_declaration(&mut self) {
+    fn variable_declaration(&mut self, constant: bool) {
+        if self.check(TokenType::LeftBracket) {
+            self.list_destructuring(constant);
+            return;
+        }
+        if self.check(TokenType::LeftParen) {
+            self.tuple_destructuring(constant);
+            return;
+        }
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::LeftBrace) {
+            self.struct_destructuring(constant);
+            return;
+        }
         let global = self.parse_variable("Expect variable name.");
+        if constant { self.mark_constant(); }
 
         if self.match_token(TokenType::Equal) {
-            self.expression();
+            let value = self.expression_value();
+            if self.current_compiler().scope_depth > 0 {
+                let group = self.current_locals().last().unwrap().group;
+                self.join_value(group, &value);
+            } else {
+                self.escape_value(&value);
+            }
+        } else if constant {
+            self.error(format!("Constant '{}' must be initialized.", self.previous.value).as_str());
         } else {
             self.emit_byte(OpCode::Nil.to_byte());
         }
         self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.");
 
-        self.define_variable(global);
+        if constant {
+            self.define_constant(global);
+        } else {
+            self.define_variable(global);
+        }
+    }
+
+    fn struct_destructuring(&mut self, constant: bool) {
+        // var TypeName { field ( , field )* ,? } = expression ;
+        self.consume(TokenType::Identifier, "Expect struct name in pattern.");
+        let type_name = self.previous.clone();
+        self.consume(TokenType::LeftBrace, "Expect '{' after struct name in pattern.");
+        let mut fields: Vec<Token<'a>> = Vec::new();
+        let mut globals: Vec<u16> = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            globals.push(self.parse_variable("Expect field name in struct pattern."));
+            if constant { self.mark_constant(); }
+            fields.push(self.previous.clone());
+            if !self.match_token(TokenType::Comma) { break; }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after struct pattern.");
+        if fields.is_empty() { self.error("Struct pattern must bind at least one field."); }
+        self.consume(TokenType::Equal, "Expect '=' after destructuring pattern.");
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.");
+
+        // DestructureStruct <type_name> <count> <field_name>* pops the value and pushes the fields in order.
+        let type_index = self.identifier_constant(type_name);
+        self.emit_bytes(OpCode::DestructureStruct.to_byte(), type_index);
+        self.emit_byte(fields.len() as u8);
+        for field in fields {
+            let field_index = self.identifier_constant(field);
+            self.emit_byte(field_index);
+        }
+        self.define_pattern_variables(&globals, constant);
+    }
+
+    fn list_destructuring(&mut self, constant: bool) {
+        // var [ name ( , name )* ( , ...rest )? ,? ] = expression ;
+        self.consume(TokenType::LeftBracket, "Expect '[' to start list pattern.");
+        let mut globals: Vec<u16> = Vec::new();
+        let mut has_rest = false;
+        while !self.check(TokenType::RightBracket) && !self.check(TokenType::Eof) {
+            if self.match_token(TokenType::DotDotDot) {
+                globals.push(self.parse_variable("Expect name after '...' in list pattern."));
+                if constant { self.mark_constant(); }
+                has_rest = true;
+                if !self.check(TokenType::RightBracket) {
+                    self.error_at_current("Rest binding must be last in a list pattern.");
+                }
+                break;
+            }
+            globals.push(self.parse_variable("Expect name in list pattern."));
+            if constant { self.mark_constant(); }
+            if !self.match_token(TokenType::Comma) { break; }
+        }
+        self.consume(TokenType::RightBracket, "Expect ']' after list pattern.");
+        if globals.is_empty() { self.error("List pattern must bind at least one name."); }
+        self.consume(TokenType::Equal, "Expect '=' after destructuring pattern.");
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.");
+
+        // DestructureList <count> <has_rest> pops the list and pushes the elements (and rest list) in order.
+        let count = globals.len() - has_rest as usize;
+        self.emit_bytes(OpCode::DestructureList.to_byte(), count as u8);
+        self.emit_byte(has_rest as u8);
+        self.define_pattern_variables(&globals, constant);
+    }
+
+    fn tuple_destructuring(&mut self, constant: bool) {
+        // var ( name ( , name )* ,? ) = expression ;
+        self.consume(TokenType::LeftParen, "Expect '(' to start tuple pattern.");
+        let mut globals: Vec<u16> = Vec::new();
+        while !self.check(TokenType::RightParen) && !self.check(TokenType::Eof) {
+            globals.push(self.parse_variable("Expect name in tuple pattern."));
+            if constant { self.mark_constant(); }
+            if !self.match_token(TokenType::Comma) { break; }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after tuple pattern.");
+        if globals.is_empty() { self.error("Tuple pattern must bind at least one name."); }
+        if globals.len() > u8::MAX as usize { self.error("Too many names in tuple pattern."); return; }
+        self.consume(TokenType::Equal, "Expect '=' after destructuring pattern.");
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.");
+
+        // DestructureTuple <count> pops a tuple of exactly count elements and pushes them in order.
+        self.emit_bytes(OpCode::DestructureTuple.to_byte(), globals.len() as u8);
+        self.define_pattern_variables(&globals, constant);
+    }
+
+    fn define_pattern_variables(&mut self, globals: &[u16], constant: bool) {
+        // The bound values sit on the stack in pattern order.
+        let scope_depth = self.current_compiler().scope_depth;
+        if scope_depth > 0 {
+            let locals = self.current_locals_mut();
+            let first = locals.len().saturating_sub(globals.len());
+            for local in locals[first..].iter_mut() {
+                local.depth = scope_depth;
+            }
+            return;
+        }
+        let define = if constant { OpCode::DefineConstGlobal } else { OpCode::DefineGlobal };
+        for global in globals.iter().rev() {
+            self.emit_global(define, *global);
+        }
     }
 
-    fn parse_variable(&mut self, message: &'a str) -> u8 {
+    fn parse_variable(&mut self, message: &'a str) -> u16 {
         self.consume(TokenType::Identifier, message);
 
         self.declare_variable();
         if self.current_compiler().scope_depth > 0 {
             return 0;
         }
-        return self.identifier_constant(self.previous.clone());
+        self.global_slot(self.previous.value)
     }
 
     fn declare_variable(&mut self) {
@@ -526,7 +827,7 @@ impl<'a> Parser<'a> {
     }
 
     fn add_local(&mut self, variable_name: Token<'a>) {
-        if self.current_locals().len() >= u8::max_value().into() {
+        if self.current_locals().len() >= u8::MAX.into() {
             self.error("Too many local variables in function.");
             return;
         }
@@ -534,7 +835,16 @@ impl<'a> Parser<'a> {
         // Set 'depth' to -1 in order to mark this variable uninitialized. If the variable
         // declaration expression has an initializer that is parsed correctly, the 'depth'
         // will be set to the scope depth of 'compiler'
-        self.current_locals_mut().push(Local { name: variable_name, depth: -1, captured: false });
+        let group = self.current_compiler_mut().new_escape_group();
+        self.current_locals_mut().push(Local { name: variable_name, depth: -1, captured: false, constant: false, group });
+    }
+
+    // Locals the compiler needs for its own bookkeeping. The names can't be written as
+    // identifiers, so user code can never resolve them.
+    fn add_hidden_local(&mut self, name: &'static str) {
+        let line = self.previous.line;
+        self.add_local(Token { token_type: TokenType::Identifier, value: name, line });
+        self.mark_initialized();
     }
 
     // fn compiler_ptr(&mut self) -> *mut Compiler<'a> {
@@ -553,24 +863,88 @@ impl<'a> Parser<'a> {
 
         self.begin_scope();
         self.consume(TokenType::LeftParen, "Expect '(' after function name.");
-        if !self.check(TokenType::RightParen) {
+        self.parameter_list(TokenType::RightParen);
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.");
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body.");
+        self.block();
+
+        self.emit_closure();
+    }
+
+    fn parameter_list(&mut self, terminator: TokenType) {
+        // param ( , param )* where param is name, name = default, or a final ...name
+        let mut seen_default = false;
+        let arity = self.current_function().arity;
+        self.current_function_mut().min_arity = arity;
+        if !self.check(terminator) {
             loop {
+                if self.match_token(TokenType::DotDotDot) {
+                    let constant = self.parse_variable("Expect parameter name after '...'.");
+                    self.define_variable(constant);
+                    self.current_function_mut().has_rest = true;
+                    if !self.check(terminator) {
+                        self.error_at_current("Rest parameter must be the last parameter.");
+                    }
+                    break;
+                }
                 self.current_function_mut().arity += 1;
-                if self.current_function_mut().arity >= 255 {
+                if self.current_function_mut().arity == 255 {
                     self.error("Can't have more than 255 parameters.");
                 }
                 let constant = self.parse_variable("Expect parameter name.");
+                let name = self.previous.value.to_string();
+                self.current_function_mut().param_names.push(name);
                 self.define_variable(constant);
 
+                if self.match_token(TokenType::Equal) {
+                    seen_default = true;
+                    self.default_parameter_value();
+                } else if seen_default {
+                    self.error("Parameter without a default can't follow one with a default.");
+                } else {
+                    let arity = self.current_function().arity;
+                    self.current_function_mut().min_arity = arity;
+                }
+
                 if !self.match_token(TokenType::Comma) {
                     break;
                 }
             }
         }
-        self.consume(TokenType::RightParen, "Expect ')' after parameters.");
-        self.consume(TokenType::LeftBrace, "Expect '{' before function body.");
-        self.block();
+    }
 
+    fn default_parameter_value(&mut self) {
+        // ArgDefault <slot> <offset> skips the default when the caller supplied the argument, so
+        // the expression runs at call time and can refer to earlier parameters.
+        let slot = (self.current_locals().len() - 1) as u8;
+        self.emit_bytes(OpCode::ArgDefault.to_byte(), slot);
+        self.emit_bytes(0xff, 0xff);
+        let skip_offset_operand = (self.current_chunk().len() - 2) as u16;
+        self.expression();
+        self.emit_bytes(OpCode::SetLocal.to_byte(), slot);
+        self.emit_byte(OpCode::Pop.to_byte());
+        self.patch_jump_offset(skip_offset_operand);
+    }
+
+    fn lambda(&mut self) {
+        // fn ( params? ) { block } in expression position
+        self.function(FunctionType::Lambda);
+    }
+
+    fn short_lambda(&mut self) {
+        // | params? | expression -- the body expression is the return value
+        self.init_compiler(FunctionType::Lambda);
+        self.begin_scope();
+        self.parameter_list(TokenType::Pipe);
+        self.consume(TokenType::Pipe, "Expect '|' after lambda parameters.");
+        self.expression();
+        self.emit_byte(OpCode::Return.to_byte());
+
+        self.emit_closure();
+    }
+
+    // Finish the function being compiled and emit a Closure for it in the enclosing chunk.
+    fn emit_closure(&mut self) {
         let upvalues = self.current_compiler().upvalues.clone();
 
         let object_function = self.end_compiler().expect("Unexpected function object.");
@@ -585,12 +959,25 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn argument_list(&mut self) -> u8 {
+    // Returns the argument count and the names of the trailing named arguments (name: value).
+    fn argument_list(&mut self) -> (u8, Vec<Token<'a>>) {
         let mut argument_count = 0;
+        let mut names: Vec<Token<'a>> = Vec::new();
         if !self.check(TokenType::RightParen) {
             loop {
+                if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+                    self.advance();
+                    let name = self.previous.clone();
+                    if names.iter().any(|n| n.value == name.value) {
+                        self.error("Duplicate named argument.");
+                    }
+                    names.push(name);
+                    self.advance(); // ':'
+                } else if !names.is_empty() {
+                    self.error_at_current("Positional argument can't follow named arguments.");
+                }
                 self.expression();
-                if argument_count >= 255 {
+                if argument_count == 255 {
                     self.error("Can't have more than 255 arguments.");
                 }
                 argument_count += 1;
@@ -601,22 +988,57 @@ impl<'a> Parser<'a> {
             }
         }
         self.consume(TokenType::RightParen, "Expect ')' after arguments.");
-        argument_count
+        (argument_count, names)
     }
 
     fn identifier_constant(&mut self, previous: Token) -> u8 {
-        let value = make_string_value(&mut self.object_manager, &mut self.intern_strings, previous.value);
+        let value = make_string_value(self.object_manager, self.intern_strings, previous.value);
         self.make_constant(value)
     }
 
-    fn define_variable(&mut self, global: u8) {
+    // Slot of the global `name` in the VM's globals, assigned on first use.
+    fn global_slot(&mut self, name: &str) -> u16 {
+        let slot = self.globals.slot(name);
+        if slot > u16::MAX as usize {
+            self.error("Too many global variables.");
+            return 0;
+        }
+        slot as u16
+    }
+
+    fn emit_global(&mut self, opcode: OpCode, slot: u16) {
+        self.emit_byte(opcode.to_byte());
+        self.emit_bytes((slot >> 8) as u8, slot as u8);
+    }
+
+    fn define_variable(&mut self, global: u16) {
         // > 0 means a local variable
         if self.current_compiler().scope_depth > 0 {
             self.mark_initialized();
             return;
         }
         
-        self.emit_bytes(OpCode::DefineGlobal.to_byte(), global);
+        self.emit_global(OpCode::DefineGlobal, global);
+    }
+
+    fn define_constant(&mut self, global: u16) {
+        if self.current_compiler().scope_depth > 0 {
+            self.mark_initialized();
+            return;
+        }
+
+        self.emit_global(OpCode::DefineConstGlobal, global);
+    }
+
+    // Flags the variable just parsed by parse_variable as immutable.
+    fn mark_constant(&mut self) {
+        if self.current_compiler().scope_depth > 0 {
+            if let Some(local) = self.current_locals_mut().last_mut() {
+                local.constant = true;
+            }
+            return;
+        }
+        self.constant_globals.insert(self.previous.value.to_string());
     }
 
     fn mark_initialized(&mut self) {
@@ -650,7 +1072,6 @@ impl<'a> Parser<'a> {
         self.force_heap_struct_literal = true; // ensure heap allocation
         self.struct_literal(type_name);
         self.force_heap_struct_literal = prev_force;
-        self.last_expr_stack_struct = false; // result is heap-based
     }
 
     fn named_variable(&mut self, name: Token, can_assign: bool) {
@@ -658,31 +1079,52 @@ impl<'a> Parser<'a> {
         let mut opcode_set: u8 = OpCode::SetLocal.to_byte();
         let current_compiler_index = self.compilers.len() - 1;
         let mut index = self.resolve_local(current_compiler_index, &name);
+        let constant;
+        let mut local_group = None;
+        let mut global = None;
         if index == -1 {
             index = self.resolve_upvalue(current_compiler_index, &name);
             if index == -1 { // global variable
-                index = self.identifier_constant(name) as i32;
-                opcode_get = OpCode::GetGlobal.to_byte();
-                opcode_set = OpCode::SetGlobal.to_byte();
+                constant = self.constant_globals.contains(name.value);
+                global = Some(self.global_slot(name.value));
             } else { // upvalue
+                constant = self.is_constant_upvalue(current_compiler_index, &name);
                 opcode_get = OpCode::GetUpvalue.to_byte();
                 opcode_set = OpCode::SetUpvalue.to_byte();
             }
+        } else {
+            constant = self.current_locals()[index as usize].constant;
+            local_group = Some(self.current_locals()[index as usize].group);
         }
 
         if can_assign && self.match_token(TokenType::Equal) {
-            self.expression();
-            self.emit_bytes(opcode_set, index as u8);
+            if constant {
+                self.error_at(&name, format!("Cannot assign to constant '{}'.", name.value).as_str());
+            }
+            let value = self.expression_value();
+            match local_group {
+                Some(group) => self.join_value(group, &value),
+                None => self.escape_value(&value),
+            }
+            match global {
+                Some(slot) => self.emit_global(OpCode::SetGlobal, slot),
+                None => self.emit_bytes(opcode_set, index as u8),
+            }
         } else {
-            self.emit_bytes(opcode_get, index as u8);
+            match global {
+                Some(slot) => self.emit_global(OpCode::GetGlobal, slot),
+                None => self.emit_bytes(opcode_get, index as u8),
+            }
         }
+        // Globals and upvalues never hold stack structs.
+        self.set_value(local_group.into_iter().collect());
     }
 
     fn resolve_local(&mut self, compiler_index: usize, name: &Token) -> i32 {
         let compiler = self.specific_compiler(compiler_index);
         let locals = &compiler.locals;
         for (index, local) in locals.iter().enumerate().rev() {
-            if Self::identifier_equal(&name, &local.name) {
+            if Self::identifier_equal(name, &local.name) {
                 if local.depth == -1 { // it's not fully defined
                     self.error("Can't read local variable in its own initializer.");
                 }
@@ -690,7 +1132,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        return -1;
+        -1
     }
 
     fn identifier_equal(left: &Token, right: &Token) -> bool {
@@ -703,8 +1145,12 @@ impl<'a> Parser<'a> {
         }
         let local = self.resolve_local(compiler_index - 1, name);
         if local != -1 {
-            let local_variable = self.specific_compiler_mut(compiler_index - 1).locals.get_mut(local as usize).unwrap();
+            let enclosing = self.specific_compiler_mut(compiler_index - 1);
+            let local_variable = enclosing.locals.get_mut(local as usize).unwrap();
             local_variable.captured = true;
+            // The captured variable outlives its frame, and so does anything stored in it.
+            let group = local_variable.group;
+            enclosing.escape(group);
             return self.add_upvalue(compiler_index, local, true) as i32;
         }
 
@@ -713,7 +1159,17 @@ impl<'a> Parser<'a> {
             return self.add_upvalue(compiler_index, upvalue, false) as i32;
         }
 
-        return -1;
+        -1
+    }
+
+    // Upvalues resolve to the nearest enclosing local of that name; it decides mutability.
+    fn is_constant_upvalue(&self, compiler_index: usize, name: &Token) -> bool {
+        for compiler in self.compilers[..compiler_index].iter().rev() {
+            if let Some(local) = compiler.locals.iter().rev().find(|local| Self::identifier_equal(name, &local.name)) {
+                return local.constant;
+            }
+        }
+        false
     }
 
     fn add_upvalue(&mut self, compiler_index: usize, local: i32, is_local: bool) -> usize {
@@ -732,15 +1188,68 @@ impl<'a> Parser<'a> {
     fn and(&mut self, _can_assign: bool) {
         let jump_offset_operand = self.emit_jump_bytes(OpCode::JumpIfFalse.to_byte());
         self.emit_byte(OpCode::Pop.to_byte());
-        self.parse_precedence(Precedence::And);
+        let mut value = self.take_left();
+        value.extend(self.parse_precedence(Precedence::And));
         self.patch_jump_offset(jump_offset_operand);
+        self.set_value(value);
     }
 
     fn or(&mut self, _can_assign: bool) {
         let jump_offset_operand = self.emit_jump_bytes(OpCode::JumpIfTrue.to_byte());
         self.emit_byte(OpCode::Pop.to_byte());
-        self.parse_precedence(Precedence::Or);
+        let mut value = self.take_left();
+        value.extend(self.parse_precedence(Precedence::Or));
         self.patch_jump_offset(jump_offset_operand);
+        self.set_value(value);
+    }
+
+    fn conditional(&mut self) {
+        // cond ? then : else -- same jump shape as if/else, but both arms leave a value.
+        let else_jump_offset_operand = self.emit_jump_bytes(OpCode::JumpIfFalse.to_byte());
+        self.emit_byte(OpCode::Pop.to_byte());
+        let mut value = self.parse_precedence(Precedence::Conditional);
+        self.consume(TokenType::Colon, "Expect ':' after then branch of conditional expression.");
+
+        let end_jump_offset_operand = self.emit_jump_bytes(OpCode::Jump.to_byte());
+        self.patch_jump_offset(else_jump_offset_operand);
+        self.emit_byte(OpCode::Pop.to_byte());
+        // Right-associative: a ? b : c ? d : e parses as a ? b : (c ? d : e)
+        value.extend(self.parse_precedence(Precedence::Conditional));
+        self.patch_jump_offset(end_jump_offset_operand);
+        self.set_value(value);
+    }
+
+    fn if_expression(&mut self) {
+        // if ( expression ) { expression } else { expression } -- the else arm is mandatory
+        // so the expression always produces a value. `else if` chains recurse.
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
+        self.inspected_expression();
+        self.consume(TokenType::RightParen, "Expect ')' after condition.");
+
+        let else_jump_offset_operand = self.emit_jump_bytes(OpCode::JumpIfFalse.to_byte());
+        self.emit_byte(OpCode::Pop.to_byte());
+        let mut value = self.expression_block("Expect '{' before if expression branch.");
+
+        let end_jump_offset_operand = self.emit_jump_bytes(OpCode::Jump.to_byte());
+        self.patch_jump_offset(else_jump_offset_operand);
+        self.emit_byte(OpCode::Pop.to_byte());
+
+        self.consume(TokenType::Else, "Expect 'else' branch in if expression.");
+        if self.match_token(TokenType::If) {
+            self.if_expression();
+            value.extend(self.value_frames.last_mut().map(|frame| mem::take(&mut frame.result)).unwrap_or_default());
+        } else {
+            value.extend(self.expression_block("Expect '{' before else expression branch."));
+        }
+        self.patch_jump_offset(end_jump_offset_operand);
+        self.set_value(value);
+    }
+
+    fn expression_block(&mut self, message: &'a str) -> Vec<usize> {
+        self.consume(TokenType::LeftBrace, message);
+        let value = self.expression_value();
+        self.consume(TokenType::RightBrace, "Expect '}' after branch expression.");
+        value
     }
 
     fn statement(&mut self) {
@@ -754,6 +1263,8 @@ impl<'a> Parser<'a> {
             self.while_statement();
         } else if self.match_token(TokenType::For) {
             self.for_statement();
+        } else if self.match_token(TokenType::Match) {
+            self.match_statement();
         } else if self.match_token(TokenType::Return) {
             self.return_statement();
         } else if self.match_token(TokenType::Print) {
@@ -765,7 +1276,7 @@ impl<'a> Parser<'a> {
 
     fn if_statement(&mut self) {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
-        self.expression();
+        self.inspected_expression();
         self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
         let jump_offset_operand = self.emit_jump_bytes(OpCode::JumpIfFalse.to_byte());
@@ -782,6 +1293,15 @@ impl<'a> Parser<'a> {
         self.patch_jump_offset(else_jump_offset_operand);
     }
 
+    // Operand naming a fresh inline cache for the field or method instruction just emitted.
+    fn emit_inline_cache(&mut self) {
+        let index = self.current_chunk_mut().add_inline_cache();
+        if index > u16::MAX as usize {
+            self.error("Too many field accesses and method calls in one function.");
+        }
+        self.emit_bytes((index >> 8) as u8, index as u8);
+    }
+
     fn emit_jump_bytes(&mut self, instruction: u8) -> u16 {
         self.emit_byte(instruction);
         // use two bytes for the jump offset operand
@@ -792,8 +1312,8 @@ impl<'a> Parser<'a> {
 
     fn patch_jump_offset(&mut self, offset: u16) {
         // -2 to adjust for the bytecode for the jump offset itself.
-        let jump_offset = self.current_chunk().len() as u16 - offset - 2;
-        if jump_offset > u16::max_value().into() {
+        let jump_offset = self.current_chunk().len() - offset as usize - 2;
+        if jump_offset > u16::MAX as usize {
             self.error("Too much code to jump over.");
         }
 
@@ -848,17 +1368,13 @@ impl<'a> Parser<'a> {
             self.emit_return();
         } else {
             self.expression();
-            if self.last_expr_stack_struct {
-                // Emit compile error; runtime also has a safety check.
-                self.error("Cannot return stack-allocated struct literal; use 'new' to allocate on heap.");
-            }
             self.consume(TokenType::Semicolon, "Expect ';' after return value.");
             self.emit_byte(OpCode::Return.to_byte());
         }
     }
 
     fn print_statement(&mut self) {
-        self.expression();
+        self.inspected_expression();
         self.consume(TokenType::Semicolon, "Expect ';' after value.");
         self.emit_byte(OpCode::Print.to_byte());
     }
@@ -867,7 +1383,7 @@ impl<'a> Parser<'a> {
         let loop_start = self.current_chunk().len();
 
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'.");
-        self.expression();
+        self.inspected_expression();
         self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
         let jump_offset_operand = self.emit_jump_bytes(OpCode::JumpIfFalse.to_byte());
@@ -885,7 +1401,7 @@ impl<'a> Parser<'a> {
 
         // +2 to skip for the operand of 'Loop' instruction.
         let offset = self.current_chunk().len() - loop_start + 2;
-        if offset > u16::max_value().into() {
+        if offset > u16::MAX.into() {
             self.error("Loop body too large.");
         }
 
@@ -896,10 +1412,16 @@ impl<'a> Parser<'a> {
     fn for_statement(&mut self) {
         self.begin_scope();
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.");
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::In) {
+            self.for_in_statement();
+            self.end_scope();
+            return;
+        }
+
         if self.match_token(TokenType::Semicolon) {
             // no initializer
         } else if self.match_token(TokenType::Var) {
-            self.variable_declaration();
+            self.variable_declaration(false);
         } else {
             self.expression_statement();
         }
@@ -907,7 +1429,7 @@ impl<'a> Parser<'a> {
         let mut loop_start = self.current_chunk().len();
         let mut exit_jump_offset_operand: i32 = -1;
         if !self.match_token(TokenType::Semicolon) { // it has a condition clause
-            self.expression();
+            self.inspected_expression();
             self.consume(TokenType::Semicolon, "Expect ';' after loop condition.");
 
             // Jump out of the loop if the condition is false.
@@ -918,7 +1440,7 @@ impl<'a> Parser<'a> {
         if !self.match_token(TokenType::RightParen) { // it has a increment clause
             let body_jump_offset_operand = self.emit_jump_bytes(OpCode::Jump.to_byte());
             let increment_start = self.current_chunk().len();
-            self.expression();
+            self.inspected_expression();
             self.emit_byte(OpCode::Pop.to_byte());
             self.consume(TokenType::RightParen, "Expect ')' after for clauses.");
 
@@ -937,27 +1459,120 @@ impl<'a> Parser<'a> {
         self.end_scope();
     }
 
-    fn expression_statement(&mut self) {
+    fn for_in_statement(&mut self) {
+        // for ( name in expression ) statement
+        // The iterable and a cursor live in hidden locals for the whole loop. The loop variable
+        // is declared in its own scope each iteration, so closures capture that iteration's value.
+        self.consume(TokenType::Identifier, "Expect loop variable name.");
+        let variable_name = self.previous.clone();
+        self.consume(TokenType::In, "Expect 'in' after loop variable.");
         self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after for-in clause.");
+
+        self.add_hidden_local("(for iterable)");
+        self.emit_constant(make_numer_value(0.0));
+        self.add_hidden_local("(for cursor)");
+        let iterable_slot = (self.current_locals().len() - 2) as u8;
+
+        let loop_start = self.current_chunk().len();
+        // ForIter <iterable_slot> <exit_offset>: pushes the next element or jumps out when exhausted.
+        self.emit_bytes(OpCode::ForIter.to_byte(), iterable_slot);
+        self.emit_bytes(0xff, 0xff);
+        let exit_jump_offset_operand = (self.current_chunk().len() - 2) as u16;
+
+        self.begin_scope();
+        self.add_local(variable_name);
+        self.mark_initialized();
+        self.statement();
+        self.end_scope();
+
+        self.emit_loop(loop_start);
+        self.patch_jump_offset(exit_jump_offset_operand);
+    }
+
+    fn expression_statement(&mut self) {
+        // The value is discarded, so it can't escape.
+        self.expression_value();
         self.consume(TokenType::Semicolon, "Expect ';' after expression.");
         self.emit_byte(OpCode::Pop.to_byte());
     }
 
     fn grouping(&mut self) {
-        self.expression();
-        self.consume(TokenType::RightParen, "Expect ')' after expression.");
+        // '(' expression ')' groups; a comma makes a tuple: '(' ')' | '(' expression ',' ( expression ( ',' expression )* ','? )? ')'
+        if self.match_token(TokenType::RightParen) {
+            self.emit_bytes(OpCode::BuildTuple.to_byte(), 0);
+            return;
+        }
+        let value = self.expression_value();
+        if !self.match_token(TokenType::Comma) {
+            self.consume(TokenType::RightParen, "Expect ')' after expression.");
+            self.set_value(value);
+            return;
+        }
+        self.escape_value(&value);
+        let mut count: usize = 1;
+        while !self.check(TokenType::RightParen) && !self.check(TokenType::Eof) {
+            self.expression();
+            count += 1;
+            if !self.match_token(TokenType::Comma) { break; }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after tuple elements.");
+        if count > u8::MAX as usize { self.error("Too many elements in tuple literal."); return; }
+        self.emit_bytes(OpCode::BuildTuple.to_byte(), count as u8);
     }
 
+    // Compile an expression whose value may outlive the frame.
     fn expression(&mut self) {
-        // Reset flag before compiling an expression; struct_literal will set if result is stack struct.
-        self.last_expr_stack_struct = false;
-        self.parse_precedence(Precedence::Assignment);
+        let value = self.expression_value();
+        self.escape_value(&value);
+    }
+
+    // Compile an expression and return the escape groups of its value; the caller decides where it goes.
+    fn expression_value(&mut self) -> Vec<usize> {
+        self.parse_precedence(Precedence::Assignment)
+    }
+
+    // An expression whose value is only looked at -- printed, tested, used as a key -- and never kept,
+    // so its structs don't escape.
+    fn inspected_expression(&mut self) {
+        self.expression_value();
+    }
+
+    // Operators produce numbers, bools and strings; their operands don't outlive them. Infix operators
+    // drop their left operand with take_left.
+    fn operand(&mut self, precedence: Precedence) {
+        self.parse_precedence(precedence);
+    }
+
+    fn escape_value(&mut self, groups: &[usize]) {
+        for &group in groups {
+            self.current_compiler_mut().escape(group);
+        }
+    }
+
+    fn join_value(&mut self, group: usize, groups: &[usize]) {
+        for &other in groups {
+            self.current_compiler_mut().join(group, other);
+        }
+    }
+
+    // Record the escape groups of the value the current parse function produces.
+    fn set_value(&mut self, groups: Vec<usize>) {
+        let compiler = self.compilers.len();
+        if let Some(frame) = self.value_frames.last_mut().filter(|frame| frame.compiler == compiler) {
+            frame.result = groups;
+        }
+    }
+
+    // The left operand's escape groups, for infix operators that keep its value.
+    fn take_left(&mut self) -> Vec<usize> {
+        self.value_frames.last_mut().map(|frame| mem::take(&mut frame.left)).unwrap_or_default()
     }
 
     fn unary(&mut self) {
         let operator_type = self.previous.token_type;
 
-        self.parse_precedence(Precedence::Unary);
+        self.operand(Precedence::Unary);
 
         match operator_type {
             TokenType::Bang => self.emit_byte(OpCode::Not.to_byte()),
@@ -967,9 +1582,10 @@ impl<'a> Parser<'a> {
     }
 
     fn binary(&mut self) {
+        self.take_left();
         let operator_type = self.previous.token_type;
         let rule = &RULES[operator_type as usize];
-        self.parse_precedence((rule.precedence as u8 + 1).into());
+        self.operand((rule.precedence as u8 + 1).into());
 
         match operator_type {
             TokenType::BangEqual => self.emit_bytes(OpCode::Equal.to_byte(), OpCode::Not.to_byte()),
@@ -986,6 +1602,15 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn range(&mut self) {
+        // start .. end (exclusive) or start ..= end (inclusive); binds looser than arithmetic,
+        // so 0..n - 1 is 0..(n - 1).
+        self.take_left();
+        let inclusive = self.previous.token_type == TokenType::DotDotEqual;
+        self.operand(Precedence::Term);
+        self.emit_bytes(OpCode::BuildRange.to_byte(), inclusive as u8);
+    }
+
     fn literal(&mut self) {
         let operator_type = self.previous.token_type;
         match operator_type {
@@ -1000,38 +1625,177 @@ impl<'a> Parser<'a> {
         // If the callee was produced by a dot access with an identifier immediately before '(',
         // we will already have compiled the receiver expression on stack. For now, default to Call.
         // More precise lowering handled in dot() when it sees a call situation.
-        let argument_count = self.argument_list();
-        self.emit_bytes(OpCode::Call.to_byte(), argument_count);
+        let (argument_count, names) = self.argument_list();
+        if names.is_empty() {
+            self.emit_bytes(OpCode::Call.to_byte(), argument_count);
+            return;
+        }
+        // CallNamed <argc> <named_count> <name>*: the last named_count arguments are matched by name.
+        self.emit_bytes(OpCode::CallNamed.to_byte(), argument_count);
+        self.emit_byte(names.len() as u8);
+        for name in names {
+            let name_index = self.identifier_constant(name);
+            self.emit_byte(name_index);
+        }
     }
 
     fn dot(&mut self, can_assign: bool) {
+        if self.match_token(TokenType::Number) {
+            self.tuple_item(can_assign);
+            return;
+        }
+        // A field's value comes from the receiver's fields group; setting a field doesn't move the receiver.
+        let receiver = self.take_left();
         // After consuming '.', expect identifier for field or method.
         self.consume(TokenType::Identifier, "Expect property name after '.'.");
         let name_token = self.previous.clone();
-        let name_value = make_string_value(&mut self.object_manager, &mut self.intern_strings, name_token.value);
+        let name_value = make_string_value(self.object_manager, self.intern_strings, name_token.value);
         let name_index = self.make_constant(name_value);
         if can_assign && self.match_token(TokenType::Equal) {
             // Assignment: obj.field = expr
             self.expression();
             self.emit_bytes(OpCode::SetField.to_byte(), name_index);
+            self.emit_inline_cache();
         } else if self.match_token(TokenType::LeftParen) {
-            // Method call: obj.method(args)
-            let argc = self.argument_list();
+            // Method call: obj.method(args); the method gets the receiver as self.
+            self.escape_value(&receiver);
+            let (argc, names) = self.argument_list();
+            if !names.is_empty() {
+                self.error("Named arguments are only supported in plain function calls.");
+            }
             self.emit_bytes(OpCode::Invoke.to_byte(), name_index);
             self.emit_byte(argc);
+            self.emit_inline_cache();
         } else {
-            // Field get
+            // Field get; a method taken as a value is bound to the receiver, so the receiver goes with it.
             self.emit_bytes(OpCode::GetField.to_byte(), name_index);
+            self.emit_inline_cache();
+            let mut value: Vec<usize> = receiver.iter().map(|&group| self.current_compiler_mut().fields_group(group)).collect();
+            value.extend(receiver);
+            self.set_value(value);
+        }
+    }
+
+    fn super_call(&mut self) {
+        // super.method(args): the supertrait's version of method for self, from inside an impl method.
+        let trait_tok = match self.impl_trait.clone() {
+            Some(tok) => tok,
+            None => { self.error("Can't use 'super' outside of an impl method."); return; }
+        };
+        let super_tok = self.previous.clone();
+        self.consume(TokenType::Dot, "Expect '.' after 'super'.");
+        self.consume(TokenType::Identifier, "Expect supertrait method name.");
+        let method_index = self.identifier_constant(self.previous.clone());
+        self.consume(TokenType::LeftParen, "Expect '(' after method name.");
+        self.named_variable(Token { token_type: TokenType::Identifier, value: "self", line: super_tok.line }, false);
+        let (argc, names) = self.argument_list();
+        if !names.is_empty() {
+            self.error("Named arguments are only supported in plain function calls.");
+        }
+        let trait_index = self.identifier_constant(trait_tok);
+        self.emit_bytes(OpCode::SuperInvoke.to_byte(), trait_index);
+        self.emit_bytes(method_index, argc);
+        self.set_value(Vec::new());
+    }
+
+    fn is_trait(&mut self) {
+        // Testing membership doesn't keep the value.
+        self.take_left();
+        self.consume(TokenType::Identifier, "Expect trait name after 'is'.");
+        let trait_index = self.identifier_constant(self.previous.clone());
+        self.emit_bytes(OpCode::IsTrait.to_byte(), trait_index);
+    }
+
+    fn tuple_item(&mut self, can_assign: bool) {
+        // t.0; t.0.1 scans as the number 0.1, which is two positional accesses.
+        let number = self.previous.clone();
+        for part in number.value.split('.') {
+            match part.parse::<u8>() {
+                Ok(index) => self.emit_bytes(OpCode::GetTupleItem.to_byte(), index),
+                Err(_) => { self.error("Expect tuple index after '.'."); return; }
+            }
+        }
+        if can_assign && self.match_token(TokenType::Equal) {
+            self.error("Tuples are immutable.");
+        }
+    }
+
+    fn list_literal(&mut self) {
+        // '[' ( expression ( ',' expression )* ','? )? ']'
+        let mut count: usize = 0;
+        if !self.check(TokenType::RightBracket) {
+            loop {
+                self.expression();
+                count += 1;
+                if !self.match_token(TokenType::Comma) { break; }
+                if self.check(TokenType::RightBracket) { break; } // trailing comma
+            }
+        }
+        self.consume(TokenType::RightBracket, "Expect ']' after list elements.");
+        if count > u8::MAX as usize { self.error("Too many elements in list literal."); return; }
+        self.emit_bytes(OpCode::BuildList.to_byte(), count as u8);
+    }
+
+    fn map_literal(&mut self) {
+        // '{' ( expression ':' expression ( ',' expression ':' expression )* ','? )? '}'
+        // Keys are ordinary expressions; the VM requires them to evaluate to strings.
+        let mut count: usize = 0;
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                self.expression();
+                self.consume(TokenType::Colon, "Expect ':' after map key.");
+                self.expression();
+                count += 1;
+                if !self.match_token(TokenType::Comma) { break; }
+                if self.check(TokenType::RightBrace) { break; } // trailing comma
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after map entries.");
+        if count > u8::MAX as usize { self.error("Too many entries in map literal."); return; }
+        self.emit_bytes(OpCode::BuildMap.to_byte(), count as u8);
+    }
+
+    fn index(&mut self, can_assign: bool) {
+        // receiver '[' expression ']' ( '=' expression )?
+        // Lists, maps and strings hold heap values only, so the receiver is just read.
+        self.take_left();
+        self.inspected_expression();
+        self.consume(TokenType::RightBracket, "Expect ']' after index.");
+        if can_assign && self.match_token(TokenType::Equal) {
+            self.expression();
+            self.emit_byte(OpCode::SetIndex.to_byte());
+        } else {
+            self.emit_byte(OpCode::GetIndex.to_byte());
         }
     }
 
     fn struct_literal(&mut self, type_name: Token) {
-        // Identifier '{' ( fieldName ':' expression (',' fieldName ':' expression)* )? '}'
+        // Identifier '{' ( fieldName '=' expression (',' fieldName '=' expression)* )? ( ',' '..' expression )? '}'
         self.consume(TokenType::LeftBrace, "Expect '{' after struct type name.");
+        // Field values (and the base's fields) live as long as the instance does.
+        let group = self.current_compiler_mut().new_escape_group();
+        if self.force_heap_struct_literal {
+            self.current_compiler_mut().escape(group);
+        }
+        let fields = self.current_compiler_mut().fields_group(group);
         let mut field_names: Vec<String> = Vec::new();
         let mut field_name_indices: Vec<u8> = Vec::new();
+        let mut has_base = false;
         if !self.check(TokenType::RightBrace) {
             loop {
+                if self.match_token(TokenType::DotDot) {
+                    // ..base copies the fields not listed from another instance of the same type
+                    let value = self.expression_value();
+                    for base in value {
+                        let base_fields = self.current_compiler_mut().fields_group(base);
+                        self.current_compiler_mut().join(fields, base_fields);
+                    }
+                    has_base = true;
+                    if !self.check(TokenType::RightBrace) {
+                        self.error_at_current("'..base' must be last in a struct literal.");
+                    }
+                    break;
+                }
                 self.consume(TokenType::Identifier, "Expect field name in struct literal.");
                 let fname = self.previous.value.to_string();
                 if field_names.contains(&fname) { self.error("Duplicate field in struct literal."); }
@@ -1039,9 +1803,10 @@ impl<'a> Parser<'a> {
                 // Accept '=' between field name and expression (since ':' token doesn't exist yet)
                 self.consume(TokenType::Equal, "Expect '=' after field name in struct literal.");
                 // compile expression for field value (will be on stack in order)
-                self.expression();
+                let value = self.expression_value();
+                self.join_value(fields, &value);
                 // store constant index for field name to send with opcode so VM can match order
-                let fv = make_string_value(&mut self.object_manager, &mut self.intern_strings, fname.as_str());
+                let fv = make_string_value(self.object_manager, self.intern_strings, fname.as_str());
                 let fi = self.make_constant(fv);
                 field_name_indices.push(fi);
                 if !self.match_token(TokenType::Comma) { break; }
@@ -1050,24 +1815,35 @@ impl<'a> Parser<'a> {
         }
         self.consume(TokenType::RightBrace, "Expect '}' after struct literal fields.");
         // Push the type name as constant index (VM will resolve to struct type via registry)
-        let tname_value = make_string_value(&mut self.object_manager, &mut self.intern_strings, type_name.value);
+        let tname_value = make_string_value(self.object_manager, self.intern_strings, type_name.value);
         let tname_index = self.make_constant(tname_value);
-        // Decide heap vs stack allocation opcode based on force flag.
-        if self.force_heap_struct_literal {
+        // Start on the stack unless already escaped; the group patches the instruction if the value escapes.
+        let root = self.current_compiler_mut().find_escape_group(group);
+        if self.current_compiler().escape_groups[root].escaped {
             self.emit_byte(OpCode::StructInstantiate.to_byte());
         } else {
+            let site = self.current_chunk().len();
+            self.current_compiler_mut().escape_groups[root].sites.push(site);
             self.emit_byte(OpCode::StructInstantiateStack.to_byte());
         }
         self.emit_byte(tname_index);
         let count = field_name_indices.len();
         if count > u8::MAX as usize { self.error("Too many fields in struct literal."); return; }
-        self.emit_byte(count as u8);
+        self.emit_bytes(count as u8, has_base as u8);
         for fi in field_name_indices.iter() { self.emit_byte(*fi); }
-        // Mark whether final expression result is stack struct (only if not forced heap).
-        self.last_expr_stack_struct = !self.force_heap_struct_literal;
+        self.set_value(vec![group]);
+    }
+
+    // Returns the escape groups of the parsed expression's value.
+    fn parse_precedence(&mut self, precedence: Precedence) -> Vec<usize> {
+        self.value_frames.push(ValueFrame { compiler: self.compilers.len(), ..ValueFrame::default() });
+        self.parse_operators(precedence);
+        let frame = self.value_frames.pop().unwrap();
+        self.escape_value(&frame.left);
+        frame.result
     }
 
-    fn parse_precedence(&mut self, precedence: Precedence) {
+    fn parse_operators(&mut self, precedence: Precedence) {
         self.advance();
 
         let can_assign = precedence <= Precedence::Assignment;
@@ -1078,10 +1854,14 @@ impl<'a> Parser<'a> {
             return;
         }
 
-        while precedence as u8 <= (&RULES[self.current.token_type as usize]).precedence as u8 {
+        while precedence as u8 <= RULES[self.current.token_type as usize].precedence as u8 {
             self.advance();
             if let Some(infix) = &RULES[self.previous.token_type as usize].infix {
+                let frame = self.value_frames.last_mut().unwrap();
+                frame.left = mem::take(&mut frame.result);
                 infix(self, can_assign);
+                let left = self.take_left();
+                self.escape_value(&left);
             } else {
                 self.error("Expect infix parse function.");
                 return;
@@ -1101,18 +1881,21 @@ impl<'a> Parser<'a> {
             }
 
             match self.current.token_type {
-                token_type if matches!(token_type,
-                    TokenType::Trait |
-                    TokenType::Impl |
-                    TokenType::Struct |
-                    TokenType::New |
-                    TokenType::Fn |
-                    TokenType::Var |
-                    TokenType::For |
-                    TokenType::If |
-                    TokenType::While |
-                    TokenType::Print |
-                    TokenType::Return) => return,
+                TokenType::Trait |
+                TokenType::Impl |
+                TokenType::Struct |
+                TokenType::Enum |
+                TokenType::Match |
+                TokenType::New |
+                TokenType::Fn |
+                TokenType::Var |
+                TokenType::Const |
+                TokenType::Let |
+                TokenType::For |
+                TokenType::If |
+                TokenType::While |
+                TokenType::Print |
+                TokenType::Return => return,
                 _ => ()
             }
 
@@ -1120,15 +1903,15 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn error(&mut self, message: &'a str) {
+    fn error(&mut self, message: &str) {
         self.error_at(&self.previous.clone(), message);
     }
 
-    fn error_at_current(&mut self, message: &'a str) {
+    fn error_at_current(&mut self, message: &str) {
         self.error_at(&self.current.clone(), message);
     }
 
-    fn error_at(&mut self, token: &Token, message: &'a str) {
+    fn error_at(&mut self, token: &Token, message: &str) {
         if self.panic_mode {
             return;
         }
@@ -1146,11 +1929,29 @@ impl<'a> Parser<'a> {
         self.has_error = true;
     }
 
+    // Diagnostics that don't fail compilation.
+    fn warning_at(&self, token: &Token, message: &str) {
+        writeln!(&mut std::io::stderr(), "[line {}] Warning at '{}': {}", token.line, token.value, message).expect("Failed to write to stderr");
+    }
+
     // -------- Trait & Impl Parsing (Step 1: grammar only, no bytecode) --------
     fn trait_declaration(&mut self) {
-        // trait IDENTIFIER '{' ( fn IDENTIFIER '(' params? ')' ';' )* '}'
-        self.consume(TokenType::Identifier, "Expect trait name.");
+        // trait IDENTIFIER ( ':' IDENTIFIER ( '+' IDENTIFIER )* )? '{' ( fn IDENTIFIER '(' params? ')' ';' )* '}'
+        let global = self.parse_variable("Expect trait name.");
         let trait_name_token = self.previous.clone();
+        let mut supertraits: Vec<Token> = Vec::new();
+        if self.match_token(TokenType::Colon) {
+            loop {
+                self.consume(TokenType::Identifier, "Expect supertrait name.");
+                if self.previous.value == trait_name_token.value {
+                    self.error("A trait can't be its own supertrait.");
+                } else if supertraits.iter().any(|t| t.value == self.previous.value) {
+                    self.error("Duplicate supertrait.");
+                }
+                supertraits.push(self.previous.clone());
+                if !self.match_token(TokenType::Plus) { break; }
+            }
+        }
         self.consume(TokenType::LeftBrace, "Expect '{' after trait name.");
         let mut method_names: Vec<String> = Vec::new();
         while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
@@ -1164,6 +1965,7 @@ impl<'a> Parser<'a> {
             self.consume(TokenType::LeftParen, "Expect '(' after method name.");
             if !self.check(TokenType::RightParen) { // parameter list (names ignored)
                 loop {
+                    self.match_token(TokenType::DotDotDot);
                     self.consume(TokenType::Identifier, "Expect parameter name.");
                     if !self.match_token(TokenType::Comma) { break; }
                 }
@@ -1174,7 +1976,7 @@ impl<'a> Parser<'a> {
         self.consume(TokenType::RightBrace, "Expect '}' after trait body.");
         // Allocate a trait object now and store as a constant for runtime registration.
         let (trait_ptr, _sz) = self.object_manager.alloc_trait(trait_name_token.value.to_string());
-        let trait_value = Value { value_type: ValueType::ValueObject, value_as: ValueUnion { object: trait_ptr as *mut crate::objects::object::Object } };
+        let trait_value = make_object_value(trait_ptr as *mut crate::objects::object::Object);
         let const_index = self.make_constant(trait_value);
         // Placeholder: emit ImplementTrait with constant index and method count (u8) then each method name constant index.
         self.emit_byte(OpCode::ImplementTrait.to_byte());
@@ -1183,10 +1985,20 @@ impl<'a> Parser<'a> {
         if count > u8::MAX as usize { self.error("Too many trait methods."); return; }
         self.emit_byte(count as u8);
         for m in method_names.iter() {
-            let mv = make_string_value(&mut self.object_manager, &mut self.intern_strings, m.as_str());
+            let mv = make_string_value(self.object_manager, self.intern_strings, m.as_str());
             let mi = self.make_constant(mv);
             self.emit_byte(mi);
         }
+        // Then the supertrait count and a name constant for each.
+        if supertraits.len() > u8::MAX as usize { self.error("Too many supertraits."); return; }
+        self.emit_byte(supertraits.len() as u8);
+        for t in supertraits {
+            let ti = self.identifier_constant(t);
+            self.emit_byte(ti);
+        }
+        // The trait is also a value, so Trait.method(x) can name which trait's method to call.
+        self.emit_bytes(OpCode::Constant.to_byte(), const_index);
+        self.define_variable(global);
     }
 
     fn impl_declaration(&mut self) {
@@ -1197,8 +2009,9 @@ impl<'a> Parser<'a> {
         self.consume(TokenType::Identifier, "Expect target type name after 'for'.");
         let type_name_tok = self.previous.clone();
         self.consume(TokenType::LeftBrace, "Expect '{' after impl header.");
+        let enclosing_impl_trait = self.impl_trait.replace(trait_name_tok.clone());
         // Compile each method body; record entries of (method name const, function const, upvalue descriptors)
-        let mut method_entries: Vec<(u8, u8, Vec<(bool, u8)>)> = Vec::new();
+        let mut method_entries: Vec<ImplMethodEntry> = Vec::new();
         while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
             if !self.match_token(TokenType::Fn) {
                 self.error("Expect 'fn' in impl body.");
@@ -1208,35 +2021,28 @@ impl<'a> Parser<'a> {
             // Method name
             self.consume(TokenType::Identifier, "Expect method name.");
             let method_name_tok = self.previous.clone();
-            let mname_val = make_string_value(&mut self.object_manager, &mut self.intern_strings, method_name_tok.value);
+            let mname_val = make_string_value(self.object_manager, self.intern_strings, method_name_tok.value);
             let mname_idx = self.make_constant(mname_val);
 
             // Compile method function with implicit 'self' receiver parameter.
             // Initialize new compiler context for this function
             self.init_compiler(FunctionType::Function);
             // Function name was set from previous token by init_compiler
+            self.current_function_mut().method_of = Some(type_name_tok.value.to_string());
             self.begin_scope();
             // Inject implicit 'self' parameter as a local and arity +1
             {
                 let scope_depth = self.current_compiler().scope_depth; // after begin_scope -> 1
                 // Create a synthetic token for 'self' using static str; comparisons use string equality
                 let self_tok = Token { token_type: TokenType::Identifier, value: "self", line: method_name_tok.line };
-                self.current_locals_mut().push(Local { name: self_tok, depth: scope_depth, captured: false });
+                let group = self.current_compiler_mut().new_escape_group();
+                self.current_locals_mut().push(Local { name: self_tok, depth: scope_depth, captured: false, constant: false, group });
                 self.current_function_mut().arity = self.current_function().arity.saturating_add(1);
             }
             // Parse parameter list and body
             self.consume(TokenType::LeftParen, "Expect '(' after method name.");
-            if !self.check(TokenType::RightParen) { // params
-                loop {
-                    self.current_function_mut().arity += 1;
-                    if self.current_function_mut().arity >= 255 {
-                        self.error("Can't have more than 255 parameters.");
-                    }
-                    let param_const = self.parse_variable("Expect parameter name.");
-                    self.define_variable(param_const);
-                    if !self.match_token(TokenType::Comma) { break; }
-                }
-            }
+            self.current_function_mut().param_names.push("self".to_string());
+            self.parameter_list(TokenType::RightParen);
             self.consume(TokenType::RightParen, "Expect ')' after parameters.");
             self.consume(TokenType::LeftBrace, "Expect '{' before method body.");
             self.block();
@@ -1251,10 +2057,11 @@ impl<'a> Parser<'a> {
             method_entries.push((mname_idx, fn_const_idx, uv_pairs));
         }
         self.consume(TokenType::RightBrace, "Expect '}' after impl body.");
+        self.impl_trait = enclosing_impl_trait;
         // Emit ImplRegister: trait name, type name, method count, then for each: method name, function const, upvalue descriptors.
-        let trait_name_val = make_string_value(&mut self.object_manager, &mut self.intern_strings, trait_name_tok.value);
+        let trait_name_val = make_string_value(self.object_manager, self.intern_strings, trait_name_tok.value);
         let trait_name_idx = self.make_constant(trait_name_val);
-        let type_name_val = make_string_value(&mut self.object_manager, &mut self.intern_strings, type_name_tok.value);
+        let type_name_val = make_string_value(self.object_manager, self.intern_strings, type_name_tok.value);
         let type_name_idx = self.make_constant(type_name_val);
         self.emit_byte(OpCode::ImplRegister.to_byte());
         self.emit_byte(trait_name_idx);
@@ -1276,33 +2083,225 @@ impl<'a> Parser<'a> {
 
     fn struct_declaration(&mut self) {
         // struct IDENTIFIER '{' (field (',' field)*)? '}'
+        // field: ( 'private' | 'readonly' )? IDENTIFIER ( '=' expression )?
         self.consume(TokenType::Identifier, "Expect struct name.");
         let name_tok = self.previous.clone();
         self.consume(TokenType::LeftBrace, "Expect '{' after struct name.");
         let mut fields: Vec<String> = Vec::new();
+        let mut field_flags: Vec<u8> = Vec::new();
         if !self.check(TokenType::RightBrace) {
             loop {
+                // 'private' and 'readonly' are modifiers only when another name follows.
+                let mut access_flags = 0u8;
+                if self.check(TokenType::Identifier) && self.check_next(TokenType::Identifier) {
+                    match self.current.value {
+                        "readonly" => access_flags = chunk::STRUCT_FIELD_READONLY,
+                        "private" => access_flags = chunk::STRUCT_FIELD_PRIVATE,
+                        _ => self.error_at_current("Expect 'private' or 'readonly' before field name."),
+                    }
+                    self.advance();
+                }
                 self.consume(TokenType::Identifier, "Expect field name.");
                 let fname = self.previous.value.to_string();
                 if fields.contains(&fname) { self.error("Duplicate field name in struct."); }
                 fields.push(fname);
+                // field = expression: the default is compiled as a closure that runs at each instantiation
+                let default = self.match_token(TokenType::Equal);
+                if default { self.field_default(); }
+                field_flags.push(if default { access_flags | chunk::STRUCT_FIELD_DEFAULT } else { access_flags });
                 if !self.match_token(TokenType::Comma) { break; }
                 if self.check(TokenType::RightBrace) { break; } // trailing comma
             }
         }
         self.consume(TokenType::RightBrace, "Expect '}' after struct fields.");
-        // Emit StructType opcode payload: name constant, field count, field name constants.
-        let name_value = make_string_value(&mut self.object_manager, &mut self.intern_strings, name_tok.value);
+        // Emit StructType opcode payload: name constant, field count, (field name constant, flags)*.
+        // Default closures were pushed in field order by field_default().
+        let name_value = make_string_value(self.object_manager, self.intern_strings, name_tok.value);
         let struct_name_index = self.make_constant(name_value);
         self.emit_byte(OpCode::StructType.to_byte());
         self.emit_byte(struct_name_index);
         let count = fields.len();
         if count > u8::MAX as usize { self.error("Too many struct fields."); return; }
         self.emit_byte(count as u8);
-        for f in fields.iter() {
-            let fv = make_string_value(&mut self.object_manager, &mut self.intern_strings, f.as_str());
+        for (f, flags) in fields.iter().zip(field_flags) {
+            let fv = make_string_value(self.object_manager, self.intern_strings, f.as_str());
             let fi = self.make_constant(fv);
-            self.emit_byte(fi);
+            self.emit_bytes(fi, flags);
+        }
+    }
+
+    fn field_default(&mut self) {
+        // Compiled like the short lambda || expression.
+        self.init_compiler(FunctionType::Lambda);
+        self.begin_scope();
+        self.expression();
+        self.emit_byte(OpCode::Return.to_byte());
+        self.emit_closure();
+    }
+
+    fn enum_declaration(&mut self) {
+        // enum IDENTIFIER '{' ( variant ( ',' variant )* ','? )? '}'
+        // variant: IDENTIFIER ( '(' field ( ',' field )* ')' )?
+        let global = self.parse_variable("Expect enum name.");
+        let name_tok = self.previous.clone();
+        self.consume(TokenType::LeftBrace, "Expect '{' after enum name.");
+        let mut variants: Vec<(String, usize)> = Vec::new();
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                self.consume(TokenType::Identifier, "Expect variant name.");
+                let vname = self.previous.value.to_string();
+                if variants.iter().any(|(n, _)| *n == vname) { self.error("Duplicate variant name in enum."); }
+                let mut arity: usize = 0;
+                if self.match_token(TokenType::LeftParen) {
+                    // Payload field names only document the variant; values are positional.
+                    if !self.check(TokenType::RightParen) {
+                        loop {
+                            self.consume(TokenType::Identifier, "Expect payload field name.");
+                            arity += 1;
+                            if !self.match_token(TokenType::Comma) { break; }
+                        }
+                    }
+                    self.consume(TokenType::RightParen, "Expect ')' after variant payload fields.");
+                }
+                variants.push((vname, arity));
+                if !self.match_token(TokenType::Comma) { break; }
+                if self.check(TokenType::RightBrace) { break; } // trailing comma
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after enum variants.");
+        // Emit EnumType payload: name constant, variant count, then (variant name constant, arity) pairs.
+        // The VM pushes the new enum type, which is then bound like any other variable.
+        let name_index = self.identifier_constant(name_tok.clone());
+        self.emit_bytes(OpCode::EnumType.to_byte(), name_index);
+        if variants.len() > u8::MAX as usize { self.error("Too many enum variants."); return; }
+        self.emit_byte(variants.len() as u8);
+        for (vname, arity) in variants.iter() {
+            if *arity > u8::MAX as usize { self.error("Too many payload fields in enum variant."); return; }
+            let vv = make_string_value(self.object_manager, self.intern_strings, vname.as_str());
+            let vi = self.make_constant(vv);
+            self.emit_bytes(vi, *arity as u8);
+        }
+        self.enum_variants.insert(name_tok.value.to_string(), variants);
+        self.define_variable(global);
+    }
+
+    fn match_statement(&mut self) {
+        // match ( expression ) '{' ( pattern '=>' ( statement | expression ) ','? )* '}'
+        // pattern: EnumName '.' Variant ( '(' binding ( ',' binding )* ')' )? | '_'
+        let match_tok = self.previous.clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'match'.");
+        self.begin_scope();
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after match value.");
+        self.add_hidden_local("(match subject)");
+        let subject_slot = (self.current_locals().len() - 1) as u8;
+        self.consume(TokenType::LeftBrace, "Expect '{' before match arms.");
+
+        let mut end_jump_offset_operands: Vec<u16> = Vec::new();
+        let mut covered: Vec<(String, String)> = Vec::new();
+        let mut has_wildcard = false;
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            if has_wildcard { self.error_at_current("Unreachable match arm after '_'."); }
+            self.consume(TokenType::Identifier, "Expect pattern in match arm.");
+            if self.previous.value == "_" {
+                has_wildcard = true;
+                self.consume(TokenType::FatArrow, "Expect '=>' after match pattern.");
+                self.match_arm_body();
+                end_jump_offset_operands.push(self.emit_jump_bytes(OpCode::Jump.to_byte()));
+            } else {
+                let enum_tok = self.previous.clone();
+                self.consume(TokenType::Dot, "Expect '.' after enum name in pattern.");
+                self.consume(TokenType::Identifier, "Expect variant name in pattern.");
+                let variant_tok = self.previous.clone();
+                let mut bindings: Vec<Token<'a>> = Vec::new();
+                if self.match_token(TokenType::LeftParen) {
+                    if !self.check(TokenType::RightParen) {
+                        loop {
+                            self.consume(TokenType::Identifier, "Expect binding name in pattern.");
+                            let binding = self.previous.clone();
+                            if binding.value != "_" && bindings.iter().any(|b| b.value == binding.value) {
+                                self.error("Duplicate binding name in pattern.");
+                            }
+                            bindings.push(binding);
+                            if !self.match_token(TokenType::Comma) { break; }
+                        }
+                    }
+                    self.consume(TokenType::RightParen, "Expect ')' after pattern bindings.");
+                }
+                // Enums declared earlier in this compilation can be checked now; others only at runtime.
+                if let Some(variants) = self.enum_variants.get(enum_tok.value) {
+                    match variants.iter().find(|(n, _)| n == variant_tok.value) {
+                        None => self.error("Unknown enum variant in pattern."),
+                        Some((_, arity)) if *arity != bindings.len() => self.error("Pattern bindings don't match the variant's payload count."),
+                        _ => {}
+                    }
+                }
+                covered.push((enum_tok.value.to_string(), variant_tok.value.to_string()));
+                self.consume(TokenType::FatArrow, "Expect '=>' after match pattern.");
+
+                // MatchVariant <subject_slot> <variant_name> <binding_count> <next_arm_offset>
+                // pops the enum type; on a match it pushes the payload as the arm's locals.
+                self.named_variable(enum_tok, false);
+                let variant_index = self.identifier_constant(variant_tok);
+                self.emit_bytes(OpCode::MatchVariant.to_byte(), subject_slot);
+                self.emit_bytes(variant_index, bindings.len() as u8);
+                self.emit_bytes(0xff, 0xff);
+                let next_arm_offset_operand = (self.current_chunk().len() - 2) as u16;
+
+                self.begin_scope();
+                for binding in bindings {
+                    self.add_local(binding);
+                    self.mark_initialized();
+                }
+                self.match_arm_body();
+                self.end_scope();
+                end_jump_offset_operands.push(self.emit_jump_bytes(OpCode::Jump.to_byte()));
+                self.patch_jump_offset(next_arm_offset_operand);
+            }
+            self.match_token(TokenType::Comma);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after match arms.");
+
+        if !has_wildcard {
+            self.emit_bytes(OpCode::MatchFail.to_byte(), subject_slot);
+            self.warn_non_exhaustive(&match_tok, &covered);
+        }
+        for operand in end_jump_offset_operands {
+            self.patch_jump_offset(operand);
+        }
+        self.end_scope();
+    }
+
+    fn match_arm_body(&mut self) {
+        // An arm is a statement, or a bare expression that may end with ',' instead of ';'.
+        let is_statement = [TokenType::If, TokenType::LeftBrace, TokenType::While, TokenType::For,
+            TokenType::Match, TokenType::Return, TokenType::Print].iter().any(|t| self.check(*t));
+        if is_statement {
+            self.statement();
+            return;
+        }
+        self.expression();
+        if !self.match_token(TokenType::Semicolon) && !self.check(TokenType::Comma) && !self.check(TokenType::RightBrace) {
+            self.error_at_current("Expect ';' or ',' after match arm expression.");
+        }
+        self.emit_byte(OpCode::Pop.to_byte());
+    }
+
+    fn warn_non_exhaustive(&self, match_tok: &Token, covered: &[(String, String)]) {
+        let enum_name = match covered.first() {
+            Some((name, _)) => name,
+            None => { self.warning_at(match_tok, "Match has no arms; every value is a runtime error."); return; }
+        };
+        let variants = match self.enum_variants.get(enum_name) {
+            Some(variants) => variants,
+            None => return, // declared elsewhere; only the runtime check applies
+        };
+        let missing: Vec<String> = variants.iter()
+            .filter(|(v, _)| !covered.iter().any(|(e, c)| e == enum_name && c == v))
+            .map(|(v, _)| format!("{}.{}", enum_name, v))
+            .collect();
+        if !missing.is_empty() {
+            self.warning_at(match_tok, &format!("Non-exhaustive match; missing {}.", missing.join(", ")));
         }
     }
 
@@ -1368,7 +2367,8 @@ mod tests {
     fn test_compile() {
         let mut object_manager = ObjectManager::new();
         let mut intern_strings = Table::new();
-        let mut parser = Parser::new(&mut object_manager, &mut intern_strings);
+        let mut globals = Globals::new();
+        let mut parser = Parser::new(&mut object_manager, &mut intern_strings, &mut globals);
     let result = parser.compile(r#"!(5 - 4 > 3 * 2 == !nil);"#);
         assert!(result.is_some());
         
@@ -1388,13 +2388,9 @@ mod tests {
         // 00000014        | Not
         // 00000015        | Pop
         // 00000016        | Return
-        assert!(*chunk.get_constant(0) == Value {
-            value_type: ValueType::ValueNumber,
-            value_as: ValueUnion{number: 5.0}});
+        assert!(*chunk.get_constant(0) == make_numer_value(5.0));
 
-        assert!(*chunk.get_constant(1) == Value {
-            value_type: ValueType::ValueNumber,
-            value_as: ValueUnion{number: 4.0}});
+        assert!(*chunk.get_constant(1) == make_numer_value(4.0));
 
         assert!(chunk.read_from_offset(0).unwrap() == OpCode::Constant.to_byte());
         assert!(chunk.read_from_offset(1).unwrap() == 0); // constant index
@@ -1420,12 +2416,13 @@ mod tests {
     fn test_intern_strings() {
         let mut object_manager = ObjectManager::new();
         let mut intern_strings = Table::new();
-        let mut parser = Parser::new(&mut object_manager, &mut intern_strings);
+        let mut globals = Globals::new();
+        let mut parser = Parser::new(&mut object_manager, &mut intern_strings, &mut globals);
         
     let result = parser.compile(r#""this is a test string";"#);
         assert!(result.is_some());
 
-        parser = Parser::new(&mut object_manager, &mut intern_strings);
+        parser = Parser::new(&mut object_manager, &mut intern_strings, &mut globals);
     let result = parser.compile(r#""this is a test string";"#);
         assert!(result.is_some());
 
@@ -1436,7 +2433,8 @@ mod tests {
     fn test_function_declaration() {
         let mut object_manager = ObjectManager::new();
         let mut intern_strings = Table::new();
-        let mut parser = Parser::new(&mut object_manager, &mut intern_strings);
+        let mut globals = Globals::new();
+        let mut parser = Parser::new(&mut object_manager, &mut intern_strings, &mut globals);
         
         let result = parser.compile(
             r#"fn areWeHavingItYet() {
@@ -1450,7 +2448,8 @@ mod tests {
     fn test_function_with_arguments() {
         let mut object_manager = ObjectManager::new();
         let mut intern_strings = Table::new();
-        let mut parser = Parser::new(&mut object_manager, &mut intern_strings);
+        let mut globals = Globals::new();
+        let mut parser = Parser::new(&mut object_manager, &mut intern_strings, &mut globals);
         
         let result = parser.compile(
             r#"fn sum(a, b, c) {
@@ -1459,4 +2458,25 @@ mod tests {
                     print 4 + sum(5, 6, 7);"#);
         assert!(result.is_some());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_global_slots_shared_across_compilations() {
+        let mut object_manager = ObjectManager::new();
+        let mut intern_strings = Table::new();
+        let mut globals = Globals::new();
+        let mut parser = Parser::new(&mut object_manager, &mut intern_strings, &mut globals);
+        assert!(parser.compile("var a = 1; var b = a;").is_some());
+
+        parser = Parser::new(&mut object_manager, &mut intern_strings, &mut globals);
+        let result = parser.compile("b = 2;");
+        assert!(result.is_some());
+        let chunk = unsafe { &(*result.unwrap()).chunk };
+        // Constant 0, SetGlobal <slot:u16>
+        assert!(chunk.read_from_offset(2).unwrap() == OpCode::SetGlobal.to_byte());
+        assert!(chunk.read_from_offset(3).unwrap() == 0 && chunk.read_from_offset(4).unwrap() == 1);
+
+        assert_eq!(globals.len(), 2);
+        assert_eq!(globals.name(1), "b");
+        assert!(!globals.is_defined(0));
+    }
+}
@@ -27,16 +27,19 @@ pub fn disassemble_instruction(chunk: &chunk::Chunk, mut offset: usize) -> usize
 
     let instruction = chunk::OpCode::from_byte(chunk.read_from_offset(offset).unwrap());
     match instruction {
-        Some(op) if matches!(op,
-            chunk::OpCode::Constant
-            | chunk::OpCode::DefineGlobal
-            | chunk::OpCode::GetGlobal
-            | chunk::OpCode::SetGlobal
-        ) => {
+        Some(chunk::OpCode::Constant) => {
             constant_instruction(&chunk::OpCode::byte_to_string(&instruction).to_string(), chunk, offset)
         }
-        Some(op) if matches!(op,
-            chunk::OpCode::Nil
+        Some(chunk::OpCode::DefineGlobal
+            | chunk::OpCode::DefineConstGlobal
+            | chunk::OpCode::GetGlobal
+            | chunk::OpCode::SetGlobal) => {
+            // <global_slot:u16>
+            let slot = ((chunk.read_from_offset(offset + 1).unwrap() as u16) << 8) | chunk.read_from_offset(offset + 2).unwrap() as u16;
+            println!("{:<16} {:>4}", chunk::OpCode::byte_to_string(&instruction), slot);
+            offset + 3
+        }
+        Some(chunk::OpCode::Nil
             | chunk::OpCode::True
             | chunk::OpCode::False
             | chunk::OpCode::Equal
@@ -51,29 +54,57 @@ pub fn disassemble_instruction(chunk: &chunk::Chunk, mut offset: usize) -> usize
             | chunk::OpCode::Print
             | chunk::OpCode::Pop
             | chunk::OpCode::CloseUpvalue
+            | chunk::OpCode::GetIndex
+            | chunk::OpCode::SetIndex
             | chunk::OpCode::Return) => {
             simple_instruction(&chunk::OpCode::byte_to_string(&instruction).to_string(), offset)
         }
-        Some(op) if matches!(op,
-            chunk::OpCode::GetLocal
+        Some(chunk::OpCode::GetLocal
             | chunk::OpCode::SetLocal
             | chunk::OpCode::GetUpvalue
             | chunk::OpCode::SetUpvalue
+            | chunk::OpCode::BuildList
+            | chunk::OpCode::BuildMap
+            | chunk::OpCode::BuildRange
             | chunk::OpCode::Call) => {
             byte_instruction(&chunk::OpCode::byte_to_string(&instruction).to_string(), chunk, offset)
         }
-        Some(op) if matches!(op, 
-            chunk::OpCode::Jump
+        Some(chunk::OpCode::Jump
             | chunk::OpCode::JumpIfFalse
             | chunk::OpCode::JumpIfTrue) => {
             jump_instruction(&chunk::OpCode::byte_to_string(&instruction).to_string(), 1, chunk, offset)
         }
-        Some(op) if matches!(op,
-            chunk::OpCode::Loop) => {
+        Some(chunk::OpCode::ForIter) => {
+            // ForIter <iterable_slot> <exit_offset:u16>
+            let slot = chunk.read_from_offset(offset + 1).unwrap();
+            let mut jump_offset = (chunk.read_from_offset(offset + 2).unwrap() as u16) << 8;
+            jump_offset |= chunk.read_from_offset(offset + 3).unwrap() as u16;
+            println!("{:<16} {:>4} -> {}", "ForIter", slot, offset + 4 + jump_offset as usize);
+            offset + 4
+        }
+        Some(chunk::OpCode::GetField | chunk::OpCode::SetField) => {
+            // GetField/SetField <name_const> <inline_cache:u16>
+            let constant = chunk.read_from_offset(offset + 1).unwrap();
+            let cache = ((chunk.read_from_offset(offset + 2).unwrap() as u16) << 8) | chunk.read_from_offset(offset + 3).unwrap() as u16;
+            print!("{:<16} {:>4} '", chunk::OpCode::byte_to_string(&instruction), constant);
+            print_value(chunk.get_constant(constant as usize));
+            println!("' cache {}", cache);
+            offset + 4
+        }
+        Some(chunk::OpCode::Invoke) => {
+            // Invoke <name_const> <arg_count> <inline_cache:u16>
+            let constant = chunk.read_from_offset(offset + 1).unwrap();
+            let arg_count = chunk.read_from_offset(offset + 2).unwrap();
+            let cache = ((chunk.read_from_offset(offset + 3).unwrap() as u16) << 8) | chunk.read_from_offset(offset + 4).unwrap() as u16;
+            print!("{:<16} {:>4} '", "Invoke", constant);
+            print_value(chunk.get_constant(constant as usize));
+            println!("' ({} args) cache {}", arg_count, cache);
+            offset + 5
+        }
+        Some(chunk::OpCode::Loop) => {
             jump_instruction(&chunk::OpCode::byte_to_string(&instruction).to_string(), -1, chunk, offset)
         }
-        Some(op) if matches!(op,
-            chunk::OpCode::Closure) => {
+        Some(chunk::OpCode::Closure) => {
             let constant = chunk.read_from_offset(offset + 1).unwrap();
             println!("{:<16} {:>4}", "Closure", constant);
             print_value(chunk.get_constant(constant as usize));
@@ -111,7 +142,7 @@ fn constant_instruction(name: &str, chunk: &chunk::Chunk, offset: usize) -> usiz
     let constant = chunk.read_from_offset(offset + 1).unwrap();
     print!("{:<16} {:>4} '", name, constant);
     let constant_index = constant as usize;
-    value::print_value(&chunk.get_constant(constant_index));
+    value::print_value(chunk.get_constant(constant_index));
     println!("'");
     offset + 2
 }
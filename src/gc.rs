@@ -1,26 +1,82 @@
-use std::collections::HashSet;
 use crate::{
     objects::{
         object::{Object, ObjectType},
         object_manager::ObjectManager,
         object_upvalue::ObjectUpvalue,
+        object_weak::ObjectWeak,
     },
-    value::{Value, is_object, as_object},
+    value::{Value, is_object, as_object, make_nil_value},
     table::Table,
+    globals::Globals,
     call_frame::CallFrame,
 };
 
+/// How the VM runs a collection once allocation crosses the threshold.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GcMode {
+    /// Mark and sweep the whole heap in one pause.
+    #[default]
+    StopTheWorld,
+    /// Mark in bounded steps between allocations; a write barrier keeps the marking sound while the
+    /// program mutates the heap, and the cycle ends with a root rescan and the sweep.
+    Incremental,
+    /// New objects start in a nursery that cheap minor collections sweep on their own; survivors are
+    /// promoted to the old generation and only full (major) collections sweep old objects.
+    Generational,
+}
+
+/// Gray objects traced per incremental step.
+pub const INCREMENTAL_MARK_STEP: usize = 64;
+
+/// Bytes allocated since the last minor collection that trigger the next one.
+pub const NURSERY_BYTES: usize = 256 * 1024;
+
+/// Minor collections a young object survives before it is promoted.
+pub const PROMOTION_AGE: u8 = 2;
+
+/// When the VM collects; see `VM::with_gc_config`.
+#[derive(Debug, Clone, Copy)]
+pub struct GcConfig {
+    /// Bytes allocated before the first full collection.
+    pub initial_threshold: usize,
+    /// After a full collection the next one triggers once the live bytes have grown by this factor.
+    pub growth_factor: f64,
+    /// Bytes allocated between minor collections in generational mode.
+    pub nursery_bytes: usize,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        GcConfig { initial_threshold: 1024 * 1024, growth_factor: 2.0, nursery_bytes: NURSERY_BYTES }
+    }
+}
+
 pub struct GarbageCollector {
-    white_set: HashSet<*mut Object>,
-    gray_set: HashSet<*mut Object>,
-    black_set: HashSet<*mut Object>,
+    // Marked objects whose references haven't been traced yet.
+    gray_stack: Vec<*mut Object>,
+    // An incremental cycle has marked the roots and not yet swept.
+    marking: bool,
+    // A minor collection is marking: old objects are neither marked nor traced.
+    minor: bool,
+    // Old objects that may reference young ones; a minor collection traces them as extra roots.
+    remembered: Vec<*mut Object>,
+    // Every live weak reference, so their targets can be cleared before a sweep frees them.
+    weak_refs: Vec<*mut Object>,
     stats: GCStats,
 }
 
-/// Aggregated GC statistics (does not include currently-live total bytes; VM tracks that).
+/// Aggregated GC statistics. The VM tracks live bytes and the next trigger itself and fills them in
+/// when handing out a copy (`VM::gc_stats`).
 #[derive(Default, Debug, Clone)]
 pub struct GCStats {
+    pub live_bytes: usize,
+    pub next_trigger_bytes: usize,
     pub cycles: u64,
+    pub minor_cycles: u64,
+    pub major_cycles: u64,
+    pub total_promoted_bytes: usize,
+    pub last_promoted_bytes: usize,
     pub total_freed_bytes: usize,
     pub last_freed_bytes: usize,
     pub last_before_bytes: usize,
@@ -31,12 +87,24 @@ pub struct GCStats {
 impl GCStats {
     fn record(&mut self, before: usize, freed: usize, after: usize, next_trigger: usize) {
         self.cycles += 1;
+        self.major_cycles += 1;
         self.total_freed_bytes += freed;
         self.last_freed_bytes = freed;
         self.last_before_bytes = before;
         self.last_after_bytes = after;
         self.last_next_trigger_bytes = next_trigger;
     }
+
+    fn record_minor(&mut self, before: usize, freed: usize, after: usize, promoted: usize) {
+        self.cycles += 1;
+        self.minor_cycles += 1;
+        self.total_freed_bytes += freed;
+        self.last_freed_bytes = freed;
+        self.last_before_bytes = before;
+        self.last_after_bytes = after;
+        self.total_promoted_bytes += promoted;
+        self.last_promoted_bytes = promoted;
+    }
 }
 
 // Lightweight tracing macro (only active with gc_debug feature)
@@ -44,37 +112,46 @@ impl GCStats {
 macro_rules! gc_trace { ($($arg:tt)*) => { eprintln!("[gc-trace] {}", format_args!($($arg)*)); } }
 #[cfg(not(feature = "gc_debug"))]
 macro_rules! gc_trace { ($($arg:tt)*) => { } }
+#[allow(unused_imports)]
 pub(crate) use gc_trace; // re-export for potential external module use
 
+fn visit_value(value: &Value, visit: &mut impl FnMut(*mut Object)) {
+    if is_object(value) {
+        visit(as_object(value) as *mut Object);
+    }
+}
+
 impl GarbageCollector {
     pub fn new() -> Self {
         Self {
-            white_set: HashSet::new(),
-            gray_set: HashSet::new(),
-            black_set: HashSet::new(),
+            gray_stack: Vec::new(),
+            marking: false,
+            minor: false,
+            remembered: Vec::new(),
+            weak_refs: Vec::new(),
             stats: GCStats::default(),
         }
     }
 
-    // Initialize the collector with all objects in white set
-    pub fn prepare_collection(&mut self, object_manager: &ObjectManager) {
+    // Objects start every cycle unmarked (new ones are allocated unmarked and the sweep clears survivors),
+    // so there is nothing to rebuild per object here.
+    pub fn prepare_collection(&mut self) {
         self.reset();
-        // Add all objects to white set initially
-        for &obj_ptr in object_manager.iter() {
-            self.white_set.insert(obj_ptr);
-        }
     }
 
-    // Mark a single object as gray (moves from white to gray set)
+    // Mark a single object and queue it for tracing
     pub fn mark_object(&mut self, obj: *mut Object) {
-        if obj.is_null() || self.black_set.contains(&obj) {
+        if obj.is_null() {
             return;
         }
-
-        if self.white_set.remove(&obj) {
-            self.gray_set.insert(obj);
-            gc_trace!("mark_object enqueue gray ptr={:p}", obj);
+        unsafe {
+            if (*obj).is_marked || (self.minor && (*obj).is_old) {
+                return;
+            }
+            (*obj).is_marked = true;
         }
+        self.gray_stack.push(obj);
+        gc_trace!("mark_object enqueue gray ptr={:p}", obj);
     }
 
     // Mark a value (if it's an object)
@@ -85,13 +162,121 @@ impl GarbageCollector {
         self.mark_object(as_object(value) as *mut Object);
     }
 
+    // Write barrier for storing `value` into `holder` (None for VM-owned roots such as globals).
+    // During an incremental cycle the holder may already be traced, so the value is marked right
+    // away; an old holder that gains a young reference joins the remembered set.
+    #[inline]
+    pub fn write_barrier(&mut self, holder: Option<*mut Object>, value: &Value) {
+        if self.marking {
+            self.mark_value(value);
+        }
+        if let Some(holder) = holder {
+            unsafe {
+                if (*holder).is_old && !(*holder).is_remembered && is_object(value) && !(*as_object(value)).is_old {
+                    (*holder).is_remembered = true;
+                    self.remembered.push(holder);
+                }
+            }
+        }
+    }
+
+    pub fn begin_minor(&mut self) { self.minor = true; }
+
+    // Whether the sweep that ends this collection frees `obj`; only meaningful once tracing is done.
+    pub fn will_sweep(&self, obj: *mut Object) -> bool {
+        unsafe { !((*obj).is_marked || (self.minor && (*obj).is_old)) }
+    }
+
+    pub fn register_weak(&mut self, weak: *mut Object) {
+        self.weak_refs.push(weak);
+    }
+
+    // Weak references don't keep their target alive: once tracing is done, reset the ones whose target
+    // is about to be swept to nil.
+    pub fn clear_weak_refs(&mut self) {
+        for &weak in &self.weak_refs {
+            unsafe {
+                let weak = weak as *mut ObjectWeak;
+                if is_object(&(*weak).target) && self.will_sweep(as_object(&(*weak).target) as *mut Object) {
+                    (*weak).target = make_nil_value();
+                }
+            }
+        }
+    }
+
+    // Forget the weak references the coming sweep frees.
+    fn prune_weak_refs(&mut self) {
+        let weak_refs = std::mem::take(&mut self.weak_refs);
+        self.weak_refs = weak_refs.into_iter().filter(|&weak| !self.will_sweep(weak)).collect();
+    }
+
+    // Trace the remembered old objects; with the roots they reach every live young object.
+    pub fn mark_remembered(&mut self) {
+        for i in 0..self.remembered.len() {
+            unsafe { self.blacken_object(self.remembered[i]); }
+        }
+    }
+
+    // Free unreached young objects and promote the survivors old enough. Promoted objects may still
+    // point at young ones, so they are remembered until the next pruning finds otherwise.
+    // Returns the bytes freed and the bytes promoted.
+    pub fn sweep_nursery(&mut self, object_manager: &mut ObjectManager) -> (usize, usize) {
+        self.prune_weak_refs();
+        self.minor = false;
+        let mut promoted = Vec::new();
+        let freed_bytes = object_manager.sweep_young(PROMOTION_AGE, &mut promoted);
+        let mut promoted_bytes = 0;
+        for obj in promoted {
+            unsafe {
+                promoted_bytes += (*obj).deep_size();
+                if !(*obj).is_remembered {
+                    (*obj).is_remembered = true;
+                    self.remembered.push(obj);
+                }
+            }
+        }
+        self.prune_remembered();
+        gc_trace!("minor sweep freed_bytes={} promoted_bytes={}", freed_bytes, promoted_bytes);
+        (freed_bytes, promoted_bytes)
+    }
+
+    // Drop remembered objects that no longer reference anything young.
+    fn prune_remembered(&mut self) {
+        self.remembered.retain(|&obj| unsafe {
+            let mut young = false;
+            Self::for_each_reference(obj, |child| young |= !child.is_null() && !(*child).is_old);
+            if !young {
+                (*obj).is_remembered = false;
+            }
+            young
+        });
+    }
+
+    pub fn begin_marking(&mut self) { self.marking = true; }
+    pub fn end_marking(&mut self) { self.marking = false; }
+    pub fn is_marking(&self) -> bool { self.marking }
+
+    // Trace at most `budget` gray objects; returns true once no gray objects remain.
+    pub fn step(&mut self, budget: usize) -> bool {
+        for _ in 0..budget {
+            match self.gray_stack.pop() {
+                Some(obj) => unsafe { self.blacken_object(obj) },
+                None => break,
+            }
+        }
+        self.gray_stack.is_empty()
+    }
+
+    // Objects allocated while an incremental cycle was marking were never traced; treat them as live.
+    pub fn mark_allocated_since(&mut self, object_manager: &ObjectManager, start: usize) {
+        for &obj in object_manager.iter().skip(start) {
+            self.mark_object(obj);
+        }
+    }
+
     // Process gray objects until none remain
     pub fn trace_references(&mut self) {
-        while !self.gray_set.is_empty() {
-            let obj = *self.gray_set.iter().next().unwrap();
-            self.gray_set.remove(&obj);
-            self.black_set.insert(obj);
-            
+        while let Some(obj) = self.gray_stack.pop() {
             unsafe {
                 gc_trace!("trace gray -> black ptr={:p}", obj);
                 self.blacken_object(obj);
@@ -101,72 +286,97 @@ impl GarbageCollector {
 
     // Mark all references in an object
     unsafe fn blacken_object(&mut self, object: *mut Object) {
+        Self::for_each_reference(object, |child| self.mark_object(child));
+    }
+
+    // Call `visit` with every object directly referenced by `object`.
+    pub(crate) unsafe fn for_each_reference(object: *mut Object, mut visit: impl FnMut(*mut Object)) {
         match (*object).obj_type {
             ObjectType::ObjClosure => {
                 let closure = (*object).as_closure();
-                // Mark referenced function object
-                self.mark_object(closure.function as *mut Object);
+                // Referenced function object
+                visit(closure.function as *mut Object);
                 // Upvalues holds indices into VM.open_upvalues (usize), NOT object pointers.
                 // The open upvalues list itself is provided as a root by the VM; when closed,
                 // each ObjectUpvalue is reachable via that root set. So nothing to mark here.
                 // for upvalue in &closure.upvalues {
-                //     self.mark_object(*upvalue as *mut Object);
+                //     visit(*upvalue as *mut Object);
                 // }
             }
             ObjectType::ObjFunction => {
                 let function = (*object).as_function();
                 for constant in function.chunk.iter_constants() {
-                    self.mark_value(constant);
+                    visit_value(constant, &mut visit);
                 }
             }
             ObjectType::ObjUpvalue => {
                 let upvalue = (*object).as_upvalue();
-                self.mark_value(&*upvalue.location);
+                visit_value(&*upvalue.location, &mut visit);
             }
             ObjectType::ObjStructType => {
-                // Only owns strings already in intern table; name & field_names are plain Strings (no GC Values)
+                // name & field_names are plain Strings (no GC Values); defaults are closures
+                let stype = (*object).as_struct_type();
+                for default in &stype.field_defaults { visit_value(default, &mut visit); }
             }
             ObjectType::ObjStructInstance => {
                 let inst = (*object).as_struct_instance();
-                self.mark_object(inst.struct_type as *mut Object);
-                for field in &inst.fields { self.mark_value(field); }
+                visit(inst.struct_type as *mut Object);
+                for field in &inst.fields { visit_value(field, &mut visit); }
+            }
+            ObjectType::ObjEnumValue => {
+                let value = (*object).as_enum_value();
+                visit(value.enum_type as *mut Object);
+                for item in &value.payload { visit_value(item, &mut visit); }
+            }
+            ObjectType::ObjList => {
+                let list = (*object).as_list();
+                for item in &list.items { visit_value(item, &mut visit); }
+            }
+            ObjectType::ObjTuple => {
+                let tuple = (*object).as_tuple();
+                for item in &tuple.items { visit_value(item, &mut visit); }
+            }
+            ObjectType::ObjBoundMethod => {
+                let bound = (*object).as_bound_method();
+                visit_value(&bound.receiver, &mut visit);
+                visit_value(&bound.method, &mut visit);
+            }
+            ObjectType::ObjMap => {
+                let map = (*object).as_map();
+                for key in &map.keys { visit_value(key, &mut visit); }
+                for (_, value) in map.entries.iter() { visit_value(value, &mut visit); }
             }
             _ => {}
         }
     }
 
-    // Sweep phase - returns the set of unreachable objects
+    // The intern table doesn't keep strings alive: once tracing is done, drop the entries whose string
+    // is about to be swept (clox's tableRemoveWhite). A minor collection only sweeps young strings.
+    pub fn remove_white_strings(&self, strings: &mut Table) {
+        strings.retain(|_, value| !self.will_sweep(as_object(value) as *mut Object));
+    }
+
+    // Sweep phase - frees unmarked objects and returns the bytes freed
     pub fn sweep(&mut self, object_manager: &mut ObjectManager) -> usize {
-        let mut freed_bytes = 0;
-        for &obj_ptr in self.white_set.iter() {
-            unsafe {
-                // Account for the object's deep size before freeing it
-                freed_bytes += (*obj_ptr).deep_size();
-            }
-            object_manager.remove_object(obj_ptr);
-            unsafe {
-                drop(Box::from_raw(obj_ptr));
-            }
-        }
-        self.white_set.clear();
+        self.remembered.retain(|&obj| unsafe { (*obj).is_marked });
+        self.prune_weak_refs();
+        let freed_bytes = object_manager.sweep_unmarked();
+        self.prune_remembered();
         gc_trace!("sweep freed_bytes={}", freed_bytes);
         freed_bytes
     }
 
     // Reset collector state
     pub fn reset(&mut self) {
-        self.white_set.clear();
-        self.gray_set.clear();
-        self.black_set.clear();
+        self.gray_stack.clear();
     }
 
     // Mark roots provided by the VM
     pub fn mark_roots(&mut self, 
         stack: &[Value], 
         stack_top: usize,
-        globals: &Table,
-        intern_strings: &Table,
-        frames: &[Box<CallFrame>],
+        globals: &Globals,
+        frames: &[CallFrame],
         open_upvalues: &[*mut ObjectUpvalue]) {
         
         // Mark stack values
@@ -174,9 +384,8 @@ impl GarbageCollector {
             self.mark_value(value);
         }
 
-    // Mark globals and interned strings
+    // Mark globals (the intern table is weak, see remove_white_strings)
     for (_, value) in globals.iter() { self.mark_value(value); }
-    for (_, value) in intern_strings.iter() { self.mark_value(value); }
 
         // Mark callframes - we'll mark the stack values which contain
         // the function/closure objects, since they are also stored there
@@ -198,6 +407,11 @@ impl GarbageCollector {
         gc_trace!("cycle summary cycles={} freed={} before={} after={} next_trigger={}", self.stats.cycles, freed, before, after, next_trigger);
     }
 
+    pub fn record_minor_cycle(&mut self, before: usize, freed: usize, after: usize, promoted: usize) {
+        self.stats.record_minor(before, freed, after, promoted);
+        gc_trace!("minor cycle summary minor_cycles={} freed={} before={} after={} promoted={}", self.stats.minor_cycles, freed, before, after, promoted);
+    }
+
     #[allow(dead_code)]
     pub fn stats(&self) -> &GCStats { &self.stats }
 }
@@ -205,10 +419,10 @@ impl GarbageCollector {
     #[cfg(test)]
     mod tests {
         use super::*;
-        use crate::{objects::object_manager::ObjectManager, table::Table, value::{Value, ValueType, ValueUnion}};
+        use crate::{objects::object_manager::ObjectManager, globals::Globals, table::Table, value::{make_object_value, make_string_value, Value}};
 
         fn value_from_object(ptr: *mut Object) -> Value {
-            Value { value_type: ValueType::ValueObject, value_as: ValueUnion { object: ptr } }
+            make_object_value(ptr)
         }
 
         #[test]
@@ -224,13 +438,11 @@ impl GarbageCollector {
             let mut stack = [Value::new(); 8];
             stack[0] = value_from_object(keep as *mut Object);
             let stack_top = 1;
-            let globals = Table::new();
-            let frames: Vec<Box<CallFrame>> = vec![];
+            let globals = Globals::new();
+            let frames: Vec<CallFrame> = vec![];
             let open_upvalues: Vec<*mut ObjectUpvalue> = vec![];
-
-            let intern_strings = Table::new();
-            gc.prepare_collection(&manager);
-            gc.mark_roots(&stack, stack_top, &globals, &intern_strings, &frames, &open_upvalues);
+            gc.prepare_collection();
+            gc.mark_roots(&stack, stack_top, &globals, &frames, &open_upvalues);
             gc.trace_references();
             let freed = gc.sweep(&mut manager);
             assert!(freed > 0, "Expected some bytes to be freed");
@@ -249,13 +461,11 @@ impl GarbageCollector {
             let mut stack = [Value::new(); 8];
             stack[0] = value_from_object(closure_root as *mut Object);
             let stack_top = 1;
-            let globals = Table::new();
-            let frames: Vec<Box<CallFrame>> = vec![];
+            let globals = Globals::new();
+            let frames: Vec<CallFrame> = vec![];
             let open_upvalues: Vec<*mut ObjectUpvalue> = vec![];
-
-            let intern_strings = Table::new();
-            gc.prepare_collection(&manager);
-            gc.mark_roots(&stack, stack_top, &globals, &intern_strings, &frames, &open_upvalues);
+            gc.prepare_collection();
+            gc.mark_roots(&stack, stack_top, &globals, &frames, &open_upvalues);
             gc.trace_references();
             gc.sweep(&mut manager);
             let remaining = manager.iter().count();
@@ -273,19 +483,73 @@ impl GarbageCollector {
             let (upvalue_ptr, _) = manager.alloc_upvalue(&mut stack[0] as *mut Value);
 
             let mut gc = GarbageCollector::new();
-            let globals = Table::new();
-            let frames: Vec<Box<CallFrame>> = vec![];
+            let globals = Globals::new();
+            let frames: Vec<CallFrame> = vec![];
             let open_upvalues: Vec<*mut ObjectUpvalue> = vec![upvalue_ptr];
-
-            let intern_strings = Table::new();
-            gc.prepare_collection(&manager);
-            gc.mark_roots(&stack, stack_top, &globals, &intern_strings, &frames, &open_upvalues);
+            gc.prepare_collection();
+            gc.mark_roots(&stack, stack_top, &globals, &frames, &open_upvalues);
             gc.trace_references();
             gc.sweep(&mut manager);
             let remaining = manager.iter().count();
             assert_eq!(remaining, 2, "Upvalue and captured string should remain");
         }
 
+        #[test]
+        fn gc_sweep_clears_marks_between_cycles() {
+            let mut manager = ObjectManager::new();
+            let (first, _) = manager.alloc_string("first");
+            let (second, _) = manager.alloc_string("second");
+            let (_garbage, _) = manager.alloc_string("garbage");
+
+            let mut gc = GarbageCollector::new();
+            let mut stack = [Value::new(); 8];
+            stack[0] = value_from_object(first as *mut Object);
+            stack[1] = value_from_object(second as *mut Object);
+            let globals = Globals::new();
+            let frames: Vec<CallFrame> = vec![];
+            let open_upvalues: Vec<*mut ObjectUpvalue> = vec![];
+
+            gc.prepare_collection();
+            gc.mark_roots(&stack, 2, &globals, &frames, &open_upvalues);
+            gc.trace_references();
+            gc.sweep(&mut manager);
+            assert_eq!(manager.iter().count(), 2);
+            assert!(manager.iter().all(|&ptr| unsafe { !(*ptr).is_marked }), "Survivors must be unmarked after sweep");
+
+            // Dropping a root makes the object collectable in the next cycle.
+            gc.prepare_collection();
+            gc.mark_roots(&stack, 1, &globals, &frames, &open_upvalues);
+            gc.trace_references();
+            let freed = gc.sweep(&mut manager);
+            assert!(freed > 0);
+            assert_eq!(manager.iter().count(), 1);
+            assert_eq!(*manager.iter().next().unwrap(), first as *mut Object);
+        }
+
+        #[test]
+        fn gc_intern_table_is_weak() {
+            let mut manager = ObjectManager::new();
+            let mut strings = Table::new();
+            let kept = make_string_value(&mut manager, &mut strings, "kept");
+            make_string_value(&mut manager, &mut strings, "dropped");
+
+            let mut gc = GarbageCollector::new();
+            let mut stack = [Value::new(); 8];
+            stack[0] = kept;
+            let globals = Globals::new();
+            let frames: Vec<CallFrame> = vec![];
+            let open_upvalues: Vec<*mut ObjectUpvalue> = vec![];
+
+            gc.prepare_collection();
+            gc.mark_roots(&stack, 1, &globals, &frames, &open_upvalues);
+            gc.trace_references();
+            gc.remove_white_strings(&mut strings);
+            gc.sweep(&mut manager);
+            assert_eq!(manager.iter().count(), 1, "Interned strings must not be roots");
+            assert!(strings.find("dropped").is_none(), "Swept strings must leave the intern table");
+            assert!(strings.find("kept").is_some());
+        }
+
         #[test]
         fn gc_stats_record_cycle() {
             let mut gc = GarbageCollector::new();
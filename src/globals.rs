@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use crate::value::{is_undefined, make_undefined_value, Value};
+
+// Global variables, indexed by slot. The compiler gives each global name a slot the first time it
+// sees it and the global opcodes carry that slot, so reads and writes don't hash the name. A slot
+// holds the undefined sentinel until its name is defined. The name map lives as long as the VM, so
+// later REPL lines and functions compiled before a definition all resolve to the same slot.
+pub struct Globals {
+    values: Vec<Value>,
+    constant: Vec<bool>,
+    names: Vec<String>,
+    slots: HashMap<String, usize>,
+}
+
+#[allow(dead_code)]
+impl Globals {
+    pub fn new() -> Self {
+        Globals { values: Vec::new(), constant: Vec::new(), names: Vec::new(), slots: HashMap::new() }
+    }
+
+    // The slot for `name`, assigning a new undefined one the first time the name is seen.
+    pub fn slot(&mut self, name: &str) -> usize {
+        if let Some(&slot) = self.slots.get(name) {
+            return slot;
+        }
+        let slot = self.values.len();
+        self.values.push(make_undefined_value());
+        self.constant.push(false);
+        self.names.push(name.to_string());
+        self.slots.insert(name.to_string(), slot);
+        slot
+    }
+
+    // The value in `slot`, or None while its name is undefined.
+    #[inline(always)]
+    pub fn get(&self, slot: usize) -> Option<Value> {
+        let value = self.values[slot];
+        if is_undefined(&value) { None } else { Some(value) }
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, slot: usize, value: Value) {
+        self.values[slot] = value;
+    }
+
+    #[inline(always)]
+    pub fn is_defined(&self, slot: usize) -> bool {
+        !is_undefined(&self.values[slot])
+    }
+
+    pub fn is_constant(&self, slot: usize) -> bool {
+        self.constant[slot]
+    }
+
+    pub fn set_constant(&mut self, slot: usize) {
+        self.constant[slot] = true;
+    }
+
+    pub fn name(&self, slot: usize) -> &str {
+        &self.names[slot]
+    }
+
+    pub fn insert(&mut self, name: String, value: Value) {
+        let slot = self.slot(&name);
+        self.values[slot] = value;
+    }
+
+    pub fn find(&self, name: &str) -> Option<Value> {
+        self.slots.get(name).and_then(|&slot| self.get(slot))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    // Defined globals with their names, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.names.iter().zip(self.values.iter()).filter(|(_, value)| !is_undefined(value))
+    }
+}
@@ -0,0 +1,147 @@
+use std::{collections::{BTreeMap, HashMap}, fmt::Write, fs};
+
+use crate::{
+    gc::GarbageCollector,
+    objects::{object::{Object, ObjectType}, object_manager::ObjectManager},
+    value::{as_object, is_object, Value},
+};
+
+/// A value the VM keeps alive, as listed in a heap dump: what holds it ("global", "stack", ...) and a
+/// label such as the global's name or the stack slot.
+pub struct HeapRoot {
+    pub kind: &'static str,
+    pub label: String,
+    pub value: Value,
+}
+
+// Upper bounds (exclusive) of the string length buckets in the summary.
+const STRING_BUCKETS: [(usize, &str); 5] = [(16, "0-15"), (64, "16-63"), (256, "64-255"), (1024, "256-1023"), (usize::MAX, "1024+")];
+
+// Longest string content shown as an object's label.
+const LABEL_CHARS: usize = 40;
+
+#[derive(Default)]
+struct Usage {
+    count: usize,
+    bytes: usize,
+}
+
+/// Write the dump produced by `to_json` to `path`.
+pub fn write_json(path: &str, objects: &ObjectManager, roots: &[HeapRoot]) -> Result<(), String> {
+    fs::write(path, to_json(objects, roots)).map_err(|e| format!("Could not write heap dump to '{}': {}", path, e))
+}
+
+/// Every managed object (id, type, deep size, label, ids of the objects it references), the roots
+/// that point into the heap, and a summary of bytes by object type, struct type and string length.
+/// Ids are positions in `ObjectManager::iter()` order.
+pub fn to_json(objects: &ObjectManager, roots: &[HeapRoot]) -> String {
+    let ids: HashMap<*mut Object, usize> = objects.iter().enumerate().map(|(id, &ptr)| (ptr, id)).collect();
+    let mut by_type: BTreeMap<String, Usage> = BTreeMap::new();
+    let mut by_struct: BTreeMap<String, Usage> = BTreeMap::new();
+    let mut by_length: Vec<Usage> = STRING_BUCKETS.iter().map(|_| Usage::default()).collect();
+    let mut total_bytes = 0;
+
+    let mut out = String::from("{\n  \"objects\": [");
+    for (id, &ptr) in objects.iter().enumerate() {
+        let obj_type = unsafe { (*ptr).obj_type };
+        let size = unsafe { (*ptr).deep_size() };
+        let mut refs = Vec::new();
+        unsafe { GarbageCollector::for_each_reference(ptr, |child| if let Some(&child_id) = ids.get(&child) { refs.push(child_id.to_string()) }) };
+        let label = unsafe { object_label(ptr) };
+        let _ = write!(out, "{}\n    {{\"id\": {}, \"type\": \"{:?}\", \"size\": {}, \"label\": {}, \"refs\": [{}]}}",
+            if id == 0 { "" } else { "," }, id, obj_type, size, json_string(&label), refs.join(", "));
+
+        total_bytes += size;
+        add(by_type.entry(format!("{:?}", obj_type)).or_default(), size);
+        match obj_type {
+            ObjectType::ObjStructInstance => add(by_struct.entry(label).or_default(), size),
+            ObjectType::ObjString => {
+                let len = unsafe { (*ptr).as_string().content.len() };
+                let bucket = STRING_BUCKETS.iter().position(|(limit, _)| len < *limit).unwrap();
+                add(&mut by_length[bucket], size);
+            }
+            _ => {}
+        }
+    }
+    out.push_str("\n  ],\n  \"roots\": [");
+    let mut first = true;
+    for root in roots.iter().filter(|root| is_object(&root.value)) {
+        let Some(id) = ids.get(&(as_object(&root.value) as *mut Object)) else { continue };
+        let _ = write!(out, "{}\n    {{\"kind\": \"{}\", \"label\": {}, \"id\": {}}}", if first { "" } else { "," }, root.kind, json_string(&root.label), id);
+        first = false;
+    }
+    let _ = write!(out, "\n  ],\n  \"summary\": {{\n    \"objects\": {},\n    \"bytes\": {},", ids.len(), total_bytes);
+    let by_length: Vec<(String, Usage)> = STRING_BUCKETS.iter().zip(by_length).map(|((_, name), usage)| (name.to_string(), usage)).collect();
+    let _ = write!(out, "\n    \"by_type\": {},", usage_json(by_type));
+    let _ = write!(out, "\n    \"by_struct_type\": {},", usage_json(by_struct));
+    let _ = write!(out, "\n    \"by_string_length\": {}\n  }}\n}}\n", usage_json(by_length));
+    out
+}
+
+fn add(usage: &mut Usage, bytes: usize) {
+    usage.count += 1;
+    usage.bytes += bytes;
+}
+
+fn usage_json(groups: impl IntoIterator<Item = (String, Usage)>) -> String {
+    let entries: Vec<String> = groups.into_iter()
+        .map(|(name, usage)| format!("{}: {{\"count\": {}, \"bytes\": {}}}", json_string(&name), usage.count, usage.bytes))
+        .collect();
+    format!("{{{}}}", entries.join(", "))
+}
+
+// A short human-readable name: string contents, function and type names, an instance's struct type.
+unsafe fn object_label(ptr: *mut Object) -> String {
+    match (*ptr).obj_type {
+        ObjectType::ObjString => (*ptr).as_string().content.chars().take(LABEL_CHARS).collect(),
+        ObjectType::ObjFunction => (*ptr).as_function().name.clone(),
+        ObjectType::ObjNativeFunction => (*ptr).as_native_function().name.clone(),
+        ObjectType::ObjClosure => (*(*ptr).as_closure().function).name.clone(),
+        ObjectType::ObjStructType => (*ptr).as_struct_type().name.clone(),
+        ObjectType::ObjStructInstance => (*(*ptr).as_struct_instance().struct_type).name.clone(),
+        ObjectType::ObjEnumType => (*ptr).as_enum_type().name.clone(),
+        ObjectType::ObjEnumValue => (*(*ptr).as_enum_value().enum_type).name.clone(),
+        ObjectType::ObjTrait => (*ptr).as_trait().name.clone(),
+        _ => String::new(),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::make_object_value;
+
+    #[test]
+    fn heap_dump_lists_references_and_roots() {
+        let mut manager = ObjectManager::new();
+        let (name, _) = manager.alloc_string("a \"quoted\" name");
+        let name_value = make_object_value(name as *mut Object);
+        let (list, _) = manager.alloc_list(vec![name_value, name_value]);
+        let list_value = make_object_value(list as *mut Object);
+        let roots = [HeapRoot { kind: "global", label: "names".to_string(), value: list_value }];
+
+        let json = to_json(&manager, &roots);
+        assert!(json.contains(r#"{"id": 0, "type": "ObjString", "#), "{}", json);
+        assert!(json.contains(r#""label": "a \"quoted\" name", "refs": []"#), "{}", json);
+        assert!(json.contains(r#""type": "ObjList", "#) && json.contains(r#""refs": [0, 0]"#), "{}", json);
+        assert!(json.contains(r#"{"kind": "global", "label": "names", "id": 1}"#), "{}", json);
+        assert!(json.contains(r#""objects": 2,"#));
+    }
+}
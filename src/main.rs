@@ -12,9 +12,11 @@ mod vm;
 mod scanner;
 mod compiler;
 mod table;
+mod globals;
 mod call_frame;
 mod constants;
 mod gc;
+mod heap_dump;
 
 
 fn repl() {
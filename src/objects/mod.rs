@@ -7,3 +7,10 @@ pub mod object_closure;
 pub mod object_upvalue;
 pub mod object_trait;
 pub mod object_struct;
+pub mod object_enum;
+pub mod object_list;
+pub mod object_map;
+pub mod object_range;
+pub mod object_bound_method;
+pub mod object_tuple;
+pub mod object_weak;
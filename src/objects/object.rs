@@ -10,10 +10,18 @@ use super::{
     object_upvalue::ObjectUpvalue,
     object_trait::ObjectTrait,
     object_struct::{ObjectStructType, ObjectStructInstance},
+    object_enum::{ObjectEnumType, ObjectEnumValue},
+    object_list::ObjectList,
+    object_map::ObjectMap,
+    object_range::ObjectRange,
+    object_bound_method::ObjectBoundMethod,
+    object_tuple::ObjectTuple,
+    object_weak::ObjectWeak,
 };
 
 #[repr(C)]
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 pub enum ObjectType {
     ObjString,
     ObjFunction,
@@ -23,12 +31,29 @@ pub enum ObjectType {
     ObjTrait,
     ObjStructType,
     ObjStructInstance,
+    ObjEnumType,
+    ObjEnumValue,
+    ObjList,
+    ObjMap,
+    ObjRange,
+    ObjBoundMethod,
+    ObjTuple,
+    ObjWeak,
 }
 
 #[repr(C)]
-#[derive(Hash, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct Object {
     pub obj_type: ObjectType,
+    // Set while tracing reachable objects; the sweep frees unmarked objects and clears the rest.
+    pub is_marked: bool,
+    // Generational collection: minor collections survived while young, whether the object has been
+    // promoted to the old generation, and whether it is already in the remembered set.
+    pub age: u8,
+    pub is_old: bool,
+    pub is_remembered: bool,
+    // A struct instance whose drop() has been queued; it is never finalized again.
+    pub is_finalized: bool,
 }
 
 pub trait NativeObject {
@@ -36,6 +61,10 @@ pub trait NativeObject {
 }
 
 impl Object {
+    pub fn new(obj_type: ObjectType) -> Self {
+        Object { obj_type, is_marked: false, age: 0, is_old: false, is_remembered: false, is_finalized: false }
+    }
+
     /// Shallow size (header only) – mainly for debugging.
     #[allow(dead_code)]
     pub fn shallow_size(&self) -> usize { size_of::<Object>() }
@@ -52,6 +81,38 @@ impl Object {
             ObjectType::ObjTrait => (*(self as *const _ as *const ObjectTrait)).deep_size(),
             ObjectType::ObjStructType => (*(self as *const _ as *const ObjectStructType)).deep_size(),
             ObjectType::ObjStructInstance => (*(self as *const _ as *const ObjectStructInstance)).deep_size(),
+            ObjectType::ObjEnumType => (*(self as *const _ as *const ObjectEnumType)).deep_size(),
+            ObjectType::ObjEnumValue => (*(self as *const _ as *const ObjectEnumValue)).deep_size(),
+            ObjectType::ObjList => (*(self as *const _ as *const ObjectList)).deep_size(),
+            ObjectType::ObjMap => (*(self as *const _ as *const ObjectMap)).deep_size(),
+            ObjectType::ObjRange => (*(self as *const _ as *const ObjectRange)).deep_size(),
+            ObjectType::ObjBoundMethod => (*(self as *const _ as *const ObjectBoundMethod)).deep_size(),
+            ObjectType::ObjTuple => (*(self as *const _ as *const ObjectTuple)).deep_size(),
+            ObjectType::ObjWeak => (*(self as *const _ as *const ObjectWeak)).deep_size(),
+        }
+    }
+
+    /// Drop the concrete object behind a header pointer allocated by `ObjectManager`.
+    /// # Safety
+    /// `ptr` must come from one of the `alloc_*` methods and must not be used afterwards.
+    pub unsafe fn free(ptr: *mut Object) {
+        match (*ptr).obj_type {
+            ObjectType::ObjString => drop(Box::from_raw(ptr as *mut ObjectString)),
+            ObjectType::ObjFunction => drop(Box::from_raw(ptr as *mut ObjectFunction)),
+            ObjectType::ObjNativeFunction => drop(Box::from_raw(ptr as *mut ObjectNativeFunction)),
+            ObjectType::ObjClosure => drop(Box::from_raw(ptr as *mut ObjectClosure)),
+            ObjectType::ObjUpvalue => drop(Box::from_raw(ptr as *mut ObjectUpvalue)),
+            ObjectType::ObjTrait => drop(Box::from_raw(ptr as *mut ObjectTrait)),
+            ObjectType::ObjStructType => drop(Box::from_raw(ptr as *mut ObjectStructType)),
+            ObjectType::ObjStructInstance => drop(Box::from_raw(ptr as *mut ObjectStructInstance)),
+            ObjectType::ObjEnumType => drop(Box::from_raw(ptr as *mut ObjectEnumType)),
+            ObjectType::ObjEnumValue => drop(Box::from_raw(ptr as *mut ObjectEnumValue)),
+            ObjectType::ObjList => drop(Box::from_raw(ptr as *mut ObjectList)),
+            ObjectType::ObjMap => drop(Box::from_raw(ptr as *mut ObjectMap)),
+            ObjectType::ObjRange => drop(Box::from_raw(ptr as *mut ObjectRange)),
+            ObjectType::ObjBoundMethod => drop(Box::from_raw(ptr as *mut ObjectBoundMethod)),
+            ObjectType::ObjTuple => drop(Box::from_raw(ptr as *mut ObjectTuple)),
+            ObjectType::ObjWeak => drop(Box::from_raw(ptr as *mut ObjectWeak)),
         }
     }
 
@@ -75,10 +136,27 @@ impl Object {
     #[cfg_attr(not(feature = "gc_debug"), allow(dead_code))]
     pub unsafe fn as_trait(&self) -> &ObjectTrait { debug_assert!(matches!(self.obj_type, ObjectType::ObjTrait)); &*(self as *const _ as *const ObjectTrait) }
     #[inline]
-    #[allow(dead_code)]
     pub unsafe fn as_struct_type(&self) -> &ObjectStructType { debug_assert!(matches!(self.obj_type, ObjectType::ObjStructType)); &*(self as *const _ as *const ObjectStructType) }
     #[inline]
     pub unsafe fn as_struct_instance(&self) -> &ObjectStructInstance { debug_assert!(matches!(self.obj_type, ObjectType::ObjStructInstance)); &*(self as *const _ as *const ObjectStructInstance) }
+    #[inline]
+    pub unsafe fn as_enum_type(&self) -> &ObjectEnumType { debug_assert!(matches!(self.obj_type, ObjectType::ObjEnumType)); &*(self as *const _ as *const ObjectEnumType) }
+    #[inline]
+    pub unsafe fn as_enum_value(&self) -> &ObjectEnumValue { debug_assert!(matches!(self.obj_type, ObjectType::ObjEnumValue)); &*(self as *const _ as *const ObjectEnumValue) }
+    #[inline]
+    pub unsafe fn as_list(&self) -> &ObjectList { debug_assert!(matches!(self.obj_type, ObjectType::ObjList)); &*(self as *const _ as *const ObjectList) }
+    #[inline]
+    pub unsafe fn as_map(&self) -> &ObjectMap { debug_assert!(matches!(self.obj_type, ObjectType::ObjMap)); &*(self as *const _ as *const ObjectMap) }
+    #[inline]
+    #[allow(dead_code)]
+    pub unsafe fn as_range(&self) -> &ObjectRange { debug_assert!(matches!(self.obj_type, ObjectType::ObjRange)); &*(self as *const _ as *const ObjectRange) }
+    #[inline]
+    pub unsafe fn as_bound_method(&self) -> &ObjectBoundMethod { debug_assert!(matches!(self.obj_type, ObjectType::ObjBoundMethod)); &*(self as *const _ as *const ObjectBoundMethod) }
+    #[inline]
+    pub unsafe fn as_tuple(&self) -> &ObjectTuple { debug_assert!(matches!(self.obj_type, ObjectType::ObjTuple)); &*(self as *const _ as *const ObjectTuple) }
+    #[inline]
+    #[cfg_attr(not(feature = "gc_debug"), allow(dead_code))]
+    pub unsafe fn as_weak(&self) -> &ObjectWeak { debug_assert!(matches!(self.obj_type, ObjectType::ObjWeak)); &*(self as *const _ as *const ObjectWeak) }
 }
 
 impl PartialEq for Object {
@@ -112,8 +190,9 @@ impl GcSize for ObjectString {
 impl GcSize for ObjectFunction {
     fn shallow_size(&self) -> usize { size_of::<ObjectFunction>() }
     fn deep_size(&self) -> usize {
-        // name capacity + chunk deep size (Box<Chunk> heap)
-        let name_bytes = self.name.capacity();
+        // name capacity + parameter names + chunk deep size (Box<Chunk> heap)
+        let name_bytes = self.name.capacity()
+            + self.param_names.iter().map(|n| size_of::<String>() + n.capacity()).sum::<usize>();
         let chunk_bytes = self.chunk.deep_size();
         self.shallow_size() + name_bytes + chunk_bytes
     }
@@ -144,7 +223,9 @@ impl GcSize for ObjectUpvalue {
 impl GcSize for ObjectTrait {
     fn shallow_size(&self) -> usize { size_of::<ObjectTrait>() }
     fn deep_size(&self) -> usize {
-        self.shallow_size() + self.name.capacity() + self.method_names.iter().map(|s| s.capacity()).sum::<usize>()
+        self.shallow_size() + self.name.capacity()
+            + self.method_names.iter().map(|s| s.capacity()).sum::<usize>()
+            + self.supertraits.iter().map(|s| s.capacity()).sum::<usize>()
     }
 }
 
@@ -153,7 +234,8 @@ impl GcSize for ObjectStructType {
     fn deep_size(&self) -> usize {
     // Approximate table memory: number of entries * (string capacity + Value size)
     let table_bytes = self.field_index.iter().map(|(k, _)| k.capacity() + size_of::<crate::value::Value>()).sum::<usize>();
-    self.shallow_size() + self.name.capacity() + self.field_names.iter().map(|s| s.capacity()).sum::<usize>() + table_bytes
+    let defaults_bytes = self.field_defaults.capacity() * size_of::<crate::value::Value>();
+    self.shallow_size() + self.name.capacity() + self.field_names.iter().map(|s| s.capacity()).sum::<usize>() + table_bytes + defaults_bytes
     }
 }
 
@@ -165,6 +247,59 @@ impl GcSize for ObjectStructInstance {
     }
 }
 
+impl GcSize for ObjectEnumType {
+    fn shallow_size(&self) -> usize { size_of::<ObjectEnumType>() }
+    fn deep_size(&self) -> usize {
+        let variant_bytes = self.variants.iter().map(|v| size_of::<super::object_enum::EnumVariant>() + v.name.capacity()).sum::<usize>();
+        self.shallow_size() + self.name.capacity() + variant_bytes
+    }
+}
+
+impl GcSize for ObjectEnumValue {
+    fn shallow_size(&self) -> usize { size_of::<ObjectEnumValue>() }
+    fn deep_size(&self) -> usize {
+        self.shallow_size() + self.payload.capacity() * size_of::<crate::value::Value>()
+    }
+}
+
+impl GcSize for ObjectList {
+    fn shallow_size(&self) -> usize { size_of::<ObjectList>() }
+    fn deep_size(&self) -> usize {
+        self.shallow_size() + self.items.capacity() * size_of::<crate::value::Value>()
+    }
+}
+
+impl GcSize for ObjectTuple {
+    fn shallow_size(&self) -> usize { size_of::<ObjectTuple>() }
+    fn deep_size(&self) -> usize {
+        self.shallow_size() + self.items.capacity() * size_of::<crate::value::Value>()
+    }
+}
+
+impl GcSize for ObjectWeak {
+    fn shallow_size(&self) -> usize { size_of::<ObjectWeak>() }
+    fn deep_size(&self) -> usize { self.shallow_size() }
+}
+
+impl GcSize for ObjectRange {
+    fn shallow_size(&self) -> usize { size_of::<ObjectRange>() }
+    fn deep_size(&self) -> usize { self.shallow_size() }
+}
+
+impl GcSize for ObjectBoundMethod {
+    fn shallow_size(&self) -> usize { size_of::<ObjectBoundMethod>() }
+    fn deep_size(&self) -> usize { self.shallow_size() }
+}
+
+impl GcSize for ObjectMap {
+    fn shallow_size(&self) -> usize { size_of::<ObjectMap>() }
+    fn deep_size(&self) -> usize {
+        // Same approximation as struct field tables: key string capacity + Value per entry.
+        let table_bytes = self.entries.iter().map(|(k, _)| k.capacity() + size_of::<crate::value::Value>()).sum::<usize>();
+        self.shallow_size() + self.keys.capacity() * size_of::<crate::value::Value>() + table_bytes
+    }
+}
+
 
 //#[cfg(feature = "debug_trace_object")]
 //  mod debug_feature {
@@ -0,0 +1,15 @@
+use crate::{objects::object::{Object, ObjectType}, value::Value};
+
+/// `receiver.method` taken without calling it; calling the value later passes `receiver` as self.
+#[repr(C)]
+pub struct ObjectBoundMethod {
+    pub object: Object,
+    pub receiver: Value,
+    pub method: Value,
+}
+
+impl ObjectBoundMethod {
+    pub fn new(receiver: Value, method: Value) -> Self {
+        Self { object: Object::new(ObjectType::ObjBoundMethod), receiver, method }
+    }
+}
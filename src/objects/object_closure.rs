@@ -16,9 +16,7 @@ pub struct ObjectClosure {
 impl ObjectClosure {
     pub fn new(function: *mut ObjectFunction) -> Self {
         ObjectClosure {
-            object: Object {
-                obj_type: ObjectType::ObjClosure,
-            },
+            object: Object::new(ObjectType::ObjClosure),
             function,
             upvalues: Vec::new(),
         }
@@ -43,6 +41,7 @@ impl ObjectClosure {
     }
 }
 
+#[cfg(feature = "debug_trace_object")]
 mod debug_feature {
     use crate::objects::object_closure::ObjectClosure;
 
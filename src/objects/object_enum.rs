@@ -0,0 +1,37 @@
+use crate::{objects::object::{Object, ObjectType}, value::Value};
+
+pub struct EnumVariant {
+    pub name: String,
+    pub arity: usize,
+}
+
+#[repr(C)]
+pub struct ObjectEnumType {
+    pub object: Object,
+    pub name: String,
+    pub variants: Vec<EnumVariant>, // index = variant tag
+}
+
+impl ObjectEnumType {
+    pub fn new(name: String) -> Self {
+        Self { object: Object::new(ObjectType::ObjEnumType), name, variants: Vec::new() }
+    }
+
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|v| v.name == name)
+    }
+}
+
+#[repr(C)]
+pub struct ObjectEnumValue {
+    pub object: Object,
+    pub enum_type: *mut ObjectEnumType,
+    pub variant: usize,
+    pub payload: Vec<Value>, // length = variants[variant].arity
+}
+
+impl ObjectEnumValue {
+    pub fn new(enum_type: *mut ObjectEnumType, variant: usize, payload: Vec<Value>) -> Self {
+        Self { object: Object::new(ObjectType::ObjEnumValue), enum_type, variant, payload }
+    }
+}
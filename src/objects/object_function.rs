@@ -4,7 +4,11 @@ use crate::{chunk::Chunk, objects::object::{Object, ObjectType}};
 #[derive(Clone)]
 pub struct ObjectFunction {
     pub object: Object,
-    pub arity: u8,
+    pub arity: u8,              // positional parameters, including those with defaults
+    pub min_arity: u8,          // parameters without a default value
+    pub has_rest: bool,         // a trailing ...rest parameter collects extra arguments into a list
+    pub param_names: Vec<String>, // positional parameter names, used to match named arguments
+    pub method_of: Option<String>, // struct type whose impl defines this function (or encloses it)
     pub chunk: Box<Chunk>,
     pub name: String,
     pub upvalue_count: usize,
@@ -14,22 +18,25 @@ pub struct ObjectFunction {
 impl ObjectFunction {
     pub fn new(arity: u8, name: String) -> Self {
         ObjectFunction {
-            object: Object {
-                obj_type: ObjectType::ObjFunction,
-            },
+            object: Object::new(ObjectType::ObjFunction),
             arity,
+            min_arity: arity,
+            has_rest: false,
+            param_names: Vec::new(),
+            method_of: None,
             chunk: Box::new(Chunk::new()),
             name,
             upvalue_count: 0,
         }
     }
 
-    pub fn chunk(&self) -> &Box<Chunk> {
+    pub fn chunk(&self) -> &Chunk {
         &self.chunk
     }
 }
 
 
+#[cfg(feature = "debug_trace_object")]
 mod debug_feature {
     use crate::objects::object_function::ObjectFunction;
 
@@ -0,0 +1,13 @@
+use crate::{objects::object::{Object, ObjectType}, value::Value};
+
+#[repr(C)]
+pub struct ObjectList {
+    pub object: Object,
+    pub items: Vec<Value>,
+}
+
+impl ObjectList {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self { object: Object::new(ObjectType::ObjList), items }
+    }
+}
@@ -3,15 +3,25 @@ use crate::objects::{
     object_string::ObjectString,
     object_function::ObjectFunction,
     object_closure::ObjectClosure,
-    object_native_function::ObjectNativeFunction,
+    object_native_function::{ObjectNativeFunction, VmNative},
     object_upvalue::ObjectUpvalue,
     object_trait::ObjectTrait,
     object_struct::{ObjectStructType, ObjectStructInstance},
+    object_enum::{ObjectEnumType, ObjectEnumValue},
+    object_list::ObjectList,
+    object_map::ObjectMap,
+    object_bound_method::ObjectBoundMethod,
+    object_tuple::ObjectTuple,
+    object_weak::ObjectWeak,
+    object_range::ObjectRange,
 };
 
 #[allow(dead_code)]
 pub struct ObjectManager {
+    // Old objects first, then young ones in allocation order; new objects are young.
     objects: Vec<*mut Object>,
+    // Index of the first young object.
+    young_start: usize,
     // Bytes allocated since last drain (deep size of each object when added)
     pending_bytes: usize,
 }
@@ -21,6 +31,7 @@ impl ObjectManager {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
+            young_start: 0,
             pending_bytes: 0,
         }
     }
@@ -34,7 +45,7 @@ impl ObjectManager {
 
     /// Push a newly allocated object pointer, record its deep size, and return that size.
     pub fn push_object(&mut self, obj: *mut Object) -> usize {
-        let size = unsafe { (*obj).deep_size() } as usize;
+        let size = unsafe { (*obj).deep_size() };
         self.pending_bytes += size;
         self.objects.push(obj);
         size
@@ -68,6 +79,13 @@ impl ObjectManager {
         (ptr, size)
     }
 
+    pub fn alloc_vm_native_function(&mut self, name: String, arity: usize, function: VmNative) -> (*mut ObjectNativeFunction, usize) {
+        let obj = Box::new(ObjectNativeFunction::new_vm(name, arity as u8, function));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
     pub fn alloc_upvalue(&mut self, location: *mut crate::value::Value) -> (*mut ObjectUpvalue, usize) {
         let obj = Box::new(ObjectUpvalue::new(location));
         let ptr = Box::into_raw(obj);
@@ -96,21 +114,124 @@ impl ObjectManager {
         (ptr, size)
     }
 
+    pub fn alloc_enum_type(&mut self, name: String) -> (*mut ObjectEnumType, usize) {
+        let obj = Box::new(ObjectEnumType::new(name));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn alloc_enum_value(&mut self, enum_type: *mut ObjectEnumType, variant: usize, payload: Vec<crate::value::Value>) -> (*mut ObjectEnumValue, usize) {
+        let obj = Box::new(ObjectEnumValue::new(enum_type, variant, payload));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn alloc_list(&mut self, items: Vec<crate::value::Value>) -> (*mut ObjectList, usize) {
+        let obj = Box::new(ObjectList::new(items));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn alloc_tuple(&mut self, items: Vec<crate::value::Value>) -> (*mut ObjectTuple, usize) {
+        let obj = Box::new(ObjectTuple::new(items));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn alloc_map(&mut self) -> (*mut ObjectMap, usize) {
+        let obj = Box::new(ObjectMap::new());
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn alloc_range(&mut self, start: f64, end: f64, inclusive: bool) -> (*mut ObjectRange, usize) {
+        let obj = Box::new(ObjectRange::new(start, end, inclusive));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn alloc_bound_method(&mut self, receiver: crate::value::Value, method: crate::value::Value) -> (*mut ObjectBoundMethod, usize) {
+        let obj = Box::new(ObjectBoundMethod::new(receiver, method));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn alloc_weak(&mut self, target: crate::value::Value) -> (*mut ObjectWeak, usize) {
+        let obj = Box::new(ObjectWeak::new(target));
+        let ptr = Box::into_raw(obj);
+        let size = self.push_object(ptr as *mut Object);
+        (ptr, size)
+    }
+
+    pub fn len(&self) -> usize { self.objects.len() }
+    pub fn is_empty(&self) -> bool { self.objects.is_empty() }
+
     /// Iterate over all managed objects (for GC mark/sweep)
     pub fn iter(&self) -> impl Iterator<Item = &*mut Object> { self.objects.iter() }
 
-    /// Remove a pointer from the manager (optional, for GC sweep)
-    pub fn remove_object(&mut self, ptr: *mut Object) {
-        if let Some(pos) = self.objects.iter().position(|&p| p == ptr) {
-            self.objects.swap_remove(pos);
+    /// Free every unmarked object and clear the mark on survivors, compacting the list in one pass.
+    /// Returns the bytes freed.
+    pub fn sweep_unmarked(&mut self) -> usize {
+        let mut freed_bytes = 0;
+        let mut old_survivors = 0;
+        self.objects.retain(|&ptr| unsafe {
+            if (*ptr).is_marked {
+                (*ptr).is_marked = false;
+                if (*ptr).is_old { old_survivors += 1; }
+                return true;
+            }
+            // Account for the object's deep size before freeing it
+            freed_bytes += (*ptr).deep_size();
+            Object::free(ptr);
+            false
+        });
+        // Retaining keeps the order, so the old objects are still in front.
+        self.young_start = old_survivors;
+        freed_bytes
+    }
+
+    /// Sweep only the young objects: free the unmarked ones, and age the survivors, promoting those
+    /// that reach `promote_age` into the old generation (their pointers are pushed to `promoted`).
+    /// Returns the bytes freed.
+    pub fn sweep_young(&mut self, promote_age: u8, promoted: &mut Vec<*mut Object>) -> usize {
+        let mut freed_bytes = 0;
+        let young = self.objects.split_off(self.young_start);
+        let mut still_young = Vec::with_capacity(young.len());
+        for ptr in young {
+            unsafe {
+                if !(*ptr).is_marked {
+                    freed_bytes += (*ptr).deep_size();
+                    Object::free(ptr);
+                    continue;
+                }
+                (*ptr).is_marked = false;
+                (*ptr).age += 1;
+                if (*ptr).age >= promote_age {
+                    (*ptr).is_old = true;
+                    self.objects.push(ptr);
+                    promoted.push(ptr);
+                } else {
+                    still_young.push(ptr);
+                }
+            }
         }
+        self.young_start = self.objects.len();
+        self.objects.extend(still_young);
+        freed_bytes
     }
 
     /// Deallocate all objects (for VM shutdown or full sweep)
     pub unsafe fn free_all(&mut self) {
         for &ptr in &self.objects {
             if !ptr.is_null() {
-                drop(Box::from_raw(ptr));
+                Object::free(ptr);
             }
         }
         self.objects.clear();
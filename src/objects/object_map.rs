@@ -0,0 +1,27 @@
+use crate::{objects::object::{Object, ObjectType}, table::Table, value::Value};
+
+/// String-keyed map. `keys` keeps the interned key strings in insertion order so
+/// iteration is deterministic; `entries` does the lookups.
+#[repr(C)]
+pub struct ObjectMap {
+    pub object: Object,
+    pub keys: Vec<Value>,
+    pub entries: Table,
+}
+
+impl ObjectMap {
+    pub fn new() -> Self {
+        Self { object: Object::new(ObjectType::ObjMap), keys: Vec::new(), entries: Table::new() }
+    }
+
+    /// `key` must be the string object for `name`.
+    pub fn insert(&mut self, key: Value, name: &str, value: Value) {
+        if self.entries.insert(name.to_string(), value).is_none() {
+            self.keys.push(key);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.entries.find(name)
+    }
+}
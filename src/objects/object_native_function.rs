@@ -1,37 +1,51 @@
-use crate::{objects::object::{NativeObject, Object, ObjectType}, value::{Value, ValueArray}};
+use crate::{objects::object::{NativeObject, Object, ObjectType}, value::{Value, ValueArray}, vm::VM};
+
+// Natives that need the VM itself (type registries, the heap) instead of just their arguments.
+pub type VmNative = fn(&mut VM, &[Value]) -> Result<Value, String>;
+
+pub enum NativeCallable {
+    Object(Box<dyn NativeObject>),
+    Vm(VmNative),
+}
 
 #[repr(C)]
 pub struct ObjectNativeFunction {
     pub object: Object,
     pub name: String,
     pub arity: u8,
-    pub native_object: Box<dyn NativeObject>,
+    pub callable: NativeCallable,
 }
 
 impl ObjectNativeFunction {
     pub fn new(name: String, arity: u8, native_object: impl NativeObject + 'static) -> Self {
+        Self::with_callable(name, arity, NativeCallable::Object(Box::new(native_object)))
+    }
+
+    pub fn new_vm(name: String, arity: u8, function: VmNative) -> Self {
+        Self::with_callable(name, arity, NativeCallable::Vm(function))
+    }
+
+    fn with_callable(name: String, arity: u8, callable: NativeCallable) -> Self {
         ObjectNativeFunction {
-            object: Object {
-                obj_type: ObjectType::ObjNativeFunction
-            },
+            object: Object::new(ObjectType::ObjNativeFunction),
             name,
             arity,
-            native_object: Box::new(native_object),
+            callable,
         }
     }
 
-    pub fn invoke(&self, args: &Option<ValueArray>) -> Result<Value, String> {
-        if self.arity > 0 {
-            match args {
-                Some(_) => {
-                },
-                None => return Err(std::format!("Expect {} arguments but got 0.", self.arity).to_string()),
-            }
+    pub fn invoke(&self, vm: &mut VM, args: ValueArray) -> Result<Value, String> {
+        if args.len() != self.arity as usize {
+            return Err(std::format!("Expect {} arguments but got {}.", self.arity, args.len()));
+        }
+        match &self.callable {
+            NativeCallable::Object(native_object) => native_object.run(&Some(args)),
+            NativeCallable::Vm(function) => function(vm, &args),
         }
-        self.native_object.run(args)
     }
 }
 
+#[cfg(feature = "debug_trace_object")]
 mod debug_feature {
     use crate::objects::object_native_function::ObjectNativeFunction;
 
@@ -0,0 +1,21 @@
+use crate::objects::object::{Object, ObjectType};
+
+/// Immutable numeric range produced by `start..end` or `start..=end`.
+#[repr(C)]
+pub struct ObjectRange {
+    pub object: Object,
+    pub start: f64,
+    pub end: f64,
+    pub inclusive: bool,
+}
+
+impl ObjectRange {
+    pub fn new(start: f64, end: f64, inclusive: bool) -> Self {
+        Self { object: Object::new(ObjectType::ObjRange), start, end, inclusive }
+    }
+
+    /// Whether `value` lies inside the range; iteration walks start, start + 1, ... while this holds.
+    pub fn contains(&self, value: f64) -> bool {
+        value >= self.start && if self.inclusive { value <= self.end } else { value < self.end }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::objects::object::{Object, ObjectType};
 
 #[repr(C)]
-#[derive(Hash, Clone)]
+#[derive(Clone)]
 pub struct ObjectString {
     pub object: Object,
     pub content: String,
@@ -10,9 +10,7 @@ pub struct ObjectString {
 impl ObjectString {
     pub fn new(content: &str) -> Self {
         let s = ObjectString{
-            object: Object {
-                    obj_type: ObjectType::ObjString,
-                },
+            object: Object::new(ObjectType::ObjString),
             content: content.to_string()
         };
         //println!("new string object: {}, addr: {:p}", content, &s);
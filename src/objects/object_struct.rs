@@ -1,5 +1,13 @@
 use crate::{objects::object::{Object, ObjectType}, table::Table, value::Value};
 
+// Who may use a field outside the type's own impl methods.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldAccess {
+    Public,
+    ReadOnly, // readable everywhere, writable only in impl methods
+    Private,  // readable and writable only in impl methods
+}
+
 #[repr(C)]
 //#[derive(Clone)]
 pub struct ObjectStructType {
@@ -7,11 +15,13 @@ pub struct ObjectStructType {
     pub name: String,
     pub field_names: Vec<String>, // index = field slot
     pub field_index: Table,        // name -> numeric Value index
+    pub field_defaults: Vec<Value>, // per slot: closure computing the default, or nil if the field is required
+    pub field_access: Vec<FieldAccess>, // per slot
 }
 
 impl ObjectStructType {
     pub fn new(name: String) -> Self {
-        Self { object: Object { obj_type: ObjectType::ObjStructType }, name, field_names: Vec::new(), field_index: Table::new() }
+        Self { object: Object::new(ObjectType::ObjStructType), name, field_names: Vec::new(), field_index: Table::new(), field_defaults: Vec::new(), field_access: Vec::new() }
     }
 }
 
@@ -25,6 +35,6 @@ pub struct ObjectStructInstance {
 
 impl ObjectStructInstance {
     pub fn new(struct_type: *mut ObjectStructType, field_count: usize) -> Self {
-        Self { object: Object { obj_type: ObjectType::ObjStructInstance }, struct_type, fields: vec![Value::new(); field_count] }
+        Self { object: Object::new(ObjectType::ObjStructInstance), struct_type, fields: vec![Value::new(); field_count] }
     }
 }
\ No newline at end of file
@@ -6,10 +6,11 @@ pub struct ObjectTrait {
     pub object: Object,
     pub name: String,
     pub method_names: Vec<String>, // signatures tracked later
+    pub supertraits: Vec<String>, // trait Shape: Display + Eq
 }
 
 impl ObjectTrait {
     pub fn new(name: String) -> Self {
-        Self { object: Object { obj_type: ObjectType::ObjTrait }, name, method_names: Vec::new() }
+        Self { object: Object::new(ObjectType::ObjTrait), name, method_names: Vec::new(), supertraits: Vec::new() }
     }
 }
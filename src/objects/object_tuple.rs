@@ -0,0 +1,14 @@
+use crate::{objects::object::{Object, ObjectType}, value::Value};
+
+/// Immutable fixed-size sequence produced by `(a, b, ...)`; compared by contents.
+#[repr(C)]
+pub struct ObjectTuple {
+    pub object: Object,
+    pub items: Vec<Value>,
+}
+
+impl ObjectTuple {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self { object: Object::new(ObjectType::ObjTuple), items }
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     value::{make_nil_value, print_value, Value},
 };
 
+#[repr(C)]
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct ObjectUpvalue {
@@ -16,9 +17,7 @@ pub struct ObjectUpvalue {
 impl ObjectUpvalue {
     pub fn new(location: *mut Value) -> Self {
         ObjectUpvalue {
-            object: Object {
-                obj_type: ObjectType::ObjUpvalue,
-            },
+            object: Object::new(ObjectType::ObjUpvalue),
             location,
             closed: make_nil_value(),
         }
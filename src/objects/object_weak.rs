@@ -0,0 +1,15 @@
+use crate::{objects::object::{Object, ObjectType}, value::Value};
+
+/// Produced by `weak(obj)`: refers to `target` without keeping it alive. The collector resets
+/// `target` to nil when it sweeps the target.
+#[repr(C)]
+pub struct ObjectWeak {
+    pub object: Object,
+    pub target: Value,
+}
+
+impl ObjectWeak {
+    pub fn new(target: Value) -> Self {
+        Self { object: Object::new(ObjectType::ObjWeak), target }
+    }
+}
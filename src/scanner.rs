@@ -9,6 +9,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -16,12 +18,19 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
+    Pipe,
 
     // One or two character tokens.
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
@@ -39,6 +48,8 @@ pub enum TokenType {
     For,
     Fn,
     If,
+    In,
+    Is,
     Nil,
     Or,
     Print,
@@ -46,11 +57,15 @@ pub enum TokenType {
     Super,
     True,
     Var,
+    Const,
+    Let,
     While,
     Trait,
     Impl,
     Struct,
     New,
+    Enum,
+    Match,
 
     Error,
     Eof,
@@ -60,12 +75,16 @@ static KEYWORDS: phf::Map<&'static str, TokenType> = phf::phf_map! {
     "and" => TokenType::And,
     "else" => TokenType::Else,
     "if" => TokenType::If,
+    "in" => TokenType::In,
+    "is" => TokenType::Is,
     "nil" => TokenType::Nil,
     "or" => TokenType::Or,
     "print" => TokenType::Print,
     "return" => TokenType::Return,
     "super" => TokenType::Super,
     "var" => TokenType::Var,
+    "const" => TokenType::Const,
+    "let" => TokenType::Let,
     "while" => TokenType::While,
     "for" => TokenType::For,
     "false" => TokenType::False,
@@ -75,6 +94,8 @@ static KEYWORDS: phf::Map<&'static str, TokenType> = phf::phf_map! {
     "impl" => TokenType::Impl,
     "struct" => TokenType::Struct,
     "new" => TokenType::New,
+    "enum" => TokenType::Enum,
+    "match" => TokenType::Match,
 };
 
 #[derive(Debug)]
@@ -103,6 +124,7 @@ pub struct Token<'a> {
     pub line: usize,
 }
 
+#[derive(Clone)]
 pub struct Scanner<'a> {
     source: &'a str,
     chars: Peekable<Chars<'a>>,
@@ -159,13 +181,30 @@ impl<'a> Scanner<'a> {
             ')' => self.make_token(TokenType::RightParen),
             '{' => self.make_token(TokenType::LeftBrace),
             '}' => self.make_token(TokenType::RightBrace),
+            '[' => self.make_token(TokenType::LeftBracket),
+            ']' => self.make_token(TokenType::RightBracket),
             ';' => self.make_token(TokenType::Semicolon),
             ',' => self.make_token(TokenType::Comma),
-            '.' => self.make_token(TokenType::Dot),
+            '.' => {
+                if self.match_char('.') {
+                    if self.match_char('=') {
+                        self.make_token(TokenType::DotDotEqual)
+                    } else if self.match_char('.') {
+                        self.make_token(TokenType::DotDotDot)
+                    } else {
+                        self.make_token(TokenType::DotDot)
+                    }
+                } else {
+                    self.make_token(TokenType::Dot)
+                }
+            },
             '-' => self.make_token(TokenType::Minus),
             '+' => self.make_token(TokenType::Plus),
             '/' => self.make_token(TokenType::Slash),
             '*' => self.make_token(TokenType::Star),
+            '?' => self.make_token(TokenType::Question),
+            ':' => self.make_token(TokenType::Colon),
+            '|' => self.make_token(TokenType::Pipe),
             '"' => self.make_string_token(),
             '!' => {
                 if self.match_char('=') {
@@ -177,6 +216,8 @@ impl<'a> Scanner<'a> {
             '=' => {
                 if self.match_char('=') {
                     self.make_token(TokenType::EqualEqual)
+                } else if self.match_char('>') {
+                    self.make_token(TokenType::FatArrow)
                 } else {
                     self.make_token(TokenType::Equal)
                 }
@@ -272,7 +313,7 @@ impl<'a> Scanner<'a> {
         }
 
         self.advance();
-        return self.make_token(TokenType::String);
+        self.make_token(TokenType::String)
     }
 
     fn skip_whitespace(&mut self) {
@@ -396,7 +437,7 @@ mod tests {
         assert!(token.value == "this");
 
         token = scanner.scan_token();
-        assert!(token.token_type == TokenType::Identifier);
+        assert!(token.token_type == TokenType::Is);
         assert!(token.value == "is");
 
         token = scanner.scan_token();
@@ -452,7 +493,7 @@ mod tests {
             let token = scanner.scan_token();
             println!("token is : {:?}", token);
             if token.token_type == TokenType::Error {
-                assert!(false);
+                panic!("unexpected error token");
             }
             if token.token_type == TokenType::Eof {
                 break;
@@ -483,4 +524,65 @@ mod tests {
         let ident = scanner.scan_token();
         assert_eq!(ident.token_type, TokenType::Identifier);
     }
+
+    #[test]
+    fn test_for_in_tokens() {
+        let mut scanner = Scanner::new("for (x in [1]) index");
+        let expected = [
+            TokenType::For, TokenType::LeftParen, TokenType::Identifier, TokenType::In,
+            TokenType::LeftBracket, TokenType::Number, TokenType::RightBracket,
+            TokenType::RightParen, TokenType::Identifier, TokenType::Eof,
+        ];
+        for token_type in expected {
+            assert_eq!(scanner.scan_token().token_type, token_type);
+        }
+    }
+
+    #[test]
+    fn test_range_tokens() {
+        let mut scanner = Scanner::new("0..10 1..=2 a.b ...rest");
+        let expected = [
+            TokenType::Number, TokenType::DotDot, TokenType::Number,
+            TokenType::Number, TokenType::DotDotEqual, TokenType::Number,
+            TokenType::Identifier, TokenType::Dot, TokenType::Identifier,
+            TokenType::DotDotDot, TokenType::Identifier, TokenType::Eof,
+        ];
+        for token_type in expected {
+            assert_eq!(scanner.scan_token().token_type, token_type);
+        }
+    }
+
+    #[test]
+    fn test_binding_keywords() {
+        let mut scanner = Scanner::new("var const let constant");
+        let expected = [
+            TokenType::Var, TokenType::Const, TokenType::Let, TokenType::Identifier, TokenType::Eof,
+        ];
+        for token_type in expected {
+            assert_eq!(scanner.scan_token().token_type, token_type);
+        }
+    }
+
+    #[test]
+    fn test_is_keyword() {
+        let mut scanner = Scanner::new("x is Shape island");
+        let expected = [
+            TokenType::Identifier, TokenType::Is, TokenType::Identifier, TokenType::Identifier, TokenType::Eof,
+        ];
+        for token_type in expected {
+            assert_eq!(scanner.scan_token().token_type, token_type);
+        }
+    }
+
+    #[test]
+    fn test_enum_match_tokens() {
+        let mut scanner = Scanner::new("enum match => == =");
+        let expected = [
+            TokenType::Enum, TokenType::Match, TokenType::FatArrow,
+            TokenType::EqualEqual, TokenType::Equal, TokenType::Eof,
+        ];
+        for token_type in expected {
+            assert_eq!(scanner.scan_token().token_type, token_type);
+        }
+    }
 }
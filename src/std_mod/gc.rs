@@ -0,0 +1,60 @@
+use crate::{value::{is_number, is_object, is_string, is_weak, as_number, as_string_object, as_weak_object, make_nil_value, make_numer_value, Value}, vm::VM};
+
+// Methods of the builtin `gc` value; args[0] is that receiver.
+
+// gc.collect(): run a full collection now.
+pub fn collect(vm: &mut VM, _args: &[Value]) -> Result<Value, String> {
+    vm.collect_garbage();
+    Ok(make_nil_value())
+}
+
+// gc.stats(): a map of the collector's counters.
+pub fn stats(vm: &mut VM, _args: &[Value]) -> Result<Value, String> {
+    let stats = vm.gc_stats();
+    let entries = [
+        ("cycles", stats.cycles as f64),
+        ("minor_cycles", stats.minor_cycles as f64),
+        ("major_cycles", stats.major_cycles as f64),
+        ("freed_bytes", stats.total_freed_bytes as f64),
+        ("promoted_bytes", stats.total_promoted_bytes as f64),
+        ("live_bytes", stats.live_bytes as f64),
+        ("next_trigger_bytes", stats.next_trigger_bytes as f64),
+    ];
+    let entries: Vec<(&str, Value)> = entries.iter().map(|(name, n)| (*name, make_numer_value(*n))).collect();
+    Ok(vm.new_map(&entries))
+}
+
+// heap_dump(path): write a JSON snapshot of the heap (see VM::heap_dump).
+pub fn heap_dump(vm: &mut VM, args: &[Value]) -> Result<Value, String> {
+    if !is_string(&args[0]) {
+        return Err("heap_dump() expects a file path string.".to_string());
+    }
+    let path = unsafe { (*as_string_object(&args[0])).content.clone() };
+    vm.heap_dump(&path)?;
+    Ok(make_nil_value())
+}
+
+// gc.set_growth_factor(f): how much the heap may grow after a full collection before the next one.
+pub fn set_growth_factor(vm: &mut VM, args: &[Value]) -> Result<Value, String> {
+    if !is_number(&args[1]) {
+        return Err("gc.set_growth_factor() expects a number.".to_string());
+    }
+    vm.set_gc_growth_factor(as_number(&args[1]))?;
+    Ok(make_nil_value())
+}
+
+// weak(obj): a reference to obj that doesn't keep it alive.
+pub fn weak(vm: &mut VM, args: &[Value]) -> Result<Value, String> {
+    if !is_object(&args[0]) {
+        return Err("weak() expects an object, not a number, bool or nil.".to_string());
+    }
+    Ok(vm.new_weak(args[0]))
+}
+
+// w.get(): the target, or nil once the collector has freed it.
+pub fn weak_get(_vm: &mut VM, args: &[Value]) -> Result<Value, String> {
+    if !is_weak(&args[0]) {
+        return Err("get() expects a weak reference.".to_string());
+    }
+    Ok(unsafe { (*as_weak_object(&args[0])).target })
+}
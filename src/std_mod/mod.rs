@@ -1 +1,3 @@
-pub mod time;
\ No newline at end of file
+pub mod time;
+pub mod reflect;
+pub mod gc;
\ No newline at end of file
@@ -0,0 +1,10 @@
+use crate::{value::{is_string, make_bool_value, as_string_object, Value}, vm::VM};
+
+// implements(value, "Trait"): the function form of `value is Trait`.
+pub fn implements(vm: &mut VM, args: &[Value]) -> Result<Value, String> {
+    if !is_string(&args[1]) {
+        return Err("implements() expects a trait name string as its second argument.".to_string());
+    }
+    let trait_name = unsafe { (*as_string_object(&args[1])).content.clone() };
+    vm.implements_trait(args[0], &trait_name).map(make_bool_value)
+}
@@ -46,4 +46,8 @@ impl Table {
     pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
         self.entries.iter()
     }
+
+    pub fn retain(&mut self, keep: impl FnMut(&String, &mut Value) -> bool) {
+        self.entries.retain(keep);
+    }
 }
\ No newline at end of file
@@ -1,7 +1,8 @@
-use crate::objects::{object::{Object, ObjectType}, object_closure::ObjectClosure, object_function::{ObjectFunction}, object_manager::ObjectManager, object_native_function::ObjectNativeFunction, object_string::ObjectString, object_upvalue::ObjectUpvalue};
+use crate::objects::{object::{Object, ObjectType}, object_closure::ObjectClosure, object_enum::{ObjectEnumType, ObjectEnumValue}, object_list::ObjectList, object_map::ObjectMap, object_range::ObjectRange, object_bound_method::ObjectBoundMethod, object_tuple::ObjectTuple, object_weak::ObjectWeak, object_function::{ObjectFunction}, object_manager::ObjectManager, object_native_function::ObjectNativeFunction, object_string::ObjectString, object_upvalue::ObjectUpvalue};
 use crate::table::Table;
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[allow(clippy::enum_variant_names)]
 pub enum ValueType {
     ValueBool,
     ValueNil,
@@ -17,24 +18,49 @@ impl Clone for ValueType {
     }
 }
 
-pub union ValueUnion {
-    pub boolean: bool,
-    pub number: f64,
-    pub object: *mut Object,
-    pub stack_index: usize, // index into frame-local stack struct store
+// Two representations of Value sit behind the is_*/as_*/make_* helpers below. The default one is a
+// ValueType tag next to a 64-bit union (16 bytes); with the `nan_boxing` feature every value is packed
+// into a single u64 (8 bytes). Code outside this file goes through the helpers only.
+
+#[cfg(not(feature = "nan_boxing"))]
+union ValueUnion {
+    boolean: bool,
+    number: f64,
+    object: *mut Object,
+    stack_index: usize, // index into frame-local stack struct store
 }
 
+#[cfg(not(feature = "nan_boxing"))]
 impl Copy for ValueUnion {}
+#[cfg(not(feature = "nan_boxing"))]
 impl Clone for ValueUnion {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-//pub type Value = f64;
+#[cfg(not(feature = "nan_boxing"))]
 pub struct Value {
-    pub value_type: ValueType,
-    pub value_as: ValueUnion,
+    value_type: ValueType,
+    value_as: ValueUnion,
+}
+
+// A number is any f64 whose quiet-NaN bits aren't all set. Everything else is a quiet NaN carrying a
+// payload: with the sign bit set, the low 48 bits are an object pointer; with bit 48 set, a stack
+// struct index; otherwise one of the nil/false/true tags.
+#[cfg(feature = "nan_boxing")]
+pub struct Value(u64);
+
+#[cfg(feature = "nan_boxing")]
+mod nan_box {
+    pub const SIGN_BIT: u64 = 0x8000_0000_0000_0000;
+    pub const QNAN: u64 = 0x7ffc_0000_0000_0000;
+    pub const STACK_STRUCT_BIT: u64 = 1 << 48;
+    pub const PAYLOAD_MASK: u64 = (1 << 48) - 1;
+    pub const NIL: u64 = QNAN | 1;
+    pub const FALSE: u64 = QNAN | 2;
+    pub const TRUE: u64 = QNAN | 3;
+    pub const UNDEFINED: u64 = QNAN;
 }
 
 impl Copy for Value {}
@@ -48,9 +74,29 @@ impl Clone for Value {
 impl Value {
     pub fn new() -> Self {
         // Default to nil
-        Self {
-            value_type: ValueType::ValueNil,
-            value_as: ValueUnion { number: 0.0 } // Safe to use any field when nil
+        make_nil_value()
+    }
+
+    #[cfg(not(feature = "nan_boxing"))]
+    #[inline(always)]
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    #[cfg(feature = "nan_boxing")]
+    #[inline(always)]
+    pub fn value_type(&self) -> ValueType {
+        use nan_box::*;
+        if self.0 & QNAN != QNAN {
+            ValueType::ValueNumber
+        } else if self.0 & SIGN_BIT != 0 {
+            ValueType::ValueObject
+        } else if self.0 & STACK_STRUCT_BIT != 0 {
+            ValueType::ValueStackStruct
+        } else if self.0 | 1 == TRUE {
+            ValueType::ValueBool
+        } else {
+            ValueType::ValueNil
         }
     }
 
@@ -58,144 +104,142 @@ impl Value {
     /// This replaces the previous two-function approach and centralizes the managed
     /// deep-clone behavior on `deep_clone` itself.
     pub fn deep_clone(&self, object_manager: &mut ObjectManager) -> Self {
+        if !is_object(self) {
+            // Stack structs are not deep-cloned here (alias semantics); like the primitives, copy as is.
+            return *self;
+        }
         unsafe {
-            match self.value_type {
-                ValueType::ValueBool => Value {
-                    value_type: self.value_type,
-                    value_as: ValueUnion { boolean: self.value_as.boolean },
-                },
-                ValueType::ValueNil => Value {
-                    value_type: self.value_type,
-                    value_as: ValueUnion { boolean: false },
-                },
-                ValueType::ValueNumber => Value {
-                    value_type: self.value_type,
-                    value_as: ValueUnion { number: self.value_as.number },
-                },
-                ValueType::ValueObject => {
-                    if self.value_as.object.is_null() {
-                        return Value { value_type: self.value_type, value_as: ValueUnion { object: std::ptr::null_mut() } };
-                    }
+            let object_ptr = as_mutable_object(self);
+            if object_ptr.is_null() {
+                return *self;
+            }
 
-                    let object = &*self.value_as.object;
-                    match object.obj_type {
-                        ObjectType::ObjString => {
-                            let original = &*(self.value_as.object as *const ObjectString);
-                            let (new_ptr, _sz) = object_manager.alloc_string(original.content.as_str());
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: new_ptr as *mut Object } }
-                        }
-
-                        ObjectType::ObjFunction => {
-                            let original = &*(self.value_as.object as *const ObjectFunction);
-                            // allocate new function via manager and copy internals
-                            let (func_ptr, _sz) = object_manager.alloc_function(original.arity as usize, original.name.clone());
-                            (*func_ptr).chunk = Box::new((*original.chunk).clone());
-                            (*func_ptr).upvalue_count = original.upvalue_count;
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: func_ptr as *mut Object } }
-                        }
-
-                        ObjectType::ObjClosure => {
-                            let original = &*(self.value_as.object as *const ObjectClosure);
-                            // deep-clone the referenced function first
-                            let orig_func = &*original.function;
-                            let (new_func_ptr, _sz_fn) = object_manager.alloc_function(orig_func.arity as usize, orig_func.name.clone());
-                            (*new_func_ptr).chunk = Box::new((*orig_func.chunk).clone());
-                            (*new_func_ptr).upvalue_count = orig_func.upvalue_count;
-
-                            // allocate closure referencing new function
-                            let (closure_ptr, _sz_cl) = object_manager.alloc_closure(new_func_ptr);
-                            // copy upvalue indices
-                            for &idx in original.upvalues.iter() {
-                                (*closure_ptr).upvalues.push(idx);
-                            }
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: closure_ptr as *mut Object } }
-                        }
-
-                        ObjectType::ObjUpvalue => {
-                            let original = &*(self.value_as.object as *const ObjectUpvalue);
-                            let (new_up, _sz_up) = object_manager.alloc_upvalue(original.location);
-                            // copy closed value
-                            (*new_up).closed = original.closed.clone();
-                            // if original was already closed (location points to original.closed),
-                            // update new location to point to new_up.closed
-                            let orig_self_ptr = self.value_as.object as *const ObjectUpvalue;
-                            let orig_closed_ptr = &(*orig_self_ptr).closed as *const Value as *mut Value;
-                            if original.location == orig_closed_ptr {
-                                (*new_up).location = &mut (*new_up).closed as *mut Value;
-                            }
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: new_up as *mut Object } }
-                        }
-
-                        ObjectType::ObjNativeFunction => {
-                            // Can't deep-clone trait objects generically; return shallow copy.
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: self.value_as.object } }
-                        }
-                        ObjectType::ObjTrait => {
-                            // Traits are immutable metadata; shallow copy pointer.
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: self.value_as.object } }
-                        }
-                        ObjectType::ObjStructType => {
-                            // Metadata only, shallow copy
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: self.value_as.object } }
-                        }
-                        ObjectType::ObjStructInstance => {
-                            // Shallow copy pointer (instances are mutable; deep clone semantics TBD)
-                            Value { value_type: self.value_type, value_as: ValueUnion { object: self.value_as.object } }
-                        }
+            let object = &*object_ptr;
+            match object.obj_type {
+                ObjectType::ObjString => {
+                    let original = &*(object_ptr as *const ObjectString);
+                    let (new_ptr, _sz) = object_manager.alloc_string(original.content.as_str());
+                    make_object_value(new_ptr as *mut Object)
+                }
+
+                ObjectType::ObjFunction => {
+                    let original = &*(object_ptr as *const ObjectFunction);
+                    // allocate new function via manager and copy internals
+                    let (func_ptr, _sz) = object_manager.alloc_function(original.arity as usize, original.name.clone());
+                    *(*func_ptr).chunk = (*original.chunk).clone();
+                    (*func_ptr).upvalue_count = original.upvalue_count;
+                    make_object_value(func_ptr as *mut Object)
+                }
+
+                ObjectType::ObjClosure => {
+                    let original = &*(object_ptr as *const ObjectClosure);
+                    // deep-clone the referenced function first
+                    let orig_func = &*original.function;
+                    let (new_func_ptr, _sz_fn) = object_manager.alloc_function(orig_func.arity as usize, orig_func.name.clone());
+                    *(*new_func_ptr).chunk = (*orig_func.chunk).clone();
+                    (*new_func_ptr).upvalue_count = orig_func.upvalue_count;
+
+                    // allocate closure referencing new function
+                    let (closure_ptr, _sz_cl) = object_manager.alloc_closure(new_func_ptr);
+                    // copy upvalue indices
+                    for &idx in original.upvalues.iter() {
+                        (*closure_ptr).upvalues.push(idx);
                     }
+                    make_object_value(closure_ptr as *mut Object)
                 }
-                ValueType::ValueStackStruct => {
-                    // Stack structs are not deep-cloned here (alias semantics). Shallow copy index.
-                    Value { value_type: ValueType::ValueStackStruct, value_as: ValueUnion { stack_index: self.value_as.stack_index } }
+
+                ObjectType::ObjUpvalue => {
+                    let original = &*(object_ptr as *const ObjectUpvalue);
+                    let (new_up, _sz_up) = object_manager.alloc_upvalue(original.location);
+                    // copy closed value
+                    (*new_up).closed = original.closed;
+                    // if original was already closed (location points to original.closed),
+                    // update new location to point to new_up.closed
+                    let orig_closed_ptr = &original.closed as *const Value as *mut Value;
+                    if original.location == orig_closed_ptr {
+                        (*new_up).location = &mut (*new_up).closed as *mut Value;
+                    }
+                    make_object_value(new_up as *mut Object)
                 }
+
+                // Can't deep-clone trait objects generically; return shallow copy.
+                ObjectType::ObjNativeFunction => *self,
+                // Traits are immutable metadata; shallow copy pointer.
+                ObjectType::ObjTrait => *self,
+                // Metadata only, shallow copy
+                ObjectType::ObjStructType => *self,
+                // Shallow copy pointer (instances are mutable; deep clone semantics TBD)
+                ObjectType::ObjStructInstance => *self,
+                // Enum metadata and variant values are immutable; shallow copy pointer.
+                ObjectType::ObjEnumType | ObjectType::ObjEnumValue => *self,
+                // Shares the receiver like any other reference; shallow copy pointer.
+                ObjectType::ObjBoundMethod => *self,
+                // Tuples are immutable; shallow copy pointer.
+                ObjectType::ObjTuple => *self,
+                // A copy refers to the same target; shallow copy pointer.
+                ObjectType::ObjWeak => *self,
+                // Ranges are immutable; shallow copy pointer.
+                ObjectType::ObjRange => *self,
+                // Collections are reference types like heap instances; shallow copy pointer.
+                ObjectType::ObjList | ObjectType::ObjMap => *self,
             }
         }
     }
-
-    
 }
 
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
-        if self.value_type != other.value_type {
+        if self.value_type() != other.value_type() {
             return false;
         }
 
-        unsafe {
-            match self.value_type {
-                ValueType::ValueBool => self.value_as.boolean == other.value_as.boolean,
-                ValueType::ValueNumber => {
-                    (self.value_as.number - other.value_as.number).abs() < f64::EPSILON
-                }
-                ValueType::ValueObject => {
-                    self.value_as.object == other.value_as.object
-                }
-                ValueType::ValueStackStruct => {
-                    self.value_as.stack_index == other.value_as.stack_index
-                }
-                ValueType::ValueNil => true,
+        match self.value_type() {
+            ValueType::ValueBool => as_bool(self) == as_bool(other),
+            ValueType::ValueNumber => {
+                (as_number(self) - as_number(other)).abs() < f64::EPSILON
+            }
+            ValueType::ValueObject => {
+                as_object(self) == as_object(other) || objects_structurally_equal(self, other)
+            }
+            ValueType::ValueStackStruct => {
+                as_stack_index(self) == as_stack_index(other)
             }
+            ValueType::ValueNil => true,
+        }
+    }
+}
+
+// Immutable value-like objects compare by contents rather than identity.
+fn objects_structurally_equal(a: &Value, b: &Value) -> bool {
+    unsafe {
+        let (a, b) = (&*as_object(a), &*as_object(b));
+        match (a.obj_type, b.obj_type) {
+            (ObjectType::ObjEnumValue, ObjectType::ObjEnumValue) => {
+                let (a, b) = (a.as_enum_value(), b.as_enum_value());
+                a.enum_type == b.enum_type && a.variant == b.variant && a.payload == b.payload
+            }
+            (ObjectType::ObjTuple, ObjectType::ObjTuple) => a.as_tuple().items == b.as_tuple().items,
+            (ObjectType::ObjBoundMethod, ObjectType::ObjBoundMethod) => {
+                let (a, b) = (a.as_bound_method(), b.as_bound_method());
+                a.receiver == b.receiver && a.method == b.method
+            }
+            _ => false,
         }
     }
 }
 
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.value_type != other.value_type {
+        if self.value_type() != other.value_type() {
             return None;
         }
 
-        match self.value_type {
+        match self.value_type() {
             ValueType::ValueBool => None,
             ValueType::ValueNil => None,
             ValueType::ValueObject => {
-                let a = unsafe {
-                    self.value_as.object
-                };
-
-                let b = unsafe {
-                    other.value_as.object
-                };
+                let a = as_object(self);
+                let b = as_object(other);
 
                 if a == b {
                     Some(std::cmp::Ordering::Equal)
@@ -206,13 +250,8 @@ impl PartialOrd for Value {
                 }
             }
             ValueType::ValueNumber => {
-                let a = unsafe {
-                    self.value_as.number
-                };
-
-                let b = unsafe {
-                    other.value_as.number
-                };
+                let a = as_number(self);
+                let b = as_number(other);
 
                 if (a - b).abs() < f64::EPSILON {
                     Some(std::cmp::Ordering::Equal)
@@ -227,30 +266,65 @@ impl PartialOrd for Value {
     }
 }
 
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn is_bool(value: &Value) -> bool {
     value.value_type == ValueType::ValueBool
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn is_bool(value: &Value) -> bool {
+    value.0 | 1 == nan_box::TRUE
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn is_nil(value: &Value) -> bool {
     value.value_type == ValueType::ValueNil
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn is_nil(value: &Value) -> bool {
+    value.0 == nan_box::NIL
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn is_number(value: &Value) -> bool {
     value.value_type == ValueType::ValueNumber
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn is_number(value: &Value) -> bool {
+    value.0 & nan_box::QNAN != nan_box::QNAN
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn is_object(value: &Value) -> bool {
     value.value_type == ValueType::ValueObject
 }
 
-#[allow(dead_code)]
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn is_object(value: &Value) -> bool {
+    value.0 & (nan_box::QNAN | nan_box::SIGN_BIT) == nan_box::QNAN | nan_box::SIGN_BIT
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn is_stack_struct(value: &Value) -> bool { value.value_type == ValueType::ValueStackStruct }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn is_stack_struct(value: &Value) -> bool {
+    use nan_box::*;
+    value.0 & (SIGN_BIT | QNAN | STACK_STRUCT_BIT) == QNAN | STACK_STRUCT_BIT
+}
+
 #[inline(always)]
 pub fn is_string(value: &Value) -> bool {
     unsafe {
@@ -279,6 +353,49 @@ pub fn is_closure(value: &Value) -> bool {
     }
 }
 
+#[inline(always)]
+pub fn is_list(value: &Value) -> bool {
+    unsafe {
+        is_object(value) && (*as_object(value)).obj_type == ObjectType::ObjList
+    }
+}
+
+#[inline(always)]
+pub fn is_map(value: &Value) -> bool {
+    unsafe {
+        is_object(value) && (*as_object(value)).obj_type == ObjectType::ObjMap
+    }
+}
+
+#[inline(always)]
+pub fn is_bound_method(value: &Value) -> bool {
+    unsafe {
+        is_object(value) && (*as_object(value)).obj_type == ObjectType::ObjBoundMethod
+    }
+}
+
+#[inline(always)]
+pub fn is_weak(value: &Value) -> bool {
+    unsafe {
+        is_object(value) && (*as_object(value)).obj_type == ObjectType::ObjWeak
+    }
+}
+
+#[inline(always)]
+pub fn is_tuple(value: &Value) -> bool {
+    unsafe {
+        is_object(value) && (*as_object(value)).obj_type == ObjectType::ObjTuple
+    }
+}
+
+#[inline(always)]
+pub fn is_range(value: &Value) -> bool {
+    unsafe {
+        is_object(value) && (*as_object(value)).obj_type == ObjectType::ObjRange
+    }
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn as_bool(value: &Value) -> bool {
     if value.value_type == ValueType::ValueBool {
@@ -289,6 +406,16 @@ pub fn as_bool(value: &Value) -> bool {
     panic!("Unexpected value type. {:?}", value.value_type);
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn as_bool(value: &Value) -> bool {
+    if is_bool(value) {
+        return value.0 == nan_box::TRUE;
+    }
+    panic!("Unexpected value type. {:?}", value.value_type());
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn as_number(value: &Value) -> f64 {
     if value.value_type == ValueType::ValueNumber {
@@ -299,8 +426,23 @@ pub fn as_number(value: &Value) -> f64 {
     panic!("Unexpected value type. {:?}", value.value_type);
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn as_number(value: &Value) -> f64 {
+    if is_number(value) {
+        return f64::from_bits(value.0);
+    }
+    panic!("Unexpected value type. {:?}", value.value_type());
+}
+
 #[inline(always)]
 pub fn as_object(value: &Value) -> *const Object {
+    as_mutable_object(value)
+}
+
+#[cfg(not(feature = "nan_boxing"))]
+#[inline(always)]
+pub fn as_mutable_object(value: &Value) -> *mut Object {
     if value.value_type == ValueType::ValueObject {
         return unsafe {
             value.value_as.object
@@ -309,17 +451,35 @@ pub fn as_object(value: &Value) -> *const Object {
     panic!("Unexpected value type. {:?}", value.value_type);
 }
 
+#[cfg(feature = "nan_boxing")]
 #[inline(always)]
-#[allow(dead_code)]
 pub fn as_mutable_object(value: &Value) -> *mut Object {
-    if value.value_type == ValueType::ValueObject {
+    if is_object(value) {
+        return (value.0 & nan_box::PAYLOAD_MASK) as *mut Object;
+    }
+    panic!("Unexpected value type. {:?}", value.value_type());
+}
+
+#[cfg(not(feature = "nan_boxing"))]
+#[inline(always)]
+pub fn as_stack_index(value: &Value) -> usize {
+    if value.value_type == ValueType::ValueStackStruct {
         return unsafe {
-            value.value_as.object
-        };   
+            value.value_as.stack_index
+        };
     }
     panic!("Unexpected value type. {:?}", value.value_type);
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn as_stack_index(value: &Value) -> usize {
+    if is_stack_struct(value) {
+        return (value.0 & nan_box::PAYLOAD_MASK) as usize;
+    }
+    panic!("Unexpected value type. {:?}", value.value_type());
+}
+
 #[inline(always)]
 pub fn as_string_object(value: &Value) -> *const ObjectString {
     as_object(value) as *const ObjectString
@@ -340,6 +500,36 @@ pub fn as_closure_object(value: &Value) -> *const ObjectClosure {
     as_object(value) as *const ObjectClosure
 }
 
+#[inline(always)]
+pub fn as_list_object(value: &Value) -> *mut ObjectList {
+    as_mutable_object(value) as *mut ObjectList
+}
+
+#[inline(always)]
+pub fn as_map_object(value: &Value) -> *mut ObjectMap {
+    as_mutable_object(value) as *mut ObjectMap
+}
+
+#[inline(always)]
+pub fn as_range_object(value: &Value) -> *const ObjectRange {
+    as_object(value) as *const ObjectRange
+}
+
+#[inline(always)]
+pub fn as_tuple_object(value: &Value) -> *const ObjectTuple {
+    as_object(value) as *const ObjectTuple
+}
+
+pub fn as_weak_object(value: &Value) -> *const ObjectWeak {
+    as_object(value) as *const ObjectWeak
+}
+
+#[inline(always)]
+pub fn as_bound_method_object(value: &Value) -> *const ObjectBoundMethod {
+    as_object(value) as *const ObjectBoundMethod
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn make_bool_value(value: bool) -> Value {
     Value {
@@ -348,6 +538,13 @@ pub fn make_bool_value(value: bool) -> Value {
     }
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn make_bool_value(value: bool) -> Value {
+    Value(if value { nan_box::TRUE } else { nan_box::FALSE })
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn make_nil_value() -> Value {
     Value {
@@ -356,6 +553,42 @@ pub fn make_nil_value() -> Value {
     }
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn make_nil_value() -> Value {
+    Value(nan_box::NIL)
+}
+
+// Marks a global slot whose name has been seen by the compiler but not defined yet. It reads as nil
+// but never leaves the globals store.
+#[cfg(not(feature = "nan_boxing"))]
+#[inline(always)]
+pub fn make_undefined_value() -> Value {
+    Value {
+        value_type: ValueType::ValueNil,
+        value_as: ValueUnion{stack_index: usize::MAX},
+    }
+}
+
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn make_undefined_value() -> Value {
+    Value(nan_box::UNDEFINED)
+}
+
+#[cfg(not(feature = "nan_boxing"))]
+#[inline(always)]
+pub fn is_undefined(value: &Value) -> bool {
+    value.value_type == ValueType::ValueNil && unsafe { value.value_as.stack_index } == usize::MAX
+}
+
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn is_undefined(value: &Value) -> bool {
+    value.0 == nan_box::UNDEFINED
+}
+
+#[cfg(not(feature = "nan_boxing"))]
 #[inline(always)]
 pub fn make_numer_value(value: f64) -> Value {
     Value {
@@ -364,52 +597,111 @@ pub fn make_numer_value(value: f64) -> Value {
     }
 }
 
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn make_numer_value(value: f64) -> Value {
+    Value(value.to_bits())
+}
+
+#[cfg(not(feature = "nan_boxing"))]
+#[inline(always)]
+pub fn make_object_value(object: *mut Object) -> Value {
+    Value {
+        value_type: ValueType::ValueObject,
+        value_as: ValueUnion{object},
+    }
+}
+
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn make_object_value(object: *mut Object) -> Value {
+    debug_assert!(object as u64 & !nan_box::PAYLOAD_MASK == 0, "object pointer doesn't fit in 48 bits");
+    Value(nan_box::SIGN_BIT | nan_box::QNAN | object as u64)
+}
+
+#[cfg(not(feature = "nan_boxing"))]
+#[inline(always)]
+pub fn make_stack_struct_value(stack_index: usize) -> Value {
+    Value {
+        value_type: ValueType::ValueStackStruct,
+        value_as: ValueUnion{stack_index},
+    }
+}
+
+#[cfg(feature = "nan_boxing")]
+#[inline(always)]
+pub fn make_stack_struct_value(stack_index: usize) -> Value {
+    Value(nan_box::QNAN | nan_box::STACK_STRUCT_BIT | stack_index as u64)
+}
+
 pub fn make_string_value(object_manager: &mut ObjectManager, intern_strings: &mut Table, str_value: &str) -> Value {
     if let Some(value) = intern_strings.find(str_value) {
-        value.clone()
+        value
     } else {
         let (object_string, _size) = object_manager.alloc_string(str_value);
-        let value = Value {
-            value_type: ValueType::ValueObject,
-            value_as: ValueUnion{object: object_string as *mut Object},
-        };
+        let value = make_object_value(object_string as *mut Object);
         intern_strings.insert(str_value.to_string(), value);
-        value.clone()
+        value
     }
 }
 
 pub fn make_function_value(function: *mut ObjectFunction) -> Value {
     let obj_ptr = unsafe { &mut (*function).object as *mut Object };
-    Value { value_type: ValueType::ValueObject, value_as: ValueUnion { object: obj_ptr } }
+    make_object_value(obj_ptr)
 }
 
 pub fn make_native_function_value(function: *mut ObjectNativeFunction) -> Value {
     let obj_ptr = unsafe { &mut (*function).object as *mut Object };
-    Value { value_type: ValueType::ValueObject, value_as: ValueUnion { object: obj_ptr } }
+    make_object_value(obj_ptr)
 }
 
 pub fn make_closure_value(closure: *mut ObjectClosure) -> Value {
     let obj_ptr = unsafe { &mut (*closure).object as *mut Object };
-    Value { value_type: ValueType::ValueObject, value_as: ValueUnion { object: obj_ptr } }
+    make_object_value(obj_ptr)
+}
+
+pub fn make_list_value(list: *mut ObjectList) -> Value {
+    let obj_ptr = unsafe { &mut (*list).object as *mut Object };
+    make_object_value(obj_ptr)
+}
+
+pub fn make_map_value(map: *mut ObjectMap) -> Value {
+    let obj_ptr = unsafe { &mut (*map).object as *mut Object };
+    make_object_value(obj_ptr)
+}
+
+pub fn make_weak_value(weak: *mut ObjectWeak) -> Value {
+    let obj_ptr = unsafe { &mut (*weak).object as *mut Object };
+    make_object_value(obj_ptr)
+}
+
+pub fn make_tuple_value(tuple: *mut ObjectTuple) -> Value {
+    let obj_ptr = unsafe { &mut (*tuple).object as *mut Object };
+    make_object_value(obj_ptr)
+}
+
+pub fn make_bound_method_value(bound: *mut ObjectBoundMethod) -> Value {
+    let obj_ptr = unsafe { &mut (*bound).object as *mut Object };
+    make_object_value(obj_ptr)
+}
+
+pub fn make_range_value(range: *mut ObjectRange) -> Value {
+    let obj_ptr = unsafe { &mut (*range).object as *mut Object };
+    make_object_value(obj_ptr)
 }
 
 #[inline(always)]
 #[allow(dead_code)]
 pub fn make_upvalue(upvalue: *mut ObjectUpvalue) -> Value {
-    Value {
-        value_type: ValueType::ValueObject,
-        value_as: ValueUnion {
-            object: upvalue as *mut Object
-        }
-    }
+    make_object_value(upvalue as *mut Object)
 }
 
 pub type ValueArray = Vec<Value>;
 
 pub fn print_value(value: &Value) {
-    match value.value_type {
+    match value.value_type() {
         ValueType::ValueNumber => {
-            let real_value = as_number(&value);
+            let real_value = as_number(value);
             if real_value.fract() == 0.0 {
                 print!("{}", real_value as i64);
             } else {
@@ -419,7 +711,7 @@ pub fn print_value(value: &Value) {
             }
         }
         ValueType::ValueBool => {
-            if as_bool(&value) {
+            if as_bool(value) {
                 print!("true");
             } else {
                 print!("false");
@@ -479,6 +771,68 @@ fn print_object(value: &Value) {
                 let s_type = &*inst.struct_type;
                 print!("<{} instance>", s_type.name);
             }
+            ObjectType::ObjList => {
+                let list = &*(object_ptr as *const ObjectList);
+                print!("[");
+                for (i, item) in list.items.iter().enumerate() {
+                    if i > 0 { print!(", "); }
+                    print_value(item);
+                }
+                print!("]");
+            }
+            ObjectType::ObjEnumType => {
+                let enum_type = &*(object_ptr as *const ObjectEnumType);
+                print!("<enum {}>", enum_type.name);
+            }
+            ObjectType::ObjEnumValue => {
+                let value = &*(object_ptr as *const ObjectEnumValue);
+                let enum_type = &*value.enum_type;
+                print!("{}.{}", enum_type.name, enum_type.variants[value.variant].name);
+                if !value.payload.is_empty() {
+                    print!("(");
+                    for (i, item) in value.payload.iter().enumerate() {
+                        if i > 0 { print!(", "); }
+                        print_value(item);
+                    }
+                    print!(")");
+                }
+            }
+            ObjectType::ObjTuple => {
+                let tuple = &*(object_ptr as *const ObjectTuple);
+                print!("(");
+                for (i, item) in tuple.items.iter().enumerate() {
+                    if i > 0 { print!(", "); }
+                    print_value(item);
+                }
+                // (x,) keeps a one-element tuple distinct from a parenthesized value
+                print!("{})", if tuple.items.len() == 1 { "," } else { "" });
+            }
+            ObjectType::ObjWeak => print!("<weak>"),
+            ObjectType::ObjBoundMethod => {
+                let bound = &*(object_ptr as *const ObjectBoundMethod);
+                if is_closure(&bound.method) {
+                    print!("<bound method {}>", (*(*as_closure_object(&bound.method)).function).name);
+                } else {
+                    print!("<bound method>");
+                }
+            }
+            ObjectType::ObjRange => {
+                let range = &*(object_ptr as *const ObjectRange);
+                print_value(&make_numer_value(range.start));
+                print!("{}", if range.inclusive { "..=" } else { ".." });
+                print_value(&make_numer_value(range.end));
+            }
+            ObjectType::ObjMap => {
+                let map = &*(object_ptr as *const ObjectMap);
+                print!("{{");
+                for (i, key) in map.keys.iter().enumerate() {
+                    if i > 0 { print!(", "); }
+                    let name = &(*as_string_object(key)).content;
+                    print!("{}: ", name);
+                    if let Some(value) = map.get(name) { print_value(&value); }
+                }
+                print!("}}");
+            }
         }
     }
 
@@ -491,4 +845,44 @@ fn print_object(value: &Value) {
 //     pub fn new(data: Value) -> Self {
 //         MyStruct { data }
 //     }
-// }
\ No newline at end of file
+// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_size_matches_representation() {
+        let expected = if cfg!(feature = "nan_boxing") { 8 } else { 16 };
+        assert_eq!(std::mem::size_of::<Value>(), expected);
+    }
+
+    #[test]
+    fn helpers_round_trip_every_kind() {
+        for number in [0.0, -0.0, 1.5, -42.0, f64::MAX, f64::MIN_POSITIVE, f64::INFINITY, f64::NEG_INFINITY] {
+            let value = make_numer_value(number);
+            assert!(is_number(&value) && !is_object(&value) && !is_nil(&value) && !is_bool(&value));
+            assert_eq!(as_number(&value).to_bits(), number.to_bits());
+        }
+        let nan = make_numer_value(f64::NAN);
+        assert!(is_number(&nan) && as_number(&nan).is_nan());
+
+        assert!(is_nil(&make_nil_value()) && !is_bool(&make_nil_value()));
+        assert!(as_bool(&make_bool_value(true)) && !as_bool(&make_bool_value(false)));
+        assert_eq!(make_bool_value(false).value_type(), ValueType::ValueBool);
+        assert!(is_undefined(&make_undefined_value()) && !is_undefined(&make_nil_value()) && !is_undefined(&make_numer_value(0.0)));
+        assert_eq!(make_undefined_value().value_type(), ValueType::ValueNil);
+
+        let stack_struct = make_stack_struct_value(12345);
+        assert!(is_stack_struct(&stack_struct) && !is_object(&stack_struct) && !is_number(&stack_struct));
+        assert_eq!(as_stack_index(&stack_struct), 12345);
+
+        let mut manager = ObjectManager::new();
+        let (string, _) = manager.alloc_string("boxed");
+        let value = make_object_value(string as *mut Object);
+        assert!(is_string(&value) && !is_stack_struct(&value) && !is_number(&value));
+        assert_eq!(as_object(&value), string as *const Object);
+        assert!(value == make_object_value(string as *mut Object));
+        assert!(value != make_nil_value() && make_numer_value(1.0) != make_bool_value(true));
+        unsafe { manager.free_all(); }
+    }
+}
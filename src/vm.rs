@@ -1,46 +1,64 @@
 use std::ptr::NonNull;
 
 use crate::{
-    gc::GarbageCollector,
+    gc::{GarbageCollector, GcConfig, GcMode, GCStats, INCREMENTAL_MARK_STEP},
+    heap_dump::{self, HeapRoot},
     call_frame::CallFrame,
-    chunk::{self, Chunk},
+    chunk::{self, Chunk, InlineCache},
     compiler::Parser,
+    globals::Globals,
     constants::{MAX_FRAMES_SIIZE, MAX_STACK_SIZE},
     debug,
     objects::{
         object::{Object, ObjectType},
         object_closure::ObjectClosure,
         object_function::ObjectFunction,
-        object_string::ObjectString,
+        object_native_function::VmNative,
         object_upvalue::ObjectUpvalue,
     },
-    std_mod::time::ClockTime,
+    std_mod::{gc as std_gc, reflect, time::ClockTime},
     table::Table,
     value::{
-        as_bool, as_closure_object, as_function_object, as_native_function_object,
-        as_number, as_string_object, is_bool, is_closure, is_function, is_native_function, 
-        is_nil, is_number, is_object, is_string, make_bool_value, make_closure_value, make_function_value,
-        make_native_function_value, make_nil_value, make_numer_value, make_string_value,
-        print_value, Value
+        as_bool, as_tuple_object, as_bound_method_object, as_closure_object, as_function_object, as_native_function_object,
+        as_list_object, as_map_object, as_mutable_object, as_number, as_range_object, as_stack_index, as_string_object, is_bool, is_closure, is_function,
+        is_bound_method, is_tuple, is_list, is_map, is_native_function, is_nil, is_number, is_object, is_range, is_stack_struct, is_string,
+        make_bool_value, make_bound_method_value, make_closure_value, make_function_value, make_list_value, make_map_value, make_weak_value,
+        make_range_value, make_tuple_value,
+        make_native_function_value, make_nil_value, make_numer_value, make_object_value, make_stack_struct_value, make_string_value,
+        print_value, Value, ValueType
     },
 };
 use crate::objects::object_manager::ObjectManager;
-use crate::objects::object_struct::{ObjectStructType, ObjectStructInstance};
+use crate::objects::object_struct::{FieldAccess, ObjectStructType, ObjectStructInstance};
+use crate::objects::object_enum::{EnumVariant, ObjectEnumType, ObjectEnumValue};
 use std::collections::HashMap;
 
 pub struct VM {
-    frames: Vec<Box<CallFrame>>,
+    frames: Vec<CallFrame>,
     stack: [Value; MAX_STACK_SIZE],
     stack_top_pos: usize,
     object_manager: Box<ObjectManager>,
     intern_strings: Box<Table>,
-    globals: Box<Table>,
+    globals: Box<Globals>,
     struct_types: Box<Table>,
     trait_registry: Box<Table>, // name -> trait object
     // Method registry: type name -> Table(method name -> function/closure value)
     type_methods: HashMap<String, Table>,
+    // Trait membership: type name -> trait name -> that impl's methods
+    type_traits: HashMap<String, HashMap<String, Table>>,
+    // Bumped whenever an impl changes type_methods; Invoke's inline caches only trust entries from the current epoch.
+    cache_epoch: u64,
     open_upvalues: Vec<*mut ObjectUpvalue>,
     gc: GarbageCollector,
+    gc_mode: GcMode,
+    // Object count when the running incremental cycle marked its roots; later objects are kept by that cycle.
+    gc_cycle_start_objects: usize,
+    gc_config: GcConfig,
+    // Bytes allocated since the last minor collection.
+    nursery_bytes: usize,
+    // Unreachable instances whose type implements Drop, kept alive until their drop() has run.
+    pending_finalizers: Vec<Value>,
+    running_finalizers: bool,
     bytes_allocated: usize,
     next_gc_bytes: usize,
     // Stack struct arenas per frame index (aligned with frames vector indices)
@@ -54,6 +72,7 @@ struct StackStruct {
 }
 
 #[derive(PartialEq, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum InterpretResult {
     InterpretOk,
     InterpretCompileError,
@@ -70,24 +89,35 @@ impl Drop for VM {
 
 impl VM {
     pub fn new() -> VM {
-        const INITIAL_GC_THRESHOLD: usize = 1024 * 1024; // 1MB
-        let vm = VM {
-                stack: [Value::new(); MAX_STACK_SIZE],
-                stack_top_pos: 0,
-                frames: Vec::with_capacity(MAX_FRAMES_SIIZE),
-                object_manager: Box::new(ObjectManager::new()),
-                intern_strings: Box::new(Table::new()),
-                globals: Box::new(Table::new()),
-                struct_types: Box::new(Table::new()),
-                trait_registry: Box::new(Table::new()),
-                type_methods: HashMap::new(),
-                open_upvalues: Vec::new(),
-                gc: GarbageCollector::new(),
-                bytes_allocated: 0,
-                next_gc_bytes: INITIAL_GC_THRESHOLD,
-                frame_stack_structs: Vec::new(),
-            };
-        vm
+        Self::with_gc_config(GcConfig::default())
+    }
+
+    /// A VM whose collector uses `config` instead of the default thresholds.
+    pub fn with_gc_config(config: GcConfig) -> VM {
+        VM {
+            stack: [Value::new(); MAX_STACK_SIZE],
+            stack_top_pos: 0,
+            frames: Vec::with_capacity(MAX_FRAMES_SIIZE),
+            object_manager: Box::new(ObjectManager::new()),
+            intern_strings: Box::new(Table::new()),
+            globals: Box::new(Globals::new()),
+            struct_types: Box::new(Table::new()),
+            trait_registry: Box::new(Table::new()),
+            type_methods: HashMap::new(),
+            type_traits: HashMap::new(),
+            cache_epoch: 1,
+            open_upvalues: Vec::new(),
+            gc: GarbageCollector::new(),
+            gc_mode: GcMode::default(),
+            gc_cycle_start_objects: 0,
+            gc_config: config,
+            nursery_bytes: 0,
+            pending_finalizers: Vec::new(),
+            running_finalizers: false,
+            bytes_allocated: 0,
+            next_gc_bytes: config.initial_threshold,
+            frame_stack_structs: Vec::new(),
+        }
     }
         
     pub fn interpret(&mut self, source: &str) -> InterpretResult {
@@ -96,11 +126,11 @@ impl VM {
     }
 
     fn compile(&mut self, source: &str) -> InterpretResult {
-        let mut parser = Box::new(Parser::new(&mut self.object_manager, &mut self.intern_strings));
+        let mut parser = Box::new(Parser::new(&mut self.object_manager, &mut self.intern_strings, &mut self.globals));
         if let Some(function_ptr) = parser.compile(source) {
             self.push(make_function_value(function_ptr));
 
-            self.call_function(function_ptr, 0);
+            self.call_function(function_ptr, 0, &[]);
         } else {
             eprintln!("Compile Error!");
             return InterpretResult::InterpretCompileError;
@@ -108,11 +138,11 @@ impl VM {
 
         // Incorporate any allocations performed during compilation (strings, functions) before execution
         self.sync_pending_allocations();
-        match self.run() {
+        match self.run(0).and_then(|result| self.run_finalizers().map(|_| result)) {
             Ok(result) => result,
             Err(e) => {
                 eprintln!("Error during interpretation: {}", e);
-                return InterpretResult::InterpretRuntimeError;
+                InterpretResult::InterpretRuntimeError
             },
         }
     }
@@ -124,9 +154,29 @@ impl VM {
 
     fn track_allocation(&mut self, bytes: usize) {
         self.bytes_allocated += bytes;
-        if self.bytes_allocated > self.next_gc_bytes {
-            self.collect_garbage();
+        self.nursery_bytes += bytes;
+        if self.gc.is_marking() {
+            self.incremental_gc_step();
+        } else if self.bytes_allocated > self.next_gc_bytes {
+            match self.gc_mode {
+                GcMode::StopTheWorld | GcMode::Generational => self.collect_garbage(),
+                GcMode::Incremental => {
+                    self.begin_incremental_cycle();
+                    self.incremental_gc_step();
+                }
+            }
+        } else if self.gc_mode == GcMode::Generational && self.nursery_bytes > self.gc_config.nursery_bytes {
+            self.collect_nursery();
+        }
+    }
+
+    /// Choose between stop-the-world and incremental collection for this VM.
+    #[allow(dead_code)]
+    pub fn set_gc_mode(&mut self, mode: GcMode) {
+        if mode != GcMode::Incremental && self.gc.is_marking() {
+            self.finish_incremental_cycle();
         }
+        self.gc_mode = mode;
     }
 
     // Test-only helper: allow tests to lower GC threshold to force cycles under smaller workloads.
@@ -135,23 +185,99 @@ impl VM {
         self.next_gc_bytes = threshold;
     }
 
+    #[cfg(test)]
+    fn set_nursery_limit(&mut self, limit: usize) {
+        self.gc_config.nursery_bytes = limit;
+    }
+
+    /// Set how much the heap may grow after a full collection before the next one (default 2.0).
+    /// Takes effect from the next collection.
+    pub fn set_gc_growth_factor(&mut self, factor: f64) -> Result<(), String> {
+        if !(factor.is_finite() && factor > 1.0) {
+            return Err(format!("GC growth factor must be a number greater than 1, got {}.", factor));
+        }
+        self.gc_config.growth_factor = factor;
+        Ok(())
+    }
+
+    /// Collector counters, with the current live bytes and next trigger filled in.
+    pub fn gc_stats(&self) -> GCStats {
+        let mut stats = self.gc.stats().clone();
+        stats.live_bytes = self.bytes_allocated;
+        stats.next_trigger_bytes = self.next_gc_bytes;
+        stats
+    }
+
     fn update_next_gc_threshold(&mut self) {
-        // Common GC tuning: increase threshold by a factor (here 2x)
-        // This provides a balance between GC frequency and memory usage
-        self.next_gc_bytes = self.bytes_allocated * 2;
+        // Grow the threshold by a factor of the live bytes, trading GC frequency against memory use.
+        self.next_gc_bytes = (self.bytes_allocated as f64 * self.gc_config.growth_factor) as usize;
     }
 
-    fn collect_garbage(&mut self) {
+    /// Run a full collection now, finishing any incremental cycle in progress first.
+    pub fn collect_garbage(&mut self) {
+        if self.gc.is_marking() {
+            self.finish_incremental_cycle();
+        }
         let before = self.bytes_allocated;
         // Prepare GC
-        self.gc.prepare_collection(&self.object_manager);
+        self.gc.prepare_collection();
+        self.mark_vm_roots();
+
+        // Trace
+        self.gc.trace_references();
+        self.gc.clear_weak_refs();
+        self.queue_finalizers();
+        self.sweep_and_record(before);
+    }
+
+    // Minor collection: only young objects are marked, starting from the roots and the remembered set.
+    fn collect_nursery(&mut self) {
+        let before = self.bytes_allocated;
+        self.gc.prepare_collection();
+        self.gc.begin_minor();
+        self.mark_vm_roots();
+        self.gc.mark_remembered();
+        self.gc.trace_references();
+        self.gc.clear_weak_refs();
+        self.queue_finalizers();
+        self.gc.remove_white_strings(&mut self.intern_strings);
+        let (freed_bytes, promoted_bytes) = self.gc.sweep_nursery(&mut self.object_manager);
+        self.bytes_allocated = self.bytes_allocated.saturating_sub(freed_bytes);
+        self.nursery_bytes = 0;
+        self.gc.record_minor_cycle(before, freed_bytes, self.bytes_allocated, promoted_bytes);
+    }
+
+    fn begin_incremental_cycle(&mut self) {
+        self.gc.prepare_collection();
+        self.gc.begin_marking();
+        self.gc_cycle_start_objects = self.object_manager.len();
+        self.mark_vm_roots();
+    }
+
+    fn incremental_gc_step(&mut self) {
+        if self.gc.step(INCREMENTAL_MARK_STEP) {
+            self.finish_incremental_cycle();
+        }
+    }
+
+    fn finish_incremental_cycle(&mut self) {
+        let before = self.bytes_allocated;
+        // Stack slots, locals and registries aren't behind the write barrier, so rescan them, and keep
+        // everything allocated since the cycle began.
+        self.mark_vm_roots();
+        self.gc.mark_allocated_since(&self.object_manager, self.gc_cycle_start_objects);
+        self.gc.trace_references();
+        self.gc.clear_weak_refs();
+        self.queue_finalizers();
+        self.gc.end_marking();
+        self.sweep_and_record(before);
+    }
 
-        // Mark roots
+    fn mark_vm_roots(&mut self) {
         self.gc.mark_roots(
             &self.stack,
             self.stack_top_pos,
             &self.globals,
-            &self.intern_strings,
             &self.frames,
             &self.open_upvalues,
         );
@@ -170,13 +296,115 @@ impl VM {
         for (_name, v) in self.struct_types.iter() { self.gc.mark_value(v); }
         // Mark method tables for each type
         for (_t, tbl) in self.type_methods.iter() { for (_k, v) in tbl.iter() { self.gc.mark_value(v); } }
+        for (_t, traits) in self.type_traits.iter() { for (_n, tbl) in traits.iter() { for (_k, v) in tbl.iter() { self.gc.mark_value(v); } } }
+        for v in &self.pending_finalizers { self.gc.mark_value(v); }
+    }
 
-        // Trace
+    fn implements_drop(&self, struct_type: *mut ObjectStructType) -> bool {
+        let type_name = unsafe { &(*struct_type).name };
+        self.type_traits.get(type_name.as_str()).is_some_and(|traits| traits.contains_key("Drop"))
+    }
+
+    // Struct instances about to be swept whose type implements Drop are marked again, with everything
+    // they reach, and queued for drop(). The object is flagged as finalized, so once it is unreachable
+    // again a later collection frees it: a drop() that stores `self` somewhere just keeps it alive.
+    fn queue_finalizers(&mut self) {
+        if !self.type_traits.values().any(|traits| traits.contains_key("Drop")) {
+            return;
+        }
+        let mut finalizable = Vec::new();
+        for &obj in self.object_manager.iter() {
+            unsafe {
+                if (*obj).obj_type != ObjectType::ObjStructInstance || (*obj).is_finalized || !self.gc.will_sweep(obj) {
+                    continue;
+                }
+                if self.implements_drop((*obj).as_struct_instance().struct_type) {
+                    finalizable.push(obj);
+                }
+            }
+        }
+        for obj in finalizable {
+            unsafe { (*obj).is_finalized = true; }
+            self.gc.mark_object(obj);
+            self.pending_finalizers.push(make_object_value(obj));
+        }
         self.gc.trace_references();
+    }
+
+    // Call drop() on the queued instances. Runs between instructions, never inside a collection; drop()
+    // calls that allocate and queue more finalizers are drained by the same loop.
+    fn run_finalizers(&mut self) -> Result<(), String> {
+        if self.running_finalizers {
+            return Ok(());
+        }
+        self.running_finalizers = true;
+        let result = self.drain_finalizers();
+        self.running_finalizers = false;
+        result
+    }
+
+    fn drain_finalizers(&mut self) -> Result<(), String> {
+        while let Some(&instance) = self.pending_finalizers.last() {
+            let type_name = self.type_name_of(instance);
+            let method = match self.type_traits[type_name.as_str()]["Drop"].find("drop") {
+                Some(method) => method,
+                None => { self.pending_finalizers.pop(); continue; }
+            };
+            // The instance moves from the queue to the stack without an allocation in between.
+            self.pending_finalizers.pop();
+            self.push(method);
+            self.push(instance);
+            let frame_count = self.frames.len();
+            if !self.call_value(method, 1) {
+                self.report("drop() call failed.")?;
+            }
+            self.run(frame_count)?;
+            self.pop();
+        }
+        Ok(())
+    }
+
+    /// Write a JSON snapshot of the heap to `path`: every object with its type, deep size and outgoing
+    /// references, the roots, and a summary of bytes by struct type and string length. Objects that are
+    /// unreachable but not swept yet are included; call `collect_garbage` first to leave them out.
+    pub fn heap_dump(&self, path: &str) -> Result<(), String> {
+        heap_dump::write_json(path, &self.object_manager, &self.heap_roots())
+    }
+
+    // The same root set mark_vm_roots marks, labelled for a heap dump.
+    fn heap_roots(&self) -> Vec<HeapRoot> {
+        let mut roots = Vec::new();
+        let mut root = |kind: &'static str, label: String, value: Value| roots.push(HeapRoot { kind, label, value });
+        for (slot, value) in self.stack[..self.stack_top_pos].iter().enumerate() { root("stack", slot.to_string(), *value); }
+        for (name, value) in self.globals.iter() { root("global", name.clone(), *value); }
+        for (index, upvalue) in self.open_upvalues.iter().enumerate() {
+            root("upvalue", index.to_string(), make_object_value(*upvalue as *mut Object));
+        }
+        for (depth, arena) in self.frame_stack_structs.iter().enumerate() {
+            for (index, st) in arena.iter().enumerate() {
+                for value in &st.fields { root("stack_struct", format!("{}:{}", depth, index), *value); }
+            }
+        }
+        for (name, value) in self.trait_registry.iter() { root("trait", name.clone(), *value); }
+        for (name, value) in self.struct_types.iter() { root("struct_type", name.clone(), *value); }
+        for (type_name, table) in self.type_methods.iter() {
+            for (name, value) in table.iter() { root("method", format!("{}.{}", type_name, name), *value); }
+        }
+        for (type_name, traits) in self.type_traits.iter() {
+            for (trait_name, table) in traits.iter() {
+                for (name, value) in table.iter() { root("impl", format!("{} for {}.{}", trait_name, type_name, name), *value); }
+            }
+        }
+        for (index, value) in self.pending_finalizers.iter().enumerate() { root("finalizer", index.to_string(), *value); }
+        roots
+    }
 
+    fn sweep_and_record(&mut self, before: usize) {
+        self.gc.remove_white_strings(&mut self.intern_strings);
         // Sweep
         let freed_bytes = self.gc.sweep(&mut self.object_manager);
         self.bytes_allocated = self.bytes_allocated.saturating_sub(freed_bytes);
+        self.nursery_bytes = 0;
         self.update_next_gc_threshold();
         let after = self.bytes_allocated;
         let next = self.next_gc_bytes;
@@ -193,20 +421,54 @@ impl VM {
         );
     }
 
-    #[inline]
-    fn warn(&self, message: &str) {
-        #[cfg(feature = "promotion_warnings")]
-        eprintln!("[warn] {}", message);
-        #[cfg(not(feature = "promotion_warnings"))]
-        let _ = message; // no-op when feature disabled
-    }
-
     fn setup_standards(&mut self) {
         // Root ordering: Insert the newly allocated native function into a root (globals) BEFORE tracking
         // the allocation, because tracking may immediately trigger GC.
         let (clock_ptr, size) = self.object_manager.alloc_native_function("clock".to_string(), 0, ClockTime::new());
         self.globals.insert("clock".to_string(), make_native_function_value(clock_ptr));
         self.track_allocation(size);
+
+        let (implements_ptr, size) = self.object_manager.alloc_vm_native_function("implements".to_string(), 2, reflect::implements);
+        self.globals.insert("implements".to_string(), make_native_function_value(implements_ptr));
+        self.track_allocation(size);
+
+        let (heap_dump_ptr, size) = self.object_manager.alloc_vm_native_function("heap_dump".to_string(), 1, std_gc::heap_dump);
+        self.globals.insert("heap_dump".to_string(), make_native_function_value(heap_dump_ptr));
+        self.track_allocation(size);
+
+        // gc.collect(), gc.stats(), gc.set_growth_factor(f): native methods of a builtin `gc` value.
+        let (gc_type, size) = self.object_manager.alloc_struct_type("gc".to_string());
+        let (gc_ptr, instance_size) = self.object_manager.alloc_struct_instance(gc_type, 0);
+        self.globals.insert("gc".to_string(), make_object_value(gc_ptr as *mut Object));
+        self.track_allocation(size + instance_size);
+        self.type_methods.insert("gc".to_string(), Table::new());
+        let methods: [(&str, usize, VmNative); 3] = [
+            ("collect", 0, std_gc::collect),
+            ("stats", 0, std_gc::stats),
+            ("set_growth_factor", 1, std_gc::set_growth_factor),
+        ];
+        for (name, arity, function) in methods {
+            // The receiver is passed as the first argument.
+            let (method_ptr, size) = self.object_manager.alloc_vm_native_function(name.to_string(), arity + 1, function);
+            self.type_methods.get_mut("gc").unwrap().insert(name.to_string(), make_native_function_value(method_ptr));
+            self.track_allocation(size);
+        }
+
+        // weak(obj) and w.get(): references the collector doesn't follow.
+        let (weak_ptr, size) = self.object_manager.alloc_vm_native_function("weak".to_string(), 1, std_gc::weak);
+        self.globals.insert("weak".to_string(), make_native_function_value(weak_ptr));
+        self.track_allocation(size);
+        let (get_ptr, size) = self.object_manager.alloc_vm_native_function("get".to_string(), 1, std_gc::weak_get);
+        self.type_methods.entry("weak".to_string()).or_insert_with(Table::new).insert("get".to_string(), make_native_function_value(get_ptr));
+        self.track_allocation(size);
+
+        // Builtin trait Drop { fn drop(); }: the collector calls drop() before freeing an instance.
+        let (drop_ptr, size) = self.object_manager.alloc_trait("Drop".to_string());
+        unsafe { (*drop_ptr).method_names = vec!["drop".to_string()]; }
+        let drop_value = make_object_value(drop_ptr as *mut Object);
+        self.trait_registry.insert("Drop".to_string(), drop_value);
+        self.globals.insert("Drop".to_string(), drop_value);
+        self.track_allocation(size);
     }
 
     fn current_frame(&mut self) -> &mut CallFrame {
@@ -223,7 +485,7 @@ impl VM {
         match self.current_frame().object_type() {
             ObjectType::ObjFunction => {
                 let function = self.current_frame().function();
-                &mut (*function).chunk 
+                &mut function.chunk 
             },
             ObjectType::ObjClosure => {
                 let closure = self.current_frame().closure();
@@ -276,15 +538,27 @@ impl VM {
     }
 
     fn call_value(&mut self, callee: Value, argument_count: u8) -> bool {
+        self.call_value_named(callee, argument_count, &[])
+    }
+
+    // `names` labels the last names.len() arguments (f(a, b: 2)); they are matched to parameters by name.
+    fn call_value_named(&mut self, callee: Value, argument_count: u8, names: &[String]) -> bool {
         if is_object(&callee) {
             if is_function(&callee) {
-                return self.call_function(as_function_object(&callee) as *mut ObjectFunction, argument_count);
+                return self.call_function(as_function_object(&callee) as *mut ObjectFunction, argument_count, names);
             } else if is_native_function(&callee) {
+                if !names.is_empty() {
+                    let _ = self.runtime_error("Native functions don't take named arguments.");
+                    return false;
+                }
                 let native_function = as_native_function_object(&callee);
-                let result = (unsafe { &*native_function }).invoke(&None);
+                let first_arg = self.stack_top_pos - argument_count as usize;
+                let args = self.stack[first_arg..self.stack_top_pos].to_vec();
+                // Arguments stay on the stack during the call so they remain rooted if the native allocates.
+                let result = (unsafe { &*native_function }).invoke(self, args);
                 match result {
                     Ok(value) => {
-                        self.stack_top_pos -= (unsafe { &*native_function }).arity as usize + 1;
+                        self.stack_top_pos -= argument_count as usize + 1;
                         self.push(value);
                         return true;
                     },
@@ -295,7 +569,18 @@ impl VM {
                 }
             } else if is_closure(&callee) {
                 let closure_ptr = as_closure_object(&callee) as *mut ObjectClosure;
-                return self.call_closure(closure_ptr, argument_count);
+                return self.call_closure(closure_ptr, argument_count, names);
+            } else if is_bound_method(&callee) {
+                // [bound, args...] -> [method, receiver, args...], then called like Invoke.
+                let bound = unsafe { &*as_bound_method_object(&callee) };
+                let (receiver, method) = (bound.receiver, bound.method);
+                if self.stack_top_pos >= MAX_STACK_SIZE {
+                    let _ = self.report("Stack overflow during invoke");
+                    return false;
+                }
+                let callee_pos = self.stack_top_pos - argument_count as usize - 1;
+                self.stack[callee_pos] = receiver;
+                return self.call_method(method, argument_count, names);
             }
 
         }
@@ -303,12 +588,14 @@ impl VM {
         false
     }
 
-    fn call_function(&mut self, function: *mut ObjectFunction, argument_count: u8) -> bool {
-        let arity = unsafe { &(*function) }.arity;
-        if arity != argument_count {
-            let _ = self.runtime_error(format!("Expected {} arguments but got {}.", arity, argument_count).as_str());
-            return false;
-        }
+    fn call_function(&mut self, function: *mut ObjectFunction, argument_count: u8, names: &[String]) -> bool {
+        let (argument_count, missing_args) = match self.bind_arguments(function, argument_count, names) {
+            Ok(bound) => bound,
+            Err(message) => {
+                let _ = self.runtime_error(&message);
+                return false;
+            }
+        };
 
         if self.frames.len() >= MAX_FRAMES_SIIZE {
             let _ = self.runtime_error("Stack overflow.");
@@ -328,19 +615,21 @@ impl VM {
         //     }
         // }
         frame.set_callable_object(function as *mut Object);
-        self.frames.push(Box::new(frame));
+        frame.set_missing_args(missing_args);
+        self.frames.push(frame);
         self.frame_stack_structs.push(Vec::new()); // new frame stack struct arena
 
         true
     }
 
-    fn call_closure(&mut self, closure: *mut ObjectClosure, argument_count: u8) -> bool {
-        let function = unsafe { &*(*closure).function };
-        let arity = function.arity;
-        if arity != argument_count {
-            let _ = self.runtime_error(format!("Expected {} arguments but got {}.", arity, argument_count).as_str());
-            return false;
-        }
+    fn call_closure(&mut self, closure: *mut ObjectClosure, argument_count: u8, names: &[String]) -> bool {
+        let (argument_count, missing_args) = match self.bind_arguments(unsafe { (*closure).function }, argument_count, names) {
+            Ok(bound) => bound,
+            Err(message) => {
+                let _ = self.runtime_error(&message);
+                return false;
+            }
+        };
 
         if self.frames.len() >= MAX_FRAMES_SIIZE {
             let _ = self.runtime_error("Stack overflow.");
@@ -349,16 +638,83 @@ impl VM {
         let stack_base_pos = self.stack_top_pos - argument_count as usize - 1;
         let mut frame = CallFrame::new(NonNull::new(&mut self.stack[stack_base_pos]).unwrap(), stack_base_pos);
         frame.set_callable_object(closure as *mut Object);
-        self.frames.push(Box::new(frame));
+        frame.set_missing_args(missing_args);
+        self.frames.push(frame);
         self.frame_stack_structs.push(Vec::new());
 
         true
     }
 
-    fn run(&mut self) -> Result<InterpretResult, String> {
+    // Lay the arguments on the stack out as the callee's parameter slots: named arguments move to their
+    // parameter, omitted ones become nil (their slots are returned so ArgDefault can fill them in), and
+    // extras go into the rest list. Returns the resulting argument count.
+    fn bind_arguments(&mut self, function: *const ObjectFunction, argument_count: u8, names: &[String]) -> Result<(u8, Vec<u8>), String> {
+        let function = unsafe { &*function };
+        let arity = function.arity as usize;
+        let min_arity = function.min_arity as usize;
+        let positional = argument_count as usize - names.len();
+        if names.is_empty() && !function.has_rest && positional == arity {
+            return Ok((argument_count, Vec::new()));
+        }
+        if names.is_empty() && (positional < min_arity || (positional > arity && !function.has_rest)) {
+            return Err(if function.has_rest {
+                format!("Expected at least {} arguments but got {}.", min_arity, positional)
+            } else if min_arity == arity {
+                format!("Expected {} arguments but got {}.", arity, positional)
+            } else {
+                format!("Expected {} to {} arguments but got {}.", min_arity, arity, positional)
+            });
+        }
+        if positional > arity && !function.has_rest {
+            return Err(format!("Expected at most {} positional arguments but got {}.", arity, positional));
+        }
+
+        let first = self.stack_top_pos - argument_count as usize;
+        let arguments = self.stack[first..self.stack_top_pos].to_vec();
+        let mut slots: Vec<Option<Value>> = vec![None; arity];
+        for (i, value) in arguments[..positional.min(arity)].iter().enumerate() {
+            slots[i] = Some(*value);
+        }
+        for (name, value) in names.iter().zip(arguments[positional..].iter()) {
+            match function.param_names.iter().position(|p| p == name) {
+                None => return Err(format!("{} has no parameter named '{}'.", function.name, name)),
+                Some(i) if slots[i].is_some() => return Err(format!("Argument '{}' was passed more than once.", name)),
+                Some(i) => slots[i] = Some(*value),
+            }
+        }
+        let mut missing_args = Vec::new();
+        for (i, slot) in slots.iter().enumerate() {
+            if slot.is_none() {
+                if i < min_arity {
+                    return Err(format!("Missing argument for parameter '{}'.", function.param_names[i]));
+                }
+                missing_args.push((i + 1) as u8);
+            }
+        }
+
+        self.stack_top_pos = first;
+        for slot in slots {
+            self.push(slot.unwrap_or_else(make_nil_value));
+        }
+        if function.has_rest {
+            let rest = arguments[..positional].iter().skip(arity).copied().collect();
+            let (list_ptr, size) = self.object_manager.alloc_list(rest);
+            self.push(make_list_value(list_ptr));
+            self.track_allocation(size);
+        }
+        Ok(((arity + function.has_rest as usize) as u8, missing_args))
+    }
+
+    // Execute until the frame stack drops back to `base_frame_count`. The top-level call passes 0;
+    // the VM passes the current depth when it needs the result of a script function synchronously
+    // (e.g. an iterator's next()), leaving the result on top of the stack.
+    fn run(&mut self, base_frame_count: usize) -> Result<InterpretResult, String> {
         loop {
             // Account for any new allocations done since last iteration (e.g., string interning during concatenation)
             self.sync_pending_allocations();
+            if !self.pending_finalizers.is_empty() {
+                self.run_finalizers()?;
+            }
             // (optional) enable disassembly via feature flag: debug_trace_execution
 
             let instruction = match self.read_byte() {
@@ -387,18 +743,10 @@ impl VM {
                     self.push(make_bool_value(a == b));
                 }
                 Some(chunk::OpCode::Greater) => {
-                    let result = self.binary_op(chunk::OpCode::Greater);
-                    match result {
-                        Err(_) => return result,
-                        _ => (),
-                    }
+                    self.binary_op(chunk::OpCode::Greater)?;
                 }
                 Some(chunk::OpCode::Less) => {
-                    let result = self.binary_op(chunk::OpCode::Less);
-                    match result {
-                        Err(_) => return result,
-                        _ => (),
-                    }
+                    self.binary_op(chunk::OpCode::Less)?;
                 }
                 Some(chunk::OpCode::Add) => {
                     if self.stack_top_pos < 2 { return self.report("There is a lack of operands in the '+' Operation."); }
@@ -421,33 +769,20 @@ impl VM {
                             self.push(combinated_value);
                         }
                     } else if is_number(&value_a) && is_number(&value_b) {
-                        let result = self.binary_op(chunk::OpCode::Add);
-                        match result { Err(_) => return result, _ => (), }
+                        self.binary_op(chunk::OpCode::Add)?;
                     } else {
                         return self.report("Operands must be two numbers or two strings.");
                     }
 
                 }
                 Some(chunk::OpCode::Subtract) => {
-                    let result = self.binary_op(chunk::OpCode::Subtract);
-                    match result {
-                        Err(_) => return result,
-                        _ => (),
-                    }
+                    self.binary_op(chunk::OpCode::Subtract)?;
                 }
                 Some(chunk::OpCode::Multiply) => {
-                    let result = self.binary_op(chunk::OpCode::Multiply);
-                    match result {
-                        Err(_) => return result,
-                        _ => (),
-                    }
+                    self.binary_op(chunk::OpCode::Multiply)?;
                 }
                 Some(chunk::OpCode::Divide) => {
-                    let result = self.binary_op(chunk::OpCode::Divide);
-                    match result {
-                        Err(_) => return result,
-                        _ => (),
-                    }
+                    self.binary_op(chunk::OpCode::Divide)?;
                 }
                 Some(chunk::OpCode::Not) => {
                     let byte = self.pop();
@@ -470,56 +805,50 @@ impl VM {
                 Some(chunk::OpCode::Pop) => {
                     self.pop();
                 }
-                Some(chunk::OpCode::DefineGlobal) => {
-                    if let Some(object_string) = self.read_string() {
-                        if let Some(value) = self.peek() {
-                            // Promote stack struct if necessary when defining a global
-                            let promoted = self.promote_stack_struct_value_reason(value, Some("global assignment"), 0);
-                            if promoted.value_type != value.value_type { // replaced
-                                // overwrite top of stack with promoted heap instance
-                                self.stack[self.stack_top_pos - 1] = promoted;
-                            }
-                            self.globals.insert((unsafe { (*object_string).clone() }).content.clone(),
-                                self.peek().unwrap());
-                            self.pop();
-                        } else {
-                            return self.report(format!("No value on stack to define the global value {}.", (unsafe { (*object_string).clone() }).content).as_str());
-                        }
+                Some(chunk::OpCode::DefineGlobal) | Some(chunk::OpCode::DefineConstGlobal) => {
+                    // Layout: DefineGlobal <slot:u16>
+                    let constant = instruction == Some(chunk::OpCode::DefineConstGlobal);
+                    let Some(slot) = self.read_short() else { return self.report("Unknown global variable defination."); };
+                    let slot = slot as usize;
+                    if self.globals.is_constant(slot) {
+                        return self.report(format!("Cannot redefine constant '{}'.", self.globals.name(slot)).as_str());
+                    }
+                    if constant {
+                        self.globals.set_constant(slot);
+                    }
+                    if let Some(value) = self.peek() {
+                        self.globals.set(slot, value);
+                        self.gc.write_barrier(None, &value);
+                        self.pop();
                     } else {
-                        return self.report("Unknown global variable defination.");
+                        return self.report(format!("No value on stack to define the global value {}.", self.globals.name(slot)).as_str());
                     }
                 }
                 Some(chunk::OpCode::GetGlobal) => {
-                    if let Some(object_string) = self.read_string() {
-                        let key = unsafe { &(*object_string).content };
-                        if let Some(value) = self.globals.find(key) {
-                            self.push(value);
-                        } else {
-                            return self.report(format!("Undefined global variable {}.", key).as_str());
-                        }
+                    // Layout: GetGlobal <slot:u16>
+                    let Some(slot) = self.read_short() else { return self.report("Unknown global variable."); };
+                    if let Some(value) = self.globals.get(slot as usize) {
+                        self.push(value);
                     } else {
-                        return self.report("Unknown global variable.");
+                        return self.report(format!("Undefined global variable {}.", self.globals.name(slot as usize)).as_str());
                     }
                 }
                 Some(chunk::OpCode::SetGlobal) => {
-                    if let Some(object_string) = self.read_string() {
-                        if let Some(value) = self.peek() {
-                            let key = (unsafe { (*object_string).clone() }).content.clone();
-                            // Promote if needed
-                            let promoted = self.promote_stack_struct_value_reason(value, Some("global assignment"), 0);
-                            if promoted.value_type != value.value_type {
-                                self.stack[self.stack_top_pos - 1] = promoted;
-                            }
-                            if let None = self.globals.insert(key, value) { // It's a new key that means the target key has not been defined.
-                                self.globals.remove(&(unsafe { (*object_string).clone() }).content);
-                                return self.report("Unknown global variable.");
-                            }
-                        } else {
-                            return self.report(format!("No value on stack to set the global value {}.", (unsafe { (*object_string).clone() }).content).as_str());
-                        }
-                    } else {
+                    // Layout: SetGlobal <slot:u16>
+                    let Some(slot) = self.read_short() else { return self.report("Unknown global variable."); };
+                    let slot = slot as usize;
+                    if self.globals.is_constant(slot) {
+                        return self.report(format!("Cannot assign to constant '{}'.", self.globals.name(slot)).as_str());
+                    }
+                    if !self.globals.is_defined(slot) {
                         return self.report("Unknown global variable.");
                     }
+                    if let Some(value) = self.peek() {
+                        self.globals.set(slot, value);
+                        self.gc.write_barrier(None, &value);
+                    } else {
+                        return self.report(format!("No value on stack to set the global value {}.", self.globals.name(slot)).as_str());
+                    }
                 }
                 Some(chunk::OpCode::GetLocal) => {
                     if let Some(slot) = self.read_byte() {
@@ -553,6 +882,7 @@ impl VM {
                     let upvalue_index = *clousre.upvalues.get(slot as usize).unwrap();
                     let value = self.peek().unwrap();
                     self.set_upvalue(upvalue_index, value);
+                    self.gc.write_barrier(Some(self.open_upvalues[upvalue_index] as *mut Object), &value);
                 }
                 Some(chunk::OpCode::JumpIfFalse) => {
                     if let Some(offset) = self.read_short() {
@@ -604,63 +934,130 @@ impl VM {
                         return self.report("There are not enough bytes to read a short.");
                     }
                 }
+                Some(chunk::OpCode::CallNamed) => {
+                    // Layout: CallNamed <arg_count> <named_count> (<name_const_index>)*
+                    let argument_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed CallNamed (missing arg count)") };
+                    let named_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed CallNamed (missing named count)") };
+                    let mut names: Vec<String> = Vec::with_capacity(named_count as usize);
+                    for _ in 0..named_count {
+                        match self.read_constant() {
+                            Some(name) => names.push(unsafe { (*as_string_object(&name)).content.clone() }),
+                            None => return self.report("Malformed CallNamed (missing argument name)"),
+                        }
+                    }
+                    if !self.call_value_named(self.peek_steps(argument_count as usize).unwrap(), argument_count, &names) {
+                        return self.report("Instruction CallNamed failed.");
+                    }
+                }
+                Some(chunk::OpCode::ArgDefault) => {
+                    // Layout: ArgDefault <param_slot> <skip_offset:u16>; runs the default only if the argument was omitted.
+                    let slot = match self.read_byte() { Some(b) => b, None => return self.report("Malformed ArgDefault (missing slot)") };
+                    let offset = match self.read_short() { Some(o) => o, None => return self.report("Malformed ArgDefault (missing offset)") };
+                    if !self.current_frame().is_missing_arg(slot) {
+                        *self.current_frame().ip() += offset as usize;
+                    }
+                }
                 Some(chunk::OpCode::Invoke) => {
-                    // Layout: Invoke <method_name_const_index> <arg_count>
+                    // Layout: Invoke <method_name_const_index> <arg_count> <inline_cache:u16>
                     let method_index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed Invoke (missing method index)") } as usize;
                     let arg_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed Invoke (missing arg count)") };
+                    let cache_index = match self.read_short() { Some(s) => s, None => return self.report("Malformed Invoke (missing inline cache)") } as usize;
                     // Callee is receiver at distance arg_count from top (like Call)
                     let receiver = self.peek_steps(arg_count as usize).unwrap();
-                    // Determine type name for method table lookup
-                    let type_name = match receiver.value_type {
-                        crate::value::ValueType::ValueObject => {
-                            let obj_ptr = unsafe { receiver.value_as.object };
-                            let obj = unsafe { &*obj_ptr };
-                            if obj.obj_type != ObjectType::ObjStructInstance { return self.report("Invoke receiver must be struct instance"); }
-                            let inst_ptr = obj_ptr as *mut ObjectStructInstance;
-                            let stype_ptr = unsafe { (*inst_ptr).struct_type };
-                            unsafe { (*stype_ptr).name.clone() }
-                        }
-                        crate::value::ValueType::ValueStackStruct => {
-                            let idx = unsafe { receiver.value_as.stack_index };
-                            let arena = match self.frame_stack_structs.last() { Some(a) => a, None => return self.report("Missing frame arena") };
-                            if idx >= arena.len() { return self.report("Invalid stack struct index"); }
-                            let s = &arena[idx];
-                            unsafe { (*s.struct_type).name.clone() }
+                    if is_object(&receiver) && unsafe { (*as_mutable_object(&receiver)).obj_type } == ObjectType::ObjEnumType {
+                        // EnumName.Variant(payload...) constructs a variant value.
+                        let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
+                        let vval = unsafe { *(*chunk_ptr).get_constant(method_index) };
+                        let vname = unsafe { (*as_string_object(&vval)).content.clone() };
+                        let enum_type = as_mutable_object(&receiver) as *mut ObjectEnumType;
+                        let (value, size) = match self.make_enum_value(enum_type, &vname, arg_count as usize) {
+                            Ok(result) => result,
+                            Err(message) => return self.report(&message),
+                        };
+                        self.stack_top_pos -= arg_count as usize + 1;
+                        self.push(value);
+                        self.track_allocation(size);
+                        continue;
+                    }
+                    if is_object(&receiver) && unsafe { (*as_mutable_object(&receiver)).obj_type } == ObjectType::ObjTrait {
+                        // Trait.method(x, args...) calls that trait's method for x's type; x becomes self.
+                        let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
+                        let mval = unsafe { *(*chunk_ptr).get_constant(method_index) };
+                        let mname = unsafe { (*as_string_object(&mval)).content.clone() };
+                        let trait_name = unsafe { (*(as_mutable_object(&receiver) as *const crate::objects::object_trait::ObjectTrait)).name.clone() };
+                        let method = match self.trait_method(&trait_name, &mname, arg_count) {
+                            Ok(method) => method,
+                            Err(message) => return self.report(&message),
+                        };
+                        let callee_pos = self.stack_top_pos - arg_count as usize - 1;
+                        self.stack[callee_pos] = method;
+                        if !self.call_value(method, arg_count) { return self.report("Invoke call failed"); }
+                        continue;
+                    }
+                    // Struct receivers resolve through the instruction's inline cache.
+                    let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
+                    let struct_type = match self.receiver_struct_type(&receiver) {
+                        Ok(struct_type) => struct_type,
+                        Err(message) => return self.report(&message),
+                    };
+                    if let Some(struct_type) = struct_type {
+                        let cache = unsafe { (*chunk_ptr).inline_cache(cache_index) };
+                        if std::ptr::eq(cache.struct_type, struct_type) && cache.epoch == self.cache_epoch {
+                            let method = cache.method;
+                            if self.stack_top_pos >= MAX_STACK_SIZE { return self.report("Stack overflow during invoke"); }
+                            if !self.call_method(method, arg_count, &[]) { return self.report("Invoke call failed"); }
+                            continue;
                         }
-                        _ => return self.report("Invoke receiver must be object or stack struct"),
+                    }
+                    // Determine type name for method table lookup; impls for builtin types use names like "string".
+                    let type_name = match struct_type {
+                        Some(struct_type) => unsafe { (*struct_type).name.clone() },
+                        None => self.type_name_of(receiver),
                     };
                     // Resolve method function
-                    let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
                     let mval = unsafe { *(*chunk_ptr).get_constant(method_index) };
                     if !is_string(&mval) { return self.report("Invoke method name constant not string"); }
-                    let mname = unsafe { (*as_string_object(&mval)).content.clone() };
+                    let mname: &str = unsafe { &(*as_string_object(&mval)).content };
                     match self.type_methods.get(type_name.as_str()) {
                         Some(table) => {
-                            match table.find(mname.as_str()) {
+                            match table.find(mname) {
                                 Some(func_val) => {
-                                    // Stack layout before: [..., receiver, arg1, ..., argN]
-                                    // Insert callee before receiver so layout becomes: [..., callee, receiver, arg1, ..., argN]
-                                    let insert_pos = self.stack_top_pos - arg_count as usize - 1;
+                                    if let Some(struct_type) = struct_type {
+                                        let epoch = self.cache_epoch;
+                                        unsafe { *(*chunk_ptr).inline_cache_mut(cache_index) = InlineCache { struct_type, slot: 0, method: func_val, epoch }; }
+                                    }
                                     if self.stack_top_pos >= MAX_STACK_SIZE { return self.report("Stack overflow during invoke"); }
-                                    // make room
-                                    let old_top = self.stack_top_pos;
-                                    self.stack_top_pos += 1;
-                                    // shift right
-                                    let mut i = old_top;
-                                    while i > insert_pos { self.stack[i] = self.stack[i-1]; i -= 1; }
-                                    // insert callee
-                                    self.stack[insert_pos] = func_val;
-                                    // include receiver as first arg
-                                    let new_argc = arg_count + 1;
-                                    
-                                    if !self.call_value(func_val, new_argc) { return self.report("Invoke call failed"); }
+                                    if !self.call_method(func_val, arg_count, &[]) { return self.report("Invoke call failed"); }
                                 }
-                                None => return self.report(format!("Unknown method '{}' for type '{}'", mname, type_name).as_str()),
+                                None => return self.report(self.missing_method_message(&type_name, mname).as_str()),
                             }
                         }
                         None => return self.report(format!("No methods registered for type '{}'", type_name).as_str()),
                     }
                 }
+                Some(chunk::OpCode::SuperInvoke) => {
+                    // Layout: SuperInvoke <trait_name_const> <method_name_const> <arg_count>; self and the args are on the stack.
+                    let trait_name = match self.read_constant() {
+                        Some(name) => unsafe { (*as_string_object(&name)).content.clone() },
+                        None => return self.report("Malformed SuperInvoke (missing trait name)"),
+                    };
+                    let method_name = match self.read_constant() {
+                        Some(name) => unsafe { (*as_string_object(&name)).content.clone() },
+                        None => return self.report("Malformed SuperInvoke (missing method name)"),
+                    };
+                    let arg_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed SuperInvoke (missing arg count)") };
+                    let receiver = self.peek_steps(arg_count as usize).unwrap();
+                    let type_name = self.type_name_of(receiver);
+                    let method = self.trait_ancestors(&trait_name).into_iter().find_map(|ancestor| {
+                        self.type_traits.get(type_name.as_str())?.get(ancestor.as_str())?.find(method_name.as_str())
+                    });
+                    let method = match method {
+                        Some(method) => method,
+                        None => return self.report(format!("No supertrait of '{}' provides method '{}' for type '{}'.", trait_name, method_name, type_name).as_str()),
+                    };
+                    if self.stack_top_pos >= MAX_STACK_SIZE { return self.report("Stack overflow during invoke"); }
+                    if !self.call_method(method, arg_count, &[]) { return self.report("Invoke call failed"); }
+                }
                 Some(chunk::OpCode::Closure) => {
                     if let Some(function_index) = self.read_constant() {
                         let object_function = as_function_object(&function_index) as *mut ObjectFunction;
@@ -671,7 +1068,7 @@ impl VM {
                             let index = self.read_byte().unwrap();
                             if is_local == 0 {
                                 let upvalues = &mut self.current_frame().closure().upvalues;
-                                let uv_index = upvalues.get(index as usize).unwrap().clone();
+                                let uv_index = *upvalues.get(index as usize).unwrap();
                                 unsafe { (*closure_ptr).upvalues.push(uv_index); }
                             } else {
                                 let slot = unsafe { self.current_frame().get_stack_base().add(index as usize) };
@@ -695,7 +1092,7 @@ impl VM {
                 Some(chunk::OpCode::Return) => {
                     let result = self.pop();
                     // If returning a stack struct created in this frame -> runtime error (per spec unknown behavior -> forbid)
-                    if result.value_type == crate::value::ValueType::ValueStackStruct {
+                    if is_stack_struct(&result) {
                         // Disallow returning frame-local stack struct
                         return self.report("Cannot return stack-allocated struct; use 'new' to allocate on heap");
                     }
@@ -710,6 +1107,9 @@ impl VM {
                     }
                     self.stack_top_pos = stack_top_pos;
                     self.push(result);
+                    if self.frames.len() == base_frame_count {
+                        return Ok(InterpretResult::InterpretOk);
+                    }
                 }
                 Some(chunk::OpCode::ImplementTrait) => {
                     // Layout emitted: ImplementTrait <trait_name_const_index> <method_count> <method_name_const_index>...
@@ -724,18 +1124,31 @@ impl VM {
                         if !is_string(&mv) { return self.report("Trait method name constant not string"); }
                         methods.push(unsafe { (*as_string_object(&mv)).content.clone() });
                     }
+                    let super_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed ImplementTrait (missing supertrait count)") } as usize;
+                    let mut supertraits: Vec<String> = Vec::with_capacity(super_count);
+                    for _ in 0..super_count {
+                        let si = match self.read_byte() { Some(b) => b, None => return self.report("Malformed ImplementTrait (missing supertrait name index)") } as usize;
+                        let sv = unsafe { *(*chunk_ptr).get_constant(si) };
+                        let sname = unsafe { (*as_string_object(&sv)).content.clone() };
+                        if self.trait_registry.find(sname.as_str()).is_none() {
+                            return self.report(format!("Unknown supertrait '{}'.", sname).as_str());
+                        }
+                        supertraits.push(sname);
+                    }
                     // Accept either a trait object constant or a name string constant
-                    if is_object(&name_val) && unsafe { (*name_val.value_as.object).obj_type } == ObjectType::ObjTrait {
-                        let tptr = unsafe { name_val.value_as.object as *mut crate::objects::object_trait::ObjectTrait };
+                    if is_object(&name_val) && unsafe { (*as_mutable_object(&name_val)).obj_type } == ObjectType::ObjTrait {
+                        let tptr = as_mutable_object(&name_val) as *mut crate::objects::object_trait::ObjectTrait;
                         let tname = unsafe { (*tptr).name.clone() };
                         unsafe { (*tptr).method_names = methods; }
+                        unsafe { (*tptr).supertraits = supertraits; }
                         self.trait_registry.insert(tname, name_val);
                     } else if is_string(&name_val) {
                         let trait_name = unsafe { (*as_string_object(&name_val)).content.clone() };
                         if self.trait_registry.find(trait_name.as_str()).is_none() {
                             let (tptr, size) = self.object_manager.alloc_trait(trait_name.clone());
                             unsafe { (*tptr).method_names = methods; }
-                            self.trait_registry.insert(trait_name, Value { value_type: crate::value::ValueType::ValueObject, value_as: crate::value::ValueUnion { object: tptr as *mut crate::objects::object::Object } });
+                            unsafe { (*tptr).supertraits = supertraits; }
+                            self.trait_registry.insert(trait_name, make_object_value(tptr as *mut crate::objects::object::Object));
                             self.track_allocation(size);
                         }
                     } else { return self.report("ImplementTrait constant must be trait object or name string"); }
@@ -754,6 +1167,12 @@ impl VM {
                     let trait_name = unsafe { (*as_string_object(&trait_val)).content.clone() };
                     let type_name = unsafe { (*as_string_object(&type_val)).content.clone() };
                     if self.trait_registry.find(trait_name.as_str()).is_none() { return self.report("ImplRegister references unknown trait"); }
+                    // Supertraits must already be implemented for the type.
+                    for ancestor in self.trait_ancestors(&trait_name) {
+                        if !self.type_traits.get(type_name.as_str()).is_some_and(|traits| traits.contains_key(ancestor.as_str())) {
+                            return self.report(format!("Type '{}' must implement '{}' before '{}'.", type_name, ancestor, trait_name).as_str());
+                        }
+                    }
 
                     for _ in 0..count {
                         // Read method name
@@ -767,8 +1186,12 @@ impl VM {
                         let fn_val = unsafe { *(*chunk_ptr).get_constant(fn_idx) };
                         if !is_object(&fn_val) { return self.report("ImplRegister function const not object"); }
                         
-                        if unsafe { (*fn_val.value_as.object).obj_type } != ObjectType::ObjFunction { return self.report("ImplRegister constant is not a function object"); }
-                        let func_ptr = unsafe { fn_val.value_as.object as *mut ObjectFunction };
+                        if unsafe { (*as_mutable_object(&fn_val)).obj_type } != ObjectType::ObjFunction { return self.report("ImplRegister constant is not a function object"); }
+                        let func_ptr = as_mutable_object(&fn_val) as *mut ObjectFunction;
+                        // The collector calls drop() with no arguments.
+                        if trait_name == "Drop" && method_name == "drop" && unsafe { (*func_ptr).arity } != 1 {
+                            return self.report("drop() takes no parameters besides self.");
+                        }
                         let (closure_ptr, size) = self.object_manager.alloc_closure(func_ptr);
                         
 
@@ -785,7 +1208,7 @@ impl VM {
                             if is_local == 0 {
                                 // Capture from enclosing closure's upvalues
                                 let upvalues = &mut self.current_frame().closure().upvalues;
-                                let uv_index = upvalues.get(index as usize).unwrap().clone();
+                                let uv_index = *upvalues.get(index as usize).unwrap();
                                 unsafe { (*closure_ptr).upvalues.push(uv_index); }
                             } else {
                                 // Capture a local from the current frame
@@ -798,8 +1221,21 @@ impl VM {
                         // Closure already pushed; add to registry table BEFORE accounting/GC
                         let top = self.peek().unwrap();
                         {
+                            let traits = self.type_traits.entry(type_name.clone()).or_default();
+                            traits.entry(trait_name.clone()).or_insert_with(Table::new).insert(method_name.clone(), top);
+                            // A name provided by two unrelated traits of the type is ambiguous; callers must pick one with
+                            // Trait.method(x). A subtrait's method overrides its supertraits' versions.
+                            let providers = self.method_providers(&type_name, &method_name);
+                            let resolved = match providers.as_slice() {
+                                [only] => self.type_traits[type_name.as_str()][only.as_str()].find(method_name.as_str()),
+                                _ => None,
+                            };
                             let table = self.type_methods.entry(type_name.clone()).or_insert_with(Table::new);
-                            table.insert(method_name.clone(), top);
+                            match resolved {
+                                Some(method) => { table.insert(method_name.clone(), method); }
+                                None => { table.remove(&method_name); }
+                            }
+                            self.cache_epoch += 1;
                         }
                         // Now account bytes; table/root already holds a copy to prevent GC reclamation
                         self.track_allocation(size);
@@ -808,8 +1244,22 @@ impl VM {
                     }
                     
                 }
+                Some(chunk::OpCode::IsTrait) => {
+                    // Layout: IsTrait <trait_name_const_index>; replaces the value with a bool.
+                    let trait_name = match self.read_constant() {
+                        Some(name) => unsafe { (*as_string_object(&name)).content.clone() },
+                        None => return self.report("Malformed IsTrait (missing trait name)"),
+                    };
+                    let value = self.pop();
+                    match self.implements_trait(value, &trait_name) {
+                        Ok(result) => self.push(make_bool_value(result)),
+                        Err(message) => return self.report(&message),
+                    }
+                }
                 Some(chunk::OpCode::StructType) => {
-                    // Layout: StructType <name_const_index> <field_count> <field_name_const_index>*
+                    // Layout: StructType <name_const_index> <field_count> (<field_name_const_index> <flags>)*
+                    // flags: chunk::STRUCT_FIELD_DEFAULT | STRUCT_FIELD_READONLY | STRUCT_FIELD_PRIVATE
+                    // The default closures sit on the stack in field order.
                     let name_index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructType (missing name index)") } as usize;
                     let field_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructType (missing field count)") } as usize;
                     let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
@@ -817,13 +1267,29 @@ impl VM {
                     if !is_string(&name_value) { return self.report("StructType name constant not string"); }
                     // Collect field names
                     let mut field_names: Vec<String> = Vec::with_capacity(field_count);
+                    let mut has_default: Vec<bool> = Vec::with_capacity(field_count);
+                    let mut field_access: Vec<FieldAccess> = Vec::with_capacity(field_count);
                     for _ in 0..field_count {
                         let fi = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructType (missing field name index)") } as usize;
                         let fv = unsafe { *(*chunk_ptr).get_constant(fi) };
                         if !is_string(&fv) { return self.report("StructType field name constant not string"); }
                         let fname = unsafe { (*as_string_object(&fv)).content.clone() };
                         field_names.push(fname);
+                        let flags = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructType (missing field flags)") };
+                        has_default.push(flags & chunk::STRUCT_FIELD_DEFAULT != 0);
+                        field_access.push(if flags & chunk::STRUCT_FIELD_PRIVATE != 0 {
+                            FieldAccess::Private
+                        } else if flags & chunk::STRUCT_FIELD_READONLY != 0 {
+                            FieldAccess::ReadOnly
+                        } else {
+                            FieldAccess::Public
+                        });
                     }
+                    let default_count = has_default.iter().filter(|d| **d).count();
+                    let mut defaults = self.stack[self.stack_top_pos - default_count..self.stack_top_pos].iter();
+                    let field_defaults: Vec<Value> = has_default.iter()
+                        .map(|d| if *d { *defaults.next().unwrap() } else { make_nil_value() })
+                        .collect();
                     // If already registered, ignore (redefinition warning could be added later)
                     unsafe {
                         let struct_name = (*as_string_object(&name_value)).content.clone();
@@ -833,186 +1299,785 @@ impl VM {
                                 (*stype_ptr).field_index.insert(fname.clone(), make_numer_value((*stype_ptr).field_names.len() as f64));
                                 (*stype_ptr).field_names.push(fname.clone());
                             }
+                            (*stype_ptr).field_defaults = field_defaults;
+                            (*stype_ptr).field_access = field_access;
                             // store registry value (struct type object) in struct_types table
-                            self.struct_types.insert(struct_name, Value { value_type: crate::value::ValueType::ValueObject, value_as: crate::value::ValueUnion { object: stype_ptr as *mut crate::objects::object::Object } });
+                            self.struct_types.insert(struct_name, make_object_value(stype_ptr as *mut crate::objects::object::Object));
                             self.track_allocation(size);
                         }
                     }
+                    self.stack_top_pos -= default_count;
                 }
-                Some(chunk::OpCode::StructInstantiate) => {
-                    // Layout emitted by compiler: StructInstantiate <type_name_const_index> <field_count> <field_name_const_index>* then field values already on stack in order of appearance
-                    let type_name_index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructInstantiate (missing type name index)") } as usize;
-                    let field_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructInstantiate (missing field count)") } as usize;
-                    let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
-                    let type_name_value = unsafe { *(*chunk_ptr).get_constant(type_name_index) };
-                    if !is_string(&type_name_value) { return self.report("StructInstantiate type name constant not string"); }
-                    let mut literal_field_names: Vec<String> = Vec::with_capacity(field_count);
-                    for _ in 0..field_count {
-                        let fi = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructInstantiate (missing field name const index)") } as usize;
-                        let fv = unsafe { *(*chunk_ptr).get_constant(fi) };
-                        if !is_string(&fv) { return self.report("StructInstantiate field name constant not string"); }
-                        let fname = unsafe { (*as_string_object(&fv)).content.clone() };
-                        literal_field_names.push(fname);
-                    }
-                    let struct_name = unsafe { (*as_string_object(&type_name_value)).content.clone() };
-                    // Lookup struct type in registry
-                    let stype_val = match self.struct_types.find(struct_name.as_str()) { Some(v) => v, None => return self.report("Unknown struct type in literal") };
-                    if stype_val.value_type != crate::value::ValueType::ValueObject { return self.report("Struct type registry entry invalid"); }
-                    if unsafe { (*stype_val.value_as.object).obj_type } != ObjectType::ObjStructType { return self.report("Registry entry not struct type"); }
-                    let stype_ptr = unsafe { stype_val.value_as.object as *mut crate::objects::object_struct::ObjectStructType };
-                    // Validate fields: order doesn't need to match definition, we'll place by index.
-                    let expected_count = unsafe { (*stype_ptr).field_names.len() };
-                    if field_count != expected_count { return self.report("Field count mismatch in struct literal"); }
-                    // Pop values in reverse order to collect, since stack has them in evaluation order.
-                    let mut provided_values: Vec<(usize, Value)> = Vec::with_capacity(field_count);
-                    for lname in literal_field_names.iter().rev() { // reverse to align with pop order
-                        let val = self.pop();
-                        // lookup index
-                        let idx_val = unsafe { (*stype_ptr).field_index.find(lname.as_str()) };
-                        if idx_val.is_none() { return self.report("Unknown field in struct literal"); }
-                        let idx_num = idx_val.unwrap();
-                        if !is_number(&idx_num) { return self.report("Corrupt field index table"); }
-                        let slot = as_number(&idx_num) as usize;
-                        provided_values.push((slot, val));
+                Some(chunk::OpCode::StructInstantiate) | Some(chunk::OpCode::StructInstantiateStack) => {
+                    // Layout emitted by compiler: StructInstantiate <type_name_const_index> <field_count> <has_base> <field_name_const_index>*
+                    // then field values already on stack in order of appearance, followed by the ..base value if any
+                    let (stype_ptr, literal_field_names, has_base) = match self.read_struct_literal("StructInstantiate") {
+                        Ok(literal) => literal,
+                        Err(message) => return self.report(&message),
+                    };
+                    let fields = self.struct_literal_fields(stype_ptr, &literal_field_names, has_base)?;
+                    // The compiler found the value doesn't outlive the frame, so it can live in the frame's arena;
+                    // types with a Drop impl stay on the heap so the collector can call drop().
+                    if instruction == Some(chunk::OpCode::StructInstantiateStack) && !self.implements_drop(stype_ptr) {
+                        match self.frame_stack_structs.last_mut() {
+                            Some(last) => {
+                                last.push(StackStruct { struct_type: stype_ptr, fields });
+                                let index = last.len() - 1;
+                                self.push(make_stack_struct_value(index));
+                            }
+                            None => return self.report("No frame arena for stack struct"),
+                        }
+                        continue;
                     }
-                    provided_values.reverse();
                     // Allocate instance
-                    let (inst_ptr, size) = self.object_manager.alloc_struct_instance(stype_ptr, expected_count);
-                    for (slot, val) in provided_values.into_iter() { unsafe { (*inst_ptr).fields[slot] = val; } }
+                    let (inst_ptr, size) = self.object_manager.alloc_struct_instance(stype_ptr, fields.len());
+                    unsafe { (*inst_ptr).fields = fields; }
+                    // push instance value before accounting so a collection sees it as a root
+                    self.push(make_object_value(inst_ptr as *mut crate::objects::object::Object));
                     self.track_allocation(size);
-                    // push instance value
-                    self.push(Value { value_type: crate::value::ValueType::ValueObject, value_as: crate::value::ValueUnion { object: inst_ptr as *mut crate::objects::object::Object } });
-                }
-                Some(chunk::OpCode::StructInstantiateStack) => {
-                    // Same layout as heap instantiate but produce stack struct
-                    let type_name_index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructInstantiateStack (missing type name index)") } as usize;
-                    let field_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructInstantiateStack (missing field count)") } as usize;
-                    let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
-                    let type_name_value = unsafe { *(*chunk_ptr).get_constant(type_name_index) };
-                    if !is_string(&type_name_value) { return self.report("StructInstantiateStack type name constant not string"); }
-                    let mut literal_field_names: Vec<String> = Vec::with_capacity(field_count);
-                    for _ in 0..field_count {
-                        let fi = match self.read_byte() { Some(b) => b, None => return self.report("Malformed StructInstantiateStack (missing field name const index)") } as usize;
-                        let fv = unsafe { *(*chunk_ptr).get_constant(fi) };
-                        if !is_string(&fv) { return self.report("StructInstantiateStack field name constant not string"); }
-                        let fname = unsafe { (*as_string_object(&fv)).content.clone() };
-                        literal_field_names.push(fname);
-                    }
-                    let struct_name = unsafe { (*as_string_object(&type_name_value)).content.clone() };
-                    let stype_val = match self.struct_types.find(struct_name.as_str()) { Some(v) => v, None => return self.report("Unknown struct type in stack literal") };
-                    if stype_val.value_type != crate::value::ValueType::ValueObject { return self.report("Struct type registry entry invalid"); }
-                    if unsafe { (*stype_val.value_as.object).obj_type } != ObjectType::ObjStructType { return self.report("Registry entry not struct type"); }
-                    let stype_ptr = unsafe { stype_val.value_as.object as *mut ObjectStructType };
-                    let expected_count = unsafe { (*stype_ptr).field_names.len() };
-                    if field_count != expected_count { return self.report("Field count mismatch in stack struct literal"); }
-                    let mut provided_values: Vec<(usize, Value)> = Vec::with_capacity(field_count);
-                    for lname in literal_field_names.iter().rev() {
-                        let val = self.pop();
-                        let idx_val = unsafe { (*stype_ptr).field_index.find(lname.as_str()) };
-                        if idx_val.is_none() { return self.report("Unknown field in stack struct literal"); }
-                        let idx_num = idx_val.unwrap();
-                        if !is_number(&idx_num) { return self.report("Corrupt field index table"); }
-                        let slot = as_number(&idx_num) as usize;
-                        provided_values.push((slot, val));
-                    }
-                    provided_values.reverse();
-                    let mut fields = vec![Value::new(); expected_count];
-                    for (slot, val) in provided_values.into_iter() { fields[slot] = val; }
-                    if let Some(last) = self.frame_stack_structs.last_mut() {
-                        last.push(StackStruct { struct_type: stype_ptr, fields });
-                        let index = last.len() - 1;
-                        self.push(Value { value_type: crate::value::ValueType::ValueStackStruct, value_as: crate::value::ValueUnion { stack_index: index } });
-                    } else {
-                        return self.report("No frame arena for stack struct");
-                    }
                 }
                 Some(chunk::OpCode::GetField) => {
-                    // Layout: GetField <field_name_const_index>
+                    // Layout: GetField <field_name_const_index> <inline_cache:u16>
                     let field_name_index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed GetField (missing name index)") } as usize;
+                    let cache_index = match self.read_short() { Some(s) => s, None => return self.report("Malformed GetField (missing inline cache)") } as usize;
                     let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
                     let name_val = unsafe { *(*chunk_ptr).get_constant(field_name_index) };
                     if !is_string(&name_val) { return self.report("GetField constant not string"); }
-                    let field_name = unsafe { (*as_string_object(&name_val)).content.clone() };
+                    let field_name: &str = unsafe { &(*as_string_object(&name_val)).content };
+                    let receiver = self.peek().unwrap();
+                    if is_object(&receiver) && unsafe { (*as_mutable_object(&receiver)).obj_type } == ObjectType::ObjEnumType {
+                        // EnumName.Variant for a variant without payload.
+                        let enum_type = as_mutable_object(&receiver) as *mut ObjectEnumType;
+                        let (value, size) = match self.make_enum_value(enum_type, field_name, 0) {
+                            Ok(result) => result,
+                            Err(message) => return self.report(&message),
+                        };
+                        self.pop();
+                        self.push(value);
+                        self.track_allocation(size);
+                        continue;
+                    }
                     let receiver = self.pop();
-                    let value = match receiver.value_type {
-                        crate::value::ValueType::ValueObject => {
-                            let obj_ptr = unsafe { receiver.value_as.object };
-                            let obj = unsafe { &*obj_ptr };
-                            if obj.obj_type != ObjectType::ObjStructInstance { return self.report("Receiver not struct instance"); }
-                            let inst_ptr = obj_ptr as *mut ObjectStructInstance;
-                            let stype_ptr = unsafe { (*inst_ptr).struct_type };
-                            let idx_val = unsafe { (*stype_ptr).field_index.find(field_name.as_str()) };
-                            if idx_val.is_none() { return self.report("Unknown field on struct instance"); }
-                            let idx_v = idx_val.unwrap(); if !is_number(&idx_v) { return self.report("Corrupt field index table"); }
-                            let slot = as_number(&idx_v) as usize;
-                            unsafe { (*inst_ptr).fields[slot] }
-                        }
-                        crate::value::ValueType::ValueStackStruct => {
-                            let idx = unsafe { receiver.value_as.stack_index };
-                            let arena = match self.frame_stack_structs.last() { Some(a) => a, None => return self.report("Missing frame arena") };
-                            if idx >= arena.len() { return self.report("Invalid stack struct index"); }
-                            let s = &arena[idx];
-                            let idx_val = unsafe { (*s.struct_type).field_index.find(field_name.as_str()) };
-                            if idx_val.is_none() { return self.report("Unknown field on stack struct") };
-                            let idx_v = idx_val.unwrap(); if !is_number(&idx_v) { return self.report("Corrupt field index table"); }
-                            let slot = as_number(&idx_v) as usize;
-                            s.fields[slot]
-                        }
-                        _ => return self.report("Only instances have fields"),
+                    let struct_type = match self.receiver_struct_type(&receiver) {
+                        Ok(struct_type) => struct_type,
+                        Err(message) => return self.report(&message),
                     };
-                    self.push(value);
+                    // None: no such field, so obj.name may name a method instead.
+                    let value = match struct_type {
+                        Some(struct_type) => match self.field_slot(chunk_ptr, cache_index, struct_type, field_name) {
+                            Ok(Some(slot)) => {
+                                if let Err(message) = self.check_field_access(struct_type, slot, false) { return self.report(&message); }
+                                Some(*self.struct_field_mut(&receiver, slot))
+                            }
+                            Ok(None) => None,
+                            Err(message) => return self.report(&message),
+                        },
+                        None => None,
+                    };
+                    match value {
+                        Some(value) => self.push(value),
+                        None => {
+                            self.push(receiver);
+                            if let Err(message) = self.bind_method(field_name) { return self.report(&message); }
+                        }
+                    }
                 }
                 Some(chunk::OpCode::SetField) => {
-                    // Layout: SetField <field_name_const_index>; stack: receiver value (value on top)
+                    // Layout: SetField <field_name_const_index> <inline_cache:u16>; stack: receiver value (value on top)
                     let field_name_index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed SetField (missing name index)") } as usize;
+                    let cache_index = match self.read_short() { Some(s) => s, None => return self.report("Malformed SetField (missing inline cache)") } as usize;
                     let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
                     let name_val = unsafe { *(*chunk_ptr).get_constant(field_name_index) };
                     if !is_string(&name_val) { return self.report("SetField constant not string"); }
-                    let field_name = unsafe { (*as_string_object(&name_val)).content.clone() };
+                    let field_name: &str = unsafe { &(*as_string_object(&name_val)).content };
                     let value = self.pop();
                     let receiver = self.pop();
-                    match receiver.value_type {
-                        crate::value::ValueType::ValueObject => {
-                            let obj_ptr = unsafe { receiver.value_as.object };
-                            let obj = unsafe { &*obj_ptr };
-                            if obj.obj_type != ObjectType::ObjStructInstance { return self.report("Receiver not struct instance"); }
-                            let inst_ptr = obj_ptr as *mut ObjectStructInstance;
-                            let stype_ptr = unsafe { (*inst_ptr).struct_type };
-                            let idx_val = unsafe { (*stype_ptr).field_index.find(field_name.as_str()) };
-                            if idx_val.is_none() { return self.report("Unknown field on struct instance"); }
-                            let idx_v = idx_val.unwrap(); if !is_number(&idx_v) { return self.report("Corrupt field index table"); }
-                            let slot = as_number(&idx_v) as usize;
-                            unsafe { (*inst_ptr).fields[slot] = value; }
-                        }
-                        crate::value::ValueType::ValueStackStruct => {
-                            let idx = unsafe { receiver.value_as.stack_index };
-                            let arena = match self.frame_stack_structs.last_mut() { Some(a) => a, None => return self.report("Missing frame arena") };
-                            if idx >= arena.len() { return self.report("Invalid stack struct index"); }
-                            let s = &mut arena[idx];
-                            let idx_val = unsafe { (*s.struct_type).field_index.find(field_name.as_str()) };
-                            if idx_val.is_none() { return self.report("Unknown field on stack struct"); }
-                            let idx_v = idx_val.unwrap(); if !is_number(&idx_v) { return self.report("Corrupt field index table"); }
-                            let slot = as_number(&idx_v) as usize;
-                            s.fields[slot] = value;
-                        }
-                        _ => return self.report("Only instances have fields"),
+                    let struct_type = match self.receiver_struct_type(&receiver) {
+                        Ok(Some(struct_type)) => struct_type,
+                        Ok(None) if is_object(&receiver) => return self.report("Receiver not struct instance"),
+                        Ok(None) => return self.report("Only instances have fields"),
+                        Err(message) => return self.report(&message),
+                    };
+                    let slot = match self.field_slot(chunk_ptr, cache_index, struct_type, field_name) {
+                        Ok(Some(slot)) => slot,
+                        Ok(None) if is_stack_struct(&receiver) => return self.report("Unknown field on stack struct"),
+                        Ok(None) => return self.report("Unknown field on struct instance"),
+                        Err(message) => return self.report(&message),
+                    };
+                    if let Err(message) = self.check_field_access(struct_type, slot, true) { return self.report(&message); }
+                    *self.struct_field_mut(&receiver, slot) = value;
+                    if is_object(&receiver) {
+                        self.gc.write_barrier(Some(as_mutable_object(&receiver)), &value);
                     }
                     // push assigned value like typical expression semantics
                     self.push(value);
                 }
+                Some(chunk::OpCode::BuildList) => {
+                    // Layout: BuildList <count>; elements are on the stack in source order.
+                    let count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed BuildList (missing count)") } as usize;
+                    let first = self.stack_top_pos - count;
+                    let items = self.stack[first..self.stack_top_pos].to_vec();
+                    let (list_ptr, size) = self.object_manager.alloc_list(items);
+                    self.stack_top_pos = first;
+                    self.push(make_list_value(list_ptr));
+                    self.track_allocation(size);
+                }
+                Some(chunk::OpCode::BuildTuple) => {
+                    // Layout: BuildTuple <count>; elements are on the stack in source order.
+                    let count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed BuildTuple (missing count)") } as usize;
+                    let first = self.stack_top_pos - count;
+                    let items = self.stack[first..self.stack_top_pos].to_vec();
+                    let (tuple_ptr, size) = self.object_manager.alloc_tuple(items);
+                    self.stack_top_pos = first;
+                    self.push(make_tuple_value(tuple_ptr));
+                    self.track_allocation(size);
+                }
+                Some(chunk::OpCode::BuildMap) => {
+                    // Layout: BuildMap <count>; stack holds key1, value1, ..., keyN, valueN.
+                    let count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed BuildMap (missing count)") } as usize;
+                    let first = self.stack_top_pos - count * 2;
+                    for i in (first..self.stack_top_pos).step_by(2) {
+                        if !is_string(&self.stack[i]) { return self.report("Map keys must be strings."); }
+                    }
+                    let (map_ptr, size) = self.object_manager.alloc_map();
+                    for i in (first..self.stack_top_pos).step_by(2) {
+                        let key = self.stack[i];
+                        let name = unsafe { (*as_string_object(&key)).content.as_str() };
+                        unsafe { (*map_ptr).insert(key, name, self.stack[i + 1]); }
+                    }
+                    self.stack_top_pos = first;
+                    self.push(make_map_value(map_ptr));
+                    self.track_allocation(size);
+                }
+                Some(chunk::OpCode::GetTupleItem) => {
+                    // Layout: GetTupleItem <index>
+                    let index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed GetTupleItem (missing index)") } as usize;
+                    let tuple = self.pop();
+                    if !is_tuple(&tuple) { return self.report("Only tuples have positional fields."); }
+                    let items = unsafe { &(*as_tuple_object(&tuple)).items };
+                    match items.get(index) {
+                        Some(item) => { let item = *item; self.push(item); }
+                        None => return self.report(format!("Tuple index {} out of range for a tuple of {} element(s).", index, items.len()).as_str()),
+                    }
+                }
+                Some(chunk::OpCode::GetIndex) => {
+                    // stack: receiver index
+                    let index = self.pop();
+                    let receiver = self.pop();
+                    match self.index_get(receiver, index) {
+                        Ok(value) => self.push(value),
+                        Err(message) => return self.report(&message),
+                    }
+                }
+                Some(chunk::OpCode::SetIndex) => {
+                    // stack: receiver index value (value on top); leaves the assigned value
+                    let value = self.peek_steps(0).unwrap();
+                    let index = self.peek_steps(1).unwrap();
+                    let receiver = self.peek_steps(2).unwrap();
+                    if let Err(message) = self.index_set(receiver, index, value) { return self.report(&message); }
+                    // List element or map entry (a new map key is stored too).
+                    let holder = as_mutable_object(&receiver);
+                    self.gc.write_barrier(Some(holder), &index);
+                    self.gc.write_barrier(Some(holder), &value);
+                    self.stack_top_pos -= 3;
+                    self.push(value);
+                }
+                Some(chunk::OpCode::BuildRange) => {
+                    // Layout: BuildRange <inclusive>; stack: start end
+                    let inclusive = match self.read_byte() { Some(b) => b != 0, None => return self.report("Malformed BuildRange (missing inclusive flag)") };
+                    let end = self.pop();
+                    let start = self.pop();
+                    if !is_number(&start) || !is_number(&end) { return self.report("Range bounds must be numbers."); }
+                    let (range_ptr, size) = self.object_manager.alloc_range(as_number(&start), as_number(&end), inclusive);
+                    self.push(make_range_value(range_ptr));
+                    self.track_allocation(size);
+                }
+                Some(chunk::OpCode::EnumType) => {
+                    // Layout: EnumType <name_const_index> <variant_count> (<variant_name_const_index> <arity>)*
+                    let name_index = match self.read_byte() { Some(b) => b, None => return self.report("Malformed EnumType (missing name index)") } as usize;
+                    let variant_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed EnumType (missing variant count)") } as usize;
+                    let chunk_ptr = unsafe { self.current_chunk() } as *mut Box<Chunk>;
+                    let name_value = unsafe { *(*chunk_ptr).get_constant(name_index) };
+                    if !is_string(&name_value) { return self.report("EnumType name constant not string"); }
+                    let mut variants: Vec<EnumVariant> = Vec::with_capacity(variant_count);
+                    for _ in 0..variant_count {
+                        let vi = match self.read_byte() { Some(b) => b, None => return self.report("Malformed EnumType (missing variant name index)") } as usize;
+                        let arity = match self.read_byte() { Some(b) => b, None => return self.report("Malformed EnumType (missing variant arity)") } as usize;
+                        let vv = unsafe { *(*chunk_ptr).get_constant(vi) };
+                        if !is_string(&vv) { return self.report("EnumType variant name constant not string"); }
+                        variants.push(EnumVariant { name: unsafe { (*as_string_object(&vv)).content.clone() }, arity });
+                    }
+                    let enum_name = unsafe { (*as_string_object(&name_value)).content.clone() };
+                    let (enum_ptr, size) = self.object_manager.alloc_enum_type(enum_name);
+                    unsafe { (*enum_ptr).variants = variants; }
+                    self.push(make_object_value(enum_ptr as *mut Object));
+                    self.track_allocation(size);
+                }
+                Some(chunk::OpCode::MatchVariant) => {
+                    // Layout: MatchVariant <subject_slot> <variant_name_const_index> <binding_count> <next_arm_offset:u16>
+                    // stack: enum type (popped). On a match the payload is pushed for the arm's bindings.
+                    let slot = match self.read_byte() { Some(b) => b, None => return self.report("Malformed MatchVariant (missing subject slot)") } as usize;
+                    let variant_value = match self.read_constant() { Some(v) => v, None => return self.report("Malformed MatchVariant (missing variant name)") };
+                    let binding_count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed MatchVariant (missing binding count)") } as usize;
+                    let offset = match self.read_short() { Some(o) => o, None => return self.report("Malformed MatchVariant (missing offset)") };
+                    let pattern_type = self.pop();
+                    if !is_object(&pattern_type) || unsafe { (*as_mutable_object(&pattern_type)).obj_type } != ObjectType::ObjEnumType {
+                        return self.report("Match pattern must name an enum type.");
+                    }
+                    let enum_type = unsafe { (*as_mutable_object(&pattern_type)).as_enum_type() };
+                    let variant_name = unsafe { &(*as_string_object(&variant_value)).content };
+                    let tag = match enum_type.variant_index(variant_name) {
+                        Some(tag) => tag,
+                        None => return self.report(format!("Unknown variant '{}.{}' in match pattern.", enum_type.name, variant_name).as_str()),
+                    };
+                    if enum_type.variants[tag].arity != binding_count {
+                        return self.report(format!("Pattern {}.{} binds {} value(s) but the variant carries {}.", enum_type.name, variant_name, binding_count, enum_type.variants[tag].arity).as_str());
+                    }
+                    let subject = *self.current_frame().get_stack_value(slot);
+                    let matched = is_object(&subject) && unsafe { (*as_mutable_object(&subject)).obj_type } == ObjectType::ObjEnumValue && {
+                        let value = unsafe { (*as_mutable_object(&subject)).as_enum_value() };
+                        std::ptr::eq(value.enum_type, enum_type) && value.variant == tag
+                    };
+                    if matched {
+                        let value = unsafe { &*(as_mutable_object(&subject) as *const ObjectEnumValue) };
+                        for item in value.payload.iter() { self.push(*item); }
+                    } else {
+                        *self.current_frame().ip() += offset as usize;
+                    }
+                }
+                Some(chunk::OpCode::MatchFail) => {
+                    // Layout: MatchFail <subject_slot>; reached when no arm of a match without '_' applied.
+                    let slot = match self.read_byte() { Some(b) => b, None => return self.report("Malformed MatchFail (missing subject slot)") } as usize;
+                    let subject = *self.current_frame().get_stack_value(slot);
+                    let description = if is_object(&subject) && unsafe { (*as_mutable_object(&subject)).obj_type } == ObjectType::ObjEnumValue {
+                        let value = unsafe { (*as_mutable_object(&subject)).as_enum_value() };
+                        let enum_type = unsafe { &*value.enum_type };
+                        format!("{}.{}", enum_type.name, enum_type.variants[value.variant].name)
+                    } else {
+                        "non-enum value".to_string()
+                    };
+                    return self.report(format!("No match arm for {}.", description).as_str());
+                }
+                Some(chunk::OpCode::DestructureStruct) => {
+                    // Layout: DestructureStruct <type_name_const_index> <count> (<field_name_const_index>)*
+                    let type_name_value = match self.read_constant() { Some(v) => v, None => return self.report("Malformed DestructureStruct (missing type name)") };
+                    let count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed DestructureStruct (missing field count)") } as usize;
+                    let mut field_names: Vec<Value> = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        match self.read_constant() { Some(v) => field_names.push(v), None => return self.report("Malformed DestructureStruct (missing field name)") }
+                    }
+                    let type_name = unsafe { (*as_string_object(&type_name_value)).content.clone() };
+                    let receiver = self.pop();
+                    let values = match self.destructure_struct(receiver, &type_name, &field_names) {
+                        Ok(values) => values,
+                        Err(message) => return self.report(&message),
+                    };
+                    for value in values { self.push(value); }
+                }
+                Some(chunk::OpCode::DestructureList) => {
+                    // Layout: DestructureList <count> <has_rest>; the rest list collects elements past count.
+                    let count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed DestructureList (missing count)") } as usize;
+                    let has_rest = match self.read_byte() { Some(b) => b, None => return self.report("Malformed DestructureList (missing rest flag)") } != 0;
+                    let list = self.peek().unwrap();
+                    if !is_list(&list) { return self.report("Only lists can be destructured with a list pattern."); }
+                    let items = unsafe { (*as_list_object(&list)).items.clone() };
+                    if items.len() < count {
+                        return self.report(format!("List pattern needs {} element(s) but the list has {}.", count, items.len()).as_str());
+                    }
+                    self.pop();
+                    for item in items.iter().take(count) { self.push(*item); }
+                    if has_rest {
+                        let (rest_ptr, size) = self.object_manager.alloc_list(items[count..].to_vec());
+                        self.push(make_list_value(rest_ptr));
+                        self.track_allocation(size);
+                    }
+                }
+                Some(chunk::OpCode::DestructureTuple) => {
+                    // Layout: DestructureTuple <count>; the tuple must have exactly count elements.
+                    let count = match self.read_byte() { Some(b) => b, None => return self.report("Malformed DestructureTuple (missing count)") } as usize;
+                    let tuple = self.pop();
+                    if !is_tuple(&tuple) { return self.report("Only tuples can be destructured with a tuple pattern."); }
+                    let items = unsafe { (*as_tuple_object(&tuple)).items.clone() };
+                    if items.len() != count {
+                        return self.report(format!("Tuple pattern needs {} element(s) but the tuple has {}.", count, items.len()).as_str());
+                    }
+                    for item in items { self.push(item); }
+                }
+                Some(chunk::OpCode::ForIter) => {
+                    // Layout: ForIter <iterable_slot> <exit_offset:u16>; the cursor is the next local slot.
+                    let slot = match self.read_byte() { Some(b) => b, None => return self.report("Malformed ForIter (missing slot)") } as usize;
+                    let offset = match self.read_short() { Some(o) => o, None => return self.report("Malformed ForIter (missing exit offset)") };
+                    match self.iterator_next(slot)? {
+                        Some(value) => self.push(value),
+                        None => *self.current_frame().ip() += offset as usize,
+                    }
+                }
                 _ => return self.report("Unknown opcode"),
             }
         }
     }
 
-    fn get_upvalue(&self, index: usize) -> Value {
-        let up_ptr = self.open_upvalues[index];
-        // up_ptr must be valid and point to an ObjectUpvalue owned by ObjectManager;
-        // it may point to either a stack slot (location) or the upvalue.closed (after closing).
-        unsafe {
-            let loc = (*up_ptr).location;
-            *loc
-        }
+    // Build `EnumName.variant` from the top `payload_count` stack values (left in place for the caller to pop).
+    fn make_enum_value(&mut self, enum_type: *mut ObjectEnumType, variant_name: &str, payload_count: usize) -> Result<(Value, usize), String> {
+        let (tag, arity, enum_name) = unsafe {
+            let enum_type = &*enum_type;
+            match enum_type.variant_index(variant_name) {
+                Some(tag) => (tag, enum_type.variants[tag].arity, enum_type.name.clone()),
+                None => return Err(format!("Unknown variant '{}' on enum '{}'.", variant_name, enum_type.name)),
+            }
+        };
+        if arity != payload_count {
+            return Err(format!("Variant {}.{} expects {} payload value(s) but got {}.", enum_name, variant_name, arity, payload_count));
+        }
+        let first = self.stack_top_pos - payload_count;
+        let payload = self.stack[first..self.stack_top_pos].to_vec();
+        let (value_ptr, size) = self.object_manager.alloc_enum_value(enum_type, tag, payload);
+        Ok((make_object_value(value_ptr as *mut Object), size))
+    }
+
+    // Decode the operands shared by StructInstantiate and StructInstantiateStack.
+    fn read_struct_literal(&mut self, opcode: &str) -> Result<(*mut ObjectStructType, Vec<String>, bool), String> {
+        let type_name_value = self.read_constant().ok_or_else(|| format!("Malformed {} (missing type name index)", opcode))?;
+        let field_count = self.read_byte().ok_or_else(|| format!("Malformed {} (missing field count)", opcode))? as usize;
+        let has_base = self.read_byte().ok_or_else(|| format!("Malformed {} (missing base flag)", opcode))? != 0;
+        if !is_string(&type_name_value) { return Err(format!("{} type name constant not string", opcode)); }
+        let mut literal_field_names: Vec<String> = Vec::with_capacity(field_count);
+        for _ in 0..field_count {
+            let fv = self.read_constant().ok_or_else(|| format!("Malformed {} (missing field name const index)", opcode))?;
+            if !is_string(&fv) { return Err(format!("{} field name constant not string", opcode)); }
+            literal_field_names.push(unsafe { (*as_string_object(&fv)).content.clone() });
+        }
+        let struct_name = unsafe { &(*as_string_object(&type_name_value)).content };
+        // Lookup struct type in registry
+        let stype_val = self.struct_types.find(struct_name.as_str()).ok_or_else(|| format!("Unknown struct type '{}' in literal", struct_name))?;
+        if !is_object(&stype_val) { return Err("Struct type registry entry invalid".to_string()); }
+        if unsafe { (*as_mutable_object(&stype_val)).obj_type } != ObjectType::ObjStructType { return Err("Registry entry not struct type".to_string()); }
+        Ok((as_mutable_object(&stype_val) as *mut ObjectStructType, literal_field_names, has_base))
+    }
+
+    // Field values of a struct literal in slot order. The stack holds the listed values in source
+    // order, then the ..base value if present. Fields not listed are copied from the base, or else
+    // computed by the field's default closure. Everything stays on the stack until the end so the
+    // values are rooted while defaults run.
+    fn struct_literal_fields(&mut self, stype_ptr: *mut ObjectStructType, literal_field_names: &[String], has_base: bool) -> Result<Vec<Value>, String> {
+        let stype = unsafe { &*stype_ptr };
+        let first = self.stack_top_pos - literal_field_names.len() - has_base as usize;
+        let mut fields: Vec<Option<Value>> = vec![None; stype.field_names.len()];
+        for (i, name) in literal_field_names.iter().enumerate() {
+            match stype.field_index.find(name.as_str()) {
+                Some(slot) if is_number(&slot) => fields[as_number(&slot) as usize] = Some(self.stack[first + i]),
+                _ => return self.report(format!("Unknown field '{}' in {} literal.", name, stype.name).as_str()).map(|_| Vec::new()),
+            }
+        }
+        if has_base {
+            let base = self.stack[self.stack_top_pos - 1];
+            let base_fields = match self.struct_fields_of(base) {
+                Some((base_type, base_fields)) if std::ptr::eq(base_type, stype_ptr) => base_fields,
+                _ => return self.report(format!("'..' in a {} literal needs another {} to copy from.", stype.name, stype.name).as_str()).map(|_| Vec::new()),
+            };
+            for (field, base_field) in fields.iter_mut().zip(base_fields) {
+                if field.is_none() { *field = Some(base_field); }
+            }
+        }
+        let missing: Vec<usize> = fields.iter().enumerate().filter(|(_, field)| field.is_none()).map(|(slot, _)| slot).collect();
+        for slot in missing {
+            let default = stype.field_defaults[slot];
+            if is_nil(&default) {
+                return self.report(format!("Missing field '{}' in {} literal.", stype.field_names[slot], stype.name).as_str()).map(|_| Vec::new());
+            }
+            self.push(default);
+            let frame_count = self.frames.len();
+            if !self.call_value(default, 0) {
+                return self.report("Field default call failed.").map(|_| Vec::new());
+            }
+            self.run(frame_count)?;
+            // The result stays on the stack (rooted) until all defaults have run.
+            fields[slot] = self.peek();
+        }
+        self.stack_top_pos = first;
+        Ok(fields.into_iter().map(|field| field.unwrap()).collect())
+    }
+
+    // The type and a copy of the fields of a heap or (current frame) stack struct.
+    fn struct_fields_of(&self, value: Value) -> Option<(*mut ObjectStructType, Vec<Value>)> {
+        match value.value_type() {
+            ValueType::ValueObject if unsafe { (*as_mutable_object(&value)).obj_type } == ObjectType::ObjStructInstance => {
+                let inst = unsafe { (*as_mutable_object(&value)).as_struct_instance() };
+                Some((inst.struct_type, inst.fields.clone()))
+            }
+            ValueType::ValueStackStruct => {
+                let idx = as_stack_index(&value);
+                self.frame_stack_structs.last().and_then(|arena| arena.get(idx)).map(|s| (s.struct_type, s.fields.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    // The struct type of a heap instance or stack struct receiver; None for any other value.
+    fn receiver_struct_type(&self, receiver: &Value) -> Result<Option<*mut ObjectStructType>, String> {
+        match receiver.value_type() {
+            ValueType::ValueObject if unsafe { (*as_mutable_object(receiver)).obj_type } == ObjectType::ObjStructInstance => {
+                Ok(Some(unsafe { (*(as_mutable_object(receiver) as *mut ObjectStructInstance)).struct_type }))
+            }
+            ValueType::ValueStackStruct => {
+                let arena = self.frame_stack_structs.last().ok_or("Missing frame arena")?;
+                let s = arena.get(as_stack_index(receiver)).ok_or("Invalid stack struct index")?;
+                Ok(Some(s.struct_type))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Slot of the named field, taken from the instruction's inline cache when it last saw the same struct
+    // type. A type's field layout never changes, so field entries don't need the cache epoch.
+    fn field_slot(&self, chunk: *mut Box<Chunk>, cache_index: usize, struct_type: *mut ObjectStructType, field_name: &str) -> Result<Option<usize>, String> {
+        let cache = unsafe { (*chunk).inline_cache(cache_index) };
+        if std::ptr::eq(cache.struct_type, struct_type) {
+            return Ok(Some(cache.slot));
+        }
+        match unsafe { (*struct_type).field_index.find(field_name) } {
+            Some(idx_v) => {
+                if !is_number(&idx_v) { return Err("Corrupt field index table".to_string()); }
+                let slot = as_number(&idx_v) as usize;
+                unsafe { *(*chunk).inline_cache_mut(cache_index) = InlineCache { struct_type, slot, ..InlineCache::default() }; }
+                Ok(Some(slot))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // A field of a receiver that receiver_struct_type accepted.
+    fn struct_field_mut(&mut self, receiver: &Value, slot: usize) -> &mut Value {
+        if is_stack_struct(receiver) {
+            &mut self.frame_stack_structs.last_mut().unwrap()[as_stack_index(receiver)].fields[slot]
+        } else {
+            unsafe { &mut (&mut (*(as_mutable_object(receiver) as *mut ObjectStructInstance)).fields)[slot] }
+        }
+    }
+
+    // Private fields are only usable, and read-only fields only writable, by code in the type's impl methods.
+    fn check_field_access(&self, struct_type: *const ObjectStructType, slot: usize, write: bool) -> Result<(), String> {
+        let struct_type = unsafe { &*struct_type };
+        let access = struct_type.field_access.get(slot).copied().unwrap_or(FieldAccess::Public);
+        if access == FieldAccess::Public || (access == FieldAccess::ReadOnly && !write) {
+            return Ok(());
+        }
+        if self.current_method_type() == Some(struct_type.name.as_str()) {
+            return Ok(());
+        }
+        let field = &struct_type.field_names[slot];
+        Err(if access == FieldAccess::Private {
+            format!("Field '{}' of {} is private.", field, struct_type.name)
+        } else {
+            format!("Field '{}' of {} is read-only.", field, struct_type.name)
+        })
+    }
+
+    // The struct type whose impl the running function belongs to, if any.
+    fn current_method_type(&self) -> Option<&str> {
+        let callable = self.frames.last()?.callable_object();
+        let function = unsafe {
+            match (*callable).obj_type {
+                ObjectType::ObjClosure => &*(*(callable as *const ObjectClosure)).function,
+                ObjectType::ObjFunction => &*(callable as *const ObjectFunction),
+                _ => return None,
+            }
+        };
+        function.method_of.as_deref()
+    }
+
+    // Type name used for trait impls: the struct or enum name, or a builtin name like "number".
+    fn type_name_of(&self, value: Value) -> String {
+        if let Some((struct_type, _)) = self.struct_fields_of(value) {
+            return unsafe { (*struct_type).name.clone() };
+        }
+        match value.value_type() {
+            ValueType::ValueNil => "nil".to_string(),
+            ValueType::ValueBool => "bool".to_string(),
+            ValueType::ValueNumber => "number".to_string(),
+            ValueType::ValueObject => unsafe {
+                match (*as_mutable_object(&value)).obj_type {
+                    ObjectType::ObjString => "string".to_string(),
+                    ObjectType::ObjList => "list".to_string(),
+                    ObjectType::ObjMap => "map".to_string(),
+                    ObjectType::ObjTuple => "tuple".to_string(),
+                    ObjectType::ObjWeak => "weak".to_string(),
+                    ObjectType::ObjEnumValue => (*(*(as_mutable_object(&value) as *const ObjectEnumValue)).enum_type).name.clone(),
+                    _ => "function".to_string(),
+                }
+            },
+            _ => "unknown".to_string(),
+        }
+    }
+
+    // Backs both `x is Trait` and implements(x, "Trait").
+    pub(crate) fn implements_trait(&self, value: Value, trait_name: &str) -> Result<bool, String> {
+        if self.trait_registry.find(trait_name).is_none() {
+            return Err(format!("Unknown trait '{}'.", trait_name));
+        }
+        let type_name = self.type_name_of(value);
+        Ok(self.type_traits.get(type_name.as_str()).is_some_and(|traits| traits.contains_key(trait_name)))
+    }
+
+    // The method a `Trait.method(x, ...)` call resolves to; x sits just above the trait on the stack.
+    fn trait_method(&self, trait_name: &str, method_name: &str, arg_count: u8) -> Result<Value, String> {
+        if arg_count == 0 {
+            return Err(format!("{}.{}() needs the receiver as its first argument.", trait_name, method_name));
+        }
+        let receiver = self.peek_steps(arg_count as usize - 1).unwrap();
+        let type_name = self.type_name_of(receiver);
+        let traits = match self.type_traits.get(type_name.as_str()) {
+            Some(traits) if traits.contains_key(trait_name) => traits,
+            _ => return Err(format!("Type '{}' does not implement trait '{}'.", type_name, trait_name)),
+        };
+        // The trait's own method first, then the nearest supertrait that provides it.
+        std::iter::once(trait_name.to_string()).chain(self.trait_ancestors(trait_name))
+            .find_map(|name| traits.get(name.as_str())?.find(method_name))
+            .ok_or_else(|| format!("Trait '{}' has no method '{}' for type '{}'.", trait_name, method_name, type_name))
+    }
+
+    // Calls a method with the receiver and its args already on the stack.
+    // Stack layout before: [..., receiver, arg1, ..., argN]; the callee is inserted below the receiver.
+    fn call_method(&mut self, method: Value, arg_count: u8, names: &[String]) -> bool {
+        let insert_pos = self.stack_top_pos - arg_count as usize - 1;
+        let mut i = self.stack_top_pos;
+        self.stack_top_pos += 1;
+        while i > insert_pos { self.stack[i] = self.stack[i - 1]; i -= 1; }
+        self.stack[insert_pos] = method;
+        // include receiver as first arg
+        self.call_value_named(method, arg_count + 1, names)
+    }
+
+    // Replaces the receiver on top of the stack with `receiver.method_name` as a bound method value.
+    fn bind_method(&mut self, method_name: &str) -> Result<(), String> {
+        let receiver = self.peek().unwrap();
+        let type_name = self.type_name_of(receiver);
+        let method = match self.type_methods.get(type_name.as_str()).and_then(|table| table.find(method_name)) {
+            Some(method) => method,
+            None if self.method_providers(&type_name, method_name).len() > 1 => return Err(self.missing_method_message(&type_name, method_name)),
+            None if self.struct_fields_of(receiver).is_some() => return Err(format!("Unknown field or method '{}' on {}.", method_name, type_name)),
+            None => return Err("Only instances have fields".to_string()),
+        };
+        let (bound_ptr, size) = self.object_manager.alloc_bound_method(receiver, method);
+        self.pop();
+        self.push(make_bound_method_value(bound_ptr));
+        self.track_allocation(size);
+        Ok(())
+    }
+
+    // The trait's supertraits, transitively, nearest first.
+    fn trait_ancestors(&self, trait_name: &str) -> Vec<String> {
+        let mut ancestors: Vec<String> = Vec::new();
+        let mut next = 0;
+        let mut current = trait_name.to_string();
+        loop {
+            if let Some(value) = self.trait_registry.find(current.as_str()) {
+                let trait_object = unsafe { (*as_mutable_object(&value)).as_trait() };
+                for parent in trait_object.supertraits.iter() {
+                    if *parent != trait_name && !ancestors.contains(parent) {
+                        ancestors.push(parent.clone());
+                    }
+                }
+            }
+            if next == ancestors.len() { return ancestors; }
+            current = ancestors[next].clone();
+            next += 1;
+        }
+    }
+
+    // Traits of the type that provide the method, leaving out those overridden by a subtrait that also provides it.
+    fn method_providers(&self, type_name: &str, method_name: &str) -> Vec<String> {
+        let traits = match self.type_traits.get(type_name) {
+            Some(traits) => traits,
+            None => return Vec::new(),
+        };
+        let candidates: Vec<&String> = traits.iter().filter(|(_, tbl)| tbl.find(method_name).is_some()).map(|(name, _)| name).collect();
+        let mut providers: Vec<String> = candidates.iter()
+            .filter(|name| !candidates.iter().any(|other| other != *name && self.trait_ancestors(other).contains(*name)))
+            .map(|name| (*name).clone())
+            .collect();
+        providers.sort();
+        providers
+    }
+
+    fn missing_method_message(&self, type_name: &str, method_name: &str) -> String {
+        let providers = self.method_providers(type_name, method_name);
+        if providers.len() > 1 {
+            format!("Method '{}' of type '{}' is ambiguous between traits {}; call it as {}.{}(value).", method_name, type_name, providers.join(", "), providers[0], method_name)
+        } else {
+            format!("Unknown method '{}' for type '{}'", method_name, type_name)
+        }
+    }
+
+    // Field values of a struct (heap or stack) for a `var TypeName { ... } = value;` pattern.
+    fn destructure_struct(&self, receiver: Value, type_name: &str, field_names: &[Value]) -> Result<Vec<Value>, String> {
+        let (struct_type, fields) = match receiver.value_type() {
+            ValueType::ValueObject if unsafe { (*as_mutable_object(&receiver)).obj_type } == ObjectType::ObjStructInstance => {
+                let inst = unsafe { &*(as_mutable_object(&receiver) as *const ObjectStructInstance) };
+                (inst.struct_type, &inst.fields)
+            }
+            ValueType::ValueStackStruct => {
+                let idx = as_stack_index(&receiver);
+                match self.frame_stack_structs.last().and_then(|arena| arena.get(idx)) {
+                    Some(s) => (s.struct_type, &s.fields),
+                    None => return Err("Invalid stack struct index".to_string()),
+                }
+            }
+            _ => return Err(format!("Can't destructure a non-struct value with a {} pattern.", type_name)),
+        };
+        let struct_type = unsafe { &*struct_type };
+        if struct_type.name != type_name {
+            return Err(format!("Can't destructure a {} with a {} pattern.", struct_type.name, type_name));
+        }
+        let mut values = Vec::with_capacity(field_names.len());
+        for name_value in field_names {
+            let name = unsafe { &(*as_string_object(name_value)).content };
+            match struct_type.field_index.find(name.as_str()) {
+                Some(slot) if is_number(&slot) => {
+                    let slot = as_number(&slot) as usize;
+                    self.check_field_access(struct_type, slot, false)?;
+                    values.push(fields[slot]);
+                }
+                _ => return Err(format!("Struct {} has no field '{}' to destructure.", type_name, name)),
+            }
+        }
+        Ok(values)
+    }
+
+    fn list_index(index: Value, len: usize) -> Result<usize, String> {
+        if !is_number(&index) { return Err("Index must be a number.".to_string()); }
+        let number = as_number(&index);
+        if number.fract() != 0.0 || number < 0.0 || number >= len as f64 {
+            return Err(format!("Index {} out of bounds for length {}.", number, len));
+        }
+        Ok(number as usize)
+    }
+
+    // Resolve a range index into [start, end) element bounds for a sequence of length `len`.
+    fn slice_bounds(range: Value, len: usize) -> Result<(usize, usize), String> {
+        let range = unsafe { &*as_range_object(&range) };
+        let end = if range.inclusive { range.end + 1.0 } else { range.end };
+        if range.start.fract() != 0.0 || end.fract() != 0.0 {
+            return Err("Slice bounds must be whole numbers.".to_string());
+        }
+        if range.start < 0.0 || end < range.start || end > len as f64 {
+            let operator = if range.inclusive { "..=" } else { ".." };
+            return Err(format!("Slice {}{}{} out of bounds for length {}.", range.start, operator, range.end, len));
+        }
+        Ok((range.start as usize, end as usize))
+    }
+
+    fn index_get(&mut self, receiver: Value, index: Value) -> Result<Value, String> {
+        if is_list(&receiver) && is_range(&index) {
+            let items = unsafe { &(*as_list_object(&receiver)).items };
+            let (start, end) = Self::slice_bounds(index, items.len())?;
+            let (list_ptr, _size) = self.object_manager.alloc_list(items[start..end].to_vec());
+            Ok(make_list_value(list_ptr))
+        } else if is_string(&receiver) && is_range(&index) {
+            let content = unsafe { &(*as_string_object(&receiver)).content };
+            let (start, end) = Self::slice_bounds(index, content.chars().count())?;
+            let slice: String = content.chars().skip(start).take(end - start).collect();
+            Ok(make_string_value(&mut self.object_manager, &mut self.intern_strings, &slice))
+        } else if is_list(&receiver) {
+            let list = unsafe { &*as_list_object(&receiver) };
+            let i = Self::list_index(index, list.items.len())?;
+            Ok(list.items[i])
+        } else if is_map(&receiver) {
+            if !is_string(&index) { return Err("Map keys must be strings.".to_string()); }
+            let map = unsafe { &*as_map_object(&receiver) };
+            let name = unsafe { &(*as_string_object(&index)).content };
+            Ok(map.get(name).unwrap_or_else(make_nil_value))
+        } else if is_string(&receiver) {
+            let content = unsafe { &(*as_string_object(&receiver)).content };
+            let i = Self::list_index(index, content.chars().count())?;
+            let ch = content.chars().nth(i).unwrap().to_string();
+            Ok(make_string_value(&mut self.object_manager, &mut self.intern_strings, &ch))
+        } else {
+            Err("Only lists, maps and strings can be indexed.".to_string())
+        }
+    }
+
+    fn index_set(&mut self, receiver: Value, index: Value, value: Value) -> Result<(), String> {
+        if is_list(&receiver) {
+            let list = unsafe { &mut *as_list_object(&receiver) };
+            let i = Self::list_index(index, list.items.len())?;
+            list.items[i] = value;
+            Ok(())
+        } else if is_map(&receiver) {
+            if !is_string(&index) { return Err("Map keys must be strings.".to_string()); }
+            let map = unsafe { &mut *as_map_object(&receiver) };
+            let name = unsafe { (*as_string_object(&index)).content.as_str() };
+            map.insert(index, name, value);
+            Ok(())
+        } else {
+            Err("Only lists and maps support index assignment.".to_string())
+        }
+    }
+
+    // Advance the for-in loop whose iterable lives in local `slot` (cursor in `slot + 1`).
+    // Returns None once the iterable is exhausted.
+    fn iterator_next(&mut self, slot: usize) -> Result<Option<Value>, String> {
+        let iterable = *self.current_frame().get_stack_value(slot);
+        if !is_object(&iterable) {
+            return self.report("Can only iterate over ranges, lists, maps, strings and iterators.").map(|_| None);
+        }
+        let cursor = as_number(self.current_frame().get_stack_value(slot + 1)) as usize;
+        let next = match unsafe { (*as_mutable_object(&iterable)).obj_type } {
+            ObjectType::ObjList => unsafe { &*as_list_object(&iterable) }.items.get(cursor).copied(),
+            ObjectType::ObjMap => unsafe { &*as_map_object(&iterable) }.keys.get(cursor).copied(),
+            ObjectType::ObjString => {
+                // The cursor is a byte offset so each step is O(1).
+                let content = unsafe { &(*as_string_object(&iterable)).content };
+                match content[cursor..].chars().next() {
+                    Some(ch) => {
+                        self.current_frame().set_stack_value(slot + 1, make_numer_value((cursor + ch.len_utf8()) as f64));
+                        let ch = ch.to_string();
+                        return Ok(Some(make_string_value(&mut self.object_manager, &mut self.intern_strings, &ch)));
+                    }
+                    None => None,
+                }
+            }
+            ObjectType::ObjRange => {
+                let range = unsafe { &*as_range_object(&iterable) };
+                let value = range.start + cursor as f64;
+                if range.contains(value) { Some(make_numer_value(value)) } else { None }
+            }
+            ObjectType::ObjStructInstance => return self.call_iterator_next(iterable),
+            _ => return self.report("Can only iterate over ranges, lists, maps, strings and iterators.").map(|_| None),
+        };
+        if next.is_some() {
+            self.current_frame().set_stack_value(slot + 1, make_numer_value((cursor + 1) as f64));
+        }
+        Ok(next)
+    }
+
+    // User iterators implement `next()`; returning nil ends the loop.
+    fn call_iterator_next(&mut self, receiver: Value) -> Result<Option<Value>, String> {
+        let instance = unsafe { (*as_mutable_object(&receiver)).as_struct_instance() };
+        let type_name = unsafe { (*instance.struct_type).name.clone() };
+        let method = match self.type_methods.get(type_name.as_str()).and_then(|table| table.find("next")) {
+            Some(method) => method,
+            None => return self.report(format!("Type '{}' is not iterable; implement 'next()'.", type_name).as_str()).map(|_| None),
+        };
+        self.push(method);
+        self.push(receiver);
+        let frame_count = self.frames.len();
+        if !self.call_value(method, 1) {
+            return self.report("Iterator next() call failed.").map(|_| None);
+        }
+        self.run(frame_count)?;
+        let result = self.pop();
+        Ok(if is_nil(&result) { None } else { Some(result) })
+    }
+
+    fn get_upvalue(&self, index: usize) -> Value {
+        let up_ptr = self.open_upvalues[index];
+        // up_ptr must be valid and point to an ObjectUpvalue owned by ObjectManager;
+        // it may point to either a stack slot (location) or the upvalue.closed (after closing).
+        unsafe {
+            let loc = (*up_ptr).location;
+            *loc
+        }
     }
     fn set_upvalue(&mut self, index: usize, value: Value) {
         let up_ptr = self.open_upvalues[index];
@@ -1060,22 +2125,11 @@ impl VM {
     }
 
     fn read_constant(&mut self) -> Option<Value> {
-        let instruction = match self.read_byte() {
-            Some(byte) => byte,
-            None => return None,
-        };
+        let instruction = self.read_byte()?;
         let chunk = unsafe { self.current_chunk() };
         Some(*chunk.get_constant(instruction as usize))
     }
 
-    fn read_string(&mut self) -> Option<*const ObjectString> {
-        if let Some(constant) = self.read_constant() {
-            Some(as_string_object(&constant))
-        } else {
-            None
-        }
-    }
-
     // fn BinaryOperation(&mut self, op_code: chunk::OpCode) {
     //     let a = self.pop();
     //     let b = self.pop();
@@ -1182,9 +2236,8 @@ impl VM {
            let up_ptr = self.open_upvalues[i];
            let loc = unsafe { (*up_ptr).location };
            if loc >= last_ptr {
-               // Copy value then possibly promote
-               let mut v = unsafe { *loc };
-               v = self.promote_stack_struct_value_reason(v, Some("closure capture"), 0);
+               let v = unsafe { *loc };
+               self.gc.write_barrier(Some(up_ptr as *mut Object), &v);
                unsafe {
                    (*up_ptr).closed = v;
                    (*up_ptr).location = &mut (*up_ptr).closed as *mut Value;
@@ -1193,33 +2246,22 @@ impl VM {
        }
     }
 
-    // Promote a ValueStackStruct to a heap ObjectStructInstance (deeply promoting nested stack structs)
-    fn promote_stack_struct_value_reason(&mut self, value: Value, reason: Option<&str>, depth: usize) -> Value {
-        if value.value_type != crate::value::ValueType::ValueStackStruct { return value; }
-        if depth == 0 {
-            if let Some(r) = reason { self.warn(&format!("Implicit promotion of stack struct to heap ({})", r)); }
-        }
-        let idx = unsafe { value.value_as.stack_index };
-        // Extract metadata and a raw pointer to fields without holding an immutable borrow across allocations.
-        let (struct_type_ptr, field_len, fields_ptr) = {
-            match self.frame_stack_structs.last() {
-                Some(arena) => {
-                    if idx >= arena.len() { return value; }
-                    let s = &arena[idx];
-                    (s.struct_type, s.fields.len(), s.fields.as_ptr())
-                }
-                None => return value,
-            }
-        };
-        // Allocate heap instance
-        let (inst_ptr, size) = self.object_manager.alloc_struct_instance(struct_type_ptr, field_len);
-        // Copy and promote each field without cloning the entire vector
-        for i in 0..field_len {
-            let fv = unsafe { *fields_ptr.add(i) };
-            unsafe { (*inst_ptr).fields[i] = self.promote_stack_struct_value_reason(fv, None, depth + 1); }
+    // A map with the given string keys, for natives that return records. Nothing here can collect; the
+    // bytes are accounted with the pending allocations once the native returns.
+    pub(crate) fn new_map(&mut self, entries: &[(&str, Value)]) -> Value {
+        let (map_ptr, _size) = self.object_manager.alloc_map();
+        for (name, value) in entries {
+            let key = make_string_value(&mut self.object_manager, &mut self.intern_strings, name);
+            unsafe { (*map_ptr).insert(key, name, *value); }
         }
-        self.track_allocation(size);
-        Value { value_type: crate::value::ValueType::ValueObject, value_as: crate::value::ValueUnion { object: inst_ptr as *mut crate::objects::object::Object } }
+        make_map_value(map_ptr)
+    }
+
+    // A weak reference to `target`. Like new_map, its bytes are accounted once the native returns.
+    pub(crate) fn new_weak(&mut self, target: Value) -> Value {
+        let (weak_ptr, _size) = self.object_manager.alloc_weak(target);
+        self.gc.register_weak(weak_ptr as *mut Object);
+        make_weak_value(weak_ptr)
     }
 
     fn report(&mut self, message: &str) -> Result<InterpretResult, String> {
@@ -1275,8 +2317,34 @@ mod debug_feature {
 #[cfg(test)]
 mod tests {
     use crate::vm::InterpretResult;
+    use crate::value::{as_number, as_string_object, is_number, is_string};
 
     use super::VM;
+    use crate::gc::{GcConfig, GcMode};
+
+    const GC_MODES: [GcMode; 3] = [GcMode::StopTheWorld, GcMode::Incremental, GcMode::Generational];
+
+    // A VM that collects early and often in `mode`; the small nursery makes generational runs do minor
+    // collections between the major ones.
+    fn gc_stress_vm(mode: GcMode, threshold: usize) -> VM {
+        let mut vm = VM::new();
+        vm.set_gc_mode(mode);
+        vm.set_gc_threshold(threshold);
+        vm.set_nursery_limit(1024);
+        vm
+    }
+
+    fn global_number(vm: &VM, name: &str) -> f64 {
+        let value = vm.globals.find(name).unwrap_or_else(|| panic!("global '{}' not defined", name));
+        assert!(is_number(&value), "global '{}' is not a number", name);
+        as_number(&value)
+    }
+
+    fn global_string(vm: &VM, name: &str) -> String {
+        let value = vm.globals.find(name).unwrap_or_else(|| panic!("global '{}' not defined", name));
+        assert!(is_string(&value), "global '{}' is not a string", name);
+        unsafe { (*as_string_object(&value)).content.clone() }
+    }
 
     #[test]
     fn test_comparison_expression() {
@@ -1310,6 +2378,28 @@ mod tests {
     assert!(vm.interpret(r#"var beverage = "coffee";"#) == InterpretResult::InterpretOk);
     }
 
+    #[test]
+    fn test_global_slots_late_binding_and_redefinition() {
+        // `later` gets its slot when `read` is compiled, but stays undefined until its var runs.
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn read() { return later; } var early = read();"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn read() { return later; } var later = 4; var seen = read();"), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "seen"), 4.0);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn write() { later = 1; } write(); var later = 0;"), InterpretResult::InterpretRuntimeError);
+        assert!(vm.globals.find("later").is_none());
+
+        // Later interpret calls, as in a REPL session, reuse the slots of earlier ones.
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var count = 1; fn bump() { count = count + 1; }"), InterpretResult::InterpretOk);
+        assert_eq!(vm.interpret("bump(); var count = count * 10; bump();"), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "count"), 21.0);
+        assert_eq!(vm.interpret("print missing;"), InterpretResult::InterpretRuntimeError);
+        assert_eq!(vm.interpret("var missing = clock() > 0;"), InterpretResult::InterpretOk);
+        assert!(crate::value::as_bool(&vm.globals.find("missing").unwrap()));
+    }
+
     #[test]
     fn test_print_local_var() {
         let mut vm = VM::new();
@@ -1490,41 +2580,249 @@ mod tests {
 
     #[test]
     fn test_gc_pressure_many_strings() {
+        for mode in GC_MODES {
+            // Force an early GC so we can observe at least one cycle during this test without huge allocations.
+            let mut vm = gc_stress_vm(mode, 0);
+            // Builds increasingly large string causing many intermediate unreachable strings.
+            let script = r#"
+                var s = "";
+                var i = 0;
+                while (i < 1500) {
+                    s = s + "abcdefgh";
+                    i = i + 1;
+                }"#;
+            let result = vm.interpret(script);
+            assert_eq!(result, InterpretResult::InterpretOk);
+            // Ensure at least one GC cycle ran under allocation pressure.
+            assert!(vm.gc.stats().cycles > 0, "Expected GC cycles > 0, got {}", vm.gc.stats().cycles);
+        }
+    }
+
+    #[test]
+    fn test_gc_concatenation_memory_stays_bounded() {
+        for mode in GC_MODES {
+            let mut vm = gc_stress_vm(mode, 64 * 1024);
+            // Thousands of distinct, short-lived strings; only `prefix` and `s` stay reachable.
+            let script = r#"
+                var prefix = "";
+                var s = "";
+                var i = 0;
+                var j = 0;
+                while (i < 20000) {
+                    s = s + "x";
+                    j = j + 1;
+                    if (j == 50) {
+                        prefix = prefix + "y";
+                        s = prefix;
+                        j = 0;
+                    }
+                    i = i + 1;
+                }"#;
+            assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+            assert!(vm.gc.stats().total_freed_bytes > 4 * 1024 * 1024, "{:?}: freed {}", mode, vm.gc.stats().total_freed_bytes);
+            assert!(vm.bytes_allocated < 512 * 1024, "{:?}: {} bytes still allocated", mode, vm.bytes_allocated);
+        }
+    }
+
+    #[test]
+    fn test_gc_script_controls() {
         let mut vm = VM::new();
-        // Force an early GC so we can observe at least one cycle during this test without huge allocations.
-        vm.set_gc_threshold(0);
-        // Builds increasingly large string causing many intermediate unreachable strings.
+        let script = r#"
+            struct Node { next }
+            var keep = nil;
+            var i = 0;
+            while (i < 100) { keep = new Node { next = keep }; i = i + 1; }
+            gc.collect();
+            var first = gc.stats();
+            gc.set_growth_factor(4);
+            keep = nil;
+            gc.collect();
+            var second = gc.stats();
+            var cycles = second["cycles"];
+            var freed = second["freed_bytes"] > first["freed_bytes"] ? 1 : 0;
+            var shrunk = second["live_bytes"] < first["live_bytes"] ? 1 : 0;
+            var trigger = second["next_trigger_bytes"] == second["live_bytes"] * 4 ? 1 : 0;
+            var collect = gc.collect;
+            collect();
+            var bound_cycles = gc.stats()["cycles"];
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "cycles"), 2.0);
+        assert_eq!(global_number(&vm, "freed"), 1.0);
+        assert_eq!(global_number(&vm, "shrunk"), 1.0);
+        assert_eq!(global_number(&vm, "bound_cycles"), 3.0);
+        assert_eq!(global_number(&vm, "trigger"), 1.0);
+    }
+
+    #[test]
+    fn test_gc_control_errors() {
+        for body in [
+            "gc.set_growth_factor(1);",
+            "gc.set_growth_factor(0.5);",
+            "gc.set_growth_factor(\"fast\");",
+            "gc.collect(1);",
+        ] {
+            let mut vm = VM::new();
+            assert_eq!(vm.interpret(body), InterpretResult::InterpretRuntimeError, "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_gc_embedding_config() {
+        let mut vm = VM::with_gc_config(GcConfig { initial_threshold: 16 * 1024, growth_factor: 3.0, ..GcConfig::default() });
         let script = r#"
             var s = "";
             var i = 0;
-            while (i < 1500) {
-                s = s + "abcdefgh";
-                i = i + 1;
-            }"#;
-        let result = vm.interpret(script);
-        assert_eq!(result, InterpretResult::InterpretOk);
-        // Ensure at least one GC cycle ran under allocation pressure.
-        assert!(vm.gc.stats().cycles > 0, "Expected GC cycles > 0, got {}", vm.gc.stats().cycles);
+            while (i < 2000) { s = s + "ab"; i = i + 1; }
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        let stats = vm.gc_stats();
+        assert!(stats.cycles > 0);
+        assert_eq!(stats.next_trigger_bytes, stats.last_after_bytes * 3);
+        assert_eq!(stats.live_bytes, vm.bytes_allocated);
+        assert!(vm.set_gc_growth_factor(1.5).is_ok());
+        assert!(vm.set_gc_growth_factor(f64::NAN).is_err());
+        let before = vm.gc_stats().cycles;
+        vm.collect_garbage();
+        let stats = vm.gc_stats();
+        assert_eq!(stats.cycles, before + 1);
+        assert_eq!(stats.next_trigger_bytes, (stats.last_after_bytes as f64 * 1.5) as usize);
     }
 
     #[test]
-    fn test_gc_pressure_functions_and_closures_original() {
-        // Original failing pattern: function defined inside loop then immediately called.
+    fn test_heap_dump() {
+        let path = std::env::temp_dir().join(format!("latte_heap_dump_{}.json", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
         let mut vm = VM::new();
-        vm.set_gc_threshold(0);
-        // Restored higher iteration count to increase allocation pressure & exercise multiple GC cycles.
-        let script = r#"
+        let script = format!(r#"
+            struct Node {{ next, name }}
+            var head = nil;
             var i = 0;
-            while (i < 300) {
-                fn f() {
-                    return i;
+            while (i < 10) {{ head = new Node {{ next = head, name = "node" }}; i = i + 1; }}
+            gc.collect();
+            heap_dump("{}");
+        "#, path);
+        assert_eq!(vm.interpret(&script), InterpretResult::InterpretOk);
+        let dump = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(dump.contains(r#""by_struct_type": {"Node": {"count": 10, "#), "{}", dump);
+        assert!(dump.contains(r#""by_string_length": {"0-15": {"count": "#));
+        // The global `head` is a root pointing at a Node instance that references the next one.
+        let root = dump.lines().find(|line| line.contains(r#""kind": "global", "label": "head""#)).unwrap();
+        let id = root.rsplit("\"id\": ").next().unwrap().trim_end_matches(['}', ',']);
+        let object = dump.lines().find(|line| line.trim_start().starts_with(&format!("{{\"id\": {},", id))).unwrap();
+        assert!(object.contains(r#""type": "ObjStructInstance""#) && object.contains(r#""label": "Node""#), "{}", object);
+        assert!(!object.contains(r#""refs": []"#));
+
+        // The embedding call writes the same kind of dump.
+        vm.heap_dump(&path).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains(r#""kind": "global", "label": "heap_dump""#));
+        let _ = std::fs::remove_file(&path);
+        assert!(vm.heap_dump("/nonexistent-dir/heap.json").is_err());
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("heap_dump(1);"), InterpretResult::InterpretRuntimeError);
+    }
+
+    #[test]
+    fn test_weak_references() {
+        for mode in GC_MODES {
+            let mut vm = gc_stress_vm(mode, 16 * 1024);
+            let script = r#"
+                struct Entry { id }
+                struct Slot { target, next }
+                var keep = new Entry { id = 7 };
+                var slots = new Slot { target = weak(keep), next = nil };
+                var i = 0;
+                while (i < 300) { slots = new Slot { target = weak(new Entry { id = i }), next = slots }; i = i + 1; }
+                gc.collect();
+                var cleared = 0;
+                var kept_id = -1;
+                var s = slots;
+                while (s != nil) {
+                    var target = s.target.get();
+                    if (target == nil) { cleared = cleared + 1; } else { kept_id = target.id; }
+                    s = s.next;
                 }
-                f();
-                i = i + 1;
-            }"#;
-        let result = vm.interpret(script);
-        assert_eq!(result, InterpretResult::InterpretOk);
-        assert!(vm.gc.stats().cycles > 0, "Expected GC cycles > 0, got {}", vm.gc.stats().cycles);
+                keep = nil;
+                gc.collect();
+                var s2 = slots;
+                while (s2.next != nil) { s2 = s2.next; }
+                var last = s2.target.get() == nil ? 1 : 0;
+            "#;
+            assert_eq!(vm.interpret(script), InterpretResult::InterpretOk, "{:?}", mode);
+            assert_eq!(global_number(&vm, "cleared"), 300.0, "{:?}", mode);
+            assert_eq!(global_number(&vm, "kept_id"), 7.0, "{:?}", mode);
+            assert_eq!(global_number(&vm, "last"), 1.0, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_drop_finalizers() {
+        for mode in GC_MODES {
+            let mut vm = gc_stress_vm(mode, 16 * 1024);
+            let script = r#"
+                struct Res { id }
+                var drops = 0;
+                var saved = nil;
+                impl Drop for Res {
+                    fn drop() {
+                        drops = drops + 1;
+                        if (self.id == 0) { saved = self; }
+                    }
+                }
+                var i = 0;
+                while (i < 500) { var r = new Res { id = i }; i = i + 1; }
+                gc.collect();
+                var first = drops;
+                var saved_id = saved.id;
+                saved = nil;
+                gc.collect();
+                gc.collect();
+                var second = drops;
+                var is_drop = new Res { id = 1 } is Drop ? 1 : 0;
+            "#;
+            assert_eq!(vm.interpret(script), InterpretResult::InterpretOk, "{:?}", mode);
+            assert_eq!(global_number(&vm, "first"), 500.0, "{:?}", mode);
+            // The resurrected instance is intact and isn't finalized a second time.
+            assert_eq!(global_number(&vm, "saved_id"), 0.0, "{:?}", mode);
+            assert_eq!(global_number(&vm, "second"), 500.0, "{:?}", mode);
+            assert_eq!(global_number(&vm, "is_drop"), 1.0, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_weak_and_drop_errors() {
+        for body in [
+            "weak(1);",
+            "weak(gc).get(1);",
+            "struct R { x } impl Drop for R { fn drop(reason) {} }",
+            "struct R { x } impl Drop for R { fn drop() { return 1 + nil; } } new R { x = 1 }; gc.collect();",
+            "struct R { x } impl Drop for R { fn drop() { return 1 + nil; } } new R { x = 1 }; gc.collect(); var after = 1;",
+        ] {
+            let mut vm = VM::new();
+            assert_eq!(vm.interpret(body), InterpretResult::InterpretRuntimeError, "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_gc_pressure_functions_and_closures_original() {
+        for mode in GC_MODES {
+            // Original failing pattern: function defined inside loop then immediately called.
+            let mut vm = gc_stress_vm(mode, 0);
+            // Restored higher iteration count to increase allocation pressure & exercise multiple GC cycles.
+            let script = r#"
+                var i = 0;
+                while (i < 300) {
+                    fn f() {
+                        return i;
+                    }
+                    f();
+                    i = i + 1;
+                }"#;
+            let result = vm.interpret(script);
+            assert_eq!(result, InterpretResult::InterpretOk);
+            assert!(vm.gc.stats().cycles > 0, "Expected GC cycles > 0, got {}", vm.gc.stats().cycles);
+        }
     }
 
     #[test]
@@ -1651,13 +2949,15 @@ mod tests {
     }
 
     #[test]
-    fn test_return_stack_struct_compile_error() {
+    fn test_return_struct_literal_allocates_on_heap() {
         let mut vm = VM::new();
         let script = r#"
             struct Point { x, y }
             fn make() { return Point { x = 1, y = 2 }; }
-        "#; // returning stack struct literal should be compile error
-        assert_eq!(vm.interpret(script), InterpretResult::InterpretCompileError);
+            var y = make().y;
+        "#; // a returned literal escapes, so it is compiled to a heap allocation
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "y"), 2.0);
     }
 
     #[test]
@@ -1783,6 +3083,94 @@ mod tests {
         assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
     }
 
+    fn heap_instances_of(vm: &VM, type_name: &str) -> usize {
+        vm.object_manager.iter().filter(|&&obj| unsafe {
+            (*obj).obj_type == crate::objects::object::ObjectType::ObjStructInstance
+                && (*(*obj).as_struct_instance().struct_type).name == type_name
+        }).count()
+    }
+
+    #[test]
+    fn test_non_escaping_struct_literals_stay_on_stack() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct P { x, y }
+            struct Box { p }
+            fn local_only() {
+                var sum = 0;
+                for (i in 0..200) {
+                    var p = P { x = i, y = 1 };
+                    var b = Box { p = p };
+                    var q = b.p;
+                    q.y = 2;
+                    sum = sum + b.p.x + p.y;
+                }
+                return sum;
+            }
+            var total = local_only();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "total"), 20300.0);
+        assert_eq!(heap_instances_of(&vm, "P") + heap_instances_of(&vm, "Box"), 0);
+    }
+
+    #[test]
+    fn test_escaping_struct_literals_move_to_heap() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct P { x, y }
+            struct Box { p }
+            trait Total { fn total(); }
+            impl Total for P { fn total() { return self.x + self.y; } }
+            fn sum_of(p) { return p.x + p.y; }
+
+            fn returned() { var p = P { x = 1, y = 2 }; return p; }
+            fn passed() { var p = P { x = 3, y = 4 }; return sum_of(p); }
+            fn invoked() { var p = P { x = 5, y = 6 }; return p.total(); }
+            fn captured() { var p = P { x = 7, y = 8 }; return || p.x; }
+            fn stored() { var p = P { x = 9, y = 10 }; var list = [p]; return list; }
+            fn nested() { var b = Box { p = P { x = 11, y = 12 } }; return b.p; }
+            fn picked(c) { var a = P { x = 13, y = 0 }; var b = P { x = 14, y = 0 }; return c ? a : b; }
+            fn aliased() {
+                var keep = nil;
+                var p = P { x = 0, y = 0 };
+                for (i in 1..4) { keep = p; p = P { x = i, y = i }; }
+                return keep;
+            }
+            var h = new Box { p = nil };
+            fn field_stored() { var p = P { x = 15, y = 16 }; h.p = p; }
+            var g = nil;
+            fn global_stored() { g = P { x = 17, y = 18 }; }
+
+            var r = returned().y + passed() + invoked() + captured()() + stored()[0].y;
+            var n = nested().x + picked(true).x + picked(false).x + aliased().x;
+            field_stored();
+            global_stored();
+            gc.collect();
+            var f = h.p.y + g.y;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "r"), 2.0 + 7.0 + 11.0 + 7.0 + 10.0);
+        assert_eq!(global_number(&vm, "n"), 11.0 + 13.0 + 14.0 + 2.0);
+        assert_eq!(global_number(&vm, "f"), 34.0);
+    }
+
+    #[test]
+    fn test_drop_runs_for_plain_struct_literal() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Res { id }
+            var dropped = 0;
+            impl Drop for Res { fn drop() { dropped = dropped + self.id; } }
+            fn use_once() { var r = Res { id = 3 }; return r.id; }
+            var id = use_once();
+            gc.collect();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "id"), 3.0);
+        assert_eq!(global_number(&vm, "dropped"), 3.0);
+    }
+
     #[test]
     fn test_trait_invoke_on_struct() {
         let mut vm = VM::new();
@@ -1857,4 +3245,1152 @@ mod tests {
         "#;
         assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_ternary_expression() {
+        let mut vm = VM::new();
+        let script = r#"
+            var a = 5;
+            var size = a > 3 ? "big" : "small";
+            var nested = a > 10 ? "huge" : a > 3 ? "big" : "small";
+            var fallback = nil ? "set" : "default";
+            var arith = 1 + (false ? 1 : 2) * 3;
+            var low = true or false ? 1 : 0; // '?' binds looser than 'or'
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "size"), "big");
+        assert_eq!(global_string(&vm, "nested"), "big");
+        assert_eq!(global_string(&vm, "fallback"), "default");
+        assert_eq!(global_number(&vm, "arith"), 7.0);
+        assert_eq!(global_number(&vm, "low"), 1.0);
+    }
+
+    #[test]
+    fn test_ternary_in_recursive_function() {
+        let mut vm = VM::new();
+        let script = r#"
+            fn fib(n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }
+            var r = fib(10);
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "r"), 55.0);
+    }
+
+    #[test]
+    fn test_if_expression() {
+        let mut vm = VM::new();
+        let script = r#"
+            var a = 6;
+            var name = if (a == 5) { "five" } else if (a == 6) { "six" } else { "other" };
+            var doubled = if (a > 0) { a * 2 } else { 0 };
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "name"), "six");
+        assert_eq!(global_number(&vm, "doubled"), 12.0);
+    }
+
+    #[test]
+    fn test_ternary_missing_colon_compile_error() {
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var x = true ? 1;"), InterpretResult::InterpretCompileError);
+        let mut vm2 = VM::new();
+        assert_eq!(vm2.interpret("var y = if (true) { 1 };"), InterpretResult::InterpretCompileError);
+    }
+
+    #[test]
+    fn test_anonymous_function_expression() {
+        let mut vm = VM::new();
+        let script = r#"
+            fn apply(f, v) { return f(v); }
+            var doubled = apply(fn (x) { return x * 2; }, 21);
+            var inc = apply(|x| x + 1, 1);
+            var mul = |a, b| a * b;
+            var product = mul(6, 7);
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "doubled"), 42.0);
+        assert_eq!(global_number(&vm, "inc"), 2.0);
+        assert_eq!(global_number(&vm, "product"), 42.0);
+    }
+
+    #[test]
+    fn test_lambda_captures_upvalues() {
+        let mut vm = VM::new();
+        let script = r#"
+            fn counter() {
+                var n = 0;
+                return || n = n + 1;
+            }
+            var c = counter();
+            c(); c();
+            var count = c();
+
+            struct P { a }
+            fn mk() {
+                var p = P { a = 9 }; // promoted to heap when the lambda closes over it
+                return fn () { return p.a; };
+            }
+            var captured = mk()();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "count"), 3.0);
+        assert_eq!(global_number(&vm, "captured"), 9.0);
+    }
+
+    #[test]
+    fn test_short_lambda_struct_literal_body() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct P { a }
+            var f = |x| P { a = x };
+            var a = f(4).a;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "a"), 4.0);
+    }
+
+    #[test]
+    fn test_list_and_map_literals_with_indexing() {
+        let mut vm = VM::new();
+        let script = r#"
+            var xs = [1, 2, 3,];
+            xs[1] = 20;
+            var second = xs[1];
+            var m = {"a": 1, "b": 2};
+            m["c"] = m["a"] + m["b"];
+            var c = m["c"];
+            var missing = m["zzz"];
+            var letter = "latte"[2];
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "second"), 20.0);
+        assert_eq!(global_number(&vm, "c"), 3.0);
+        assert!(crate::value::is_nil(&vm.globals.find("missing").unwrap()));
+        assert_eq!(global_string(&vm, "letter"), "t");
+    }
+
+    #[test]
+    fn test_list_index_out_of_bounds_error() {
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var xs = [1]; print xs[1];"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var xs = [1]; xs[-1] = 2;"), InterpretResult::InterpretRuntimeError);
+    }
+
+    #[test]
+    fn test_for_in_builtin_iterables() {
+        let mut vm = VM::new();
+        let script = r#"
+            var sum = 0;
+            for (x in [1, 2, 3]) { sum = sum + x; }
+            var chars = "";
+            for (c in "héllo") chars = c + chars;
+            var keys = "";
+            var values = 0;
+            var m = {"x": 10, "y": 20};
+            for (k in m) { keys = keys + k; values = values + m[k]; }
+            var nested = 0;
+            for (row in [[1, 2], [3]]) for (v in row) nested = nested + v;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "sum"), 6.0);
+        assert_eq!(global_string(&vm, "chars"), "olléh");
+        assert_eq!(global_string(&vm, "keys"), "xy");
+        assert_eq!(global_number(&vm, "values"), 30.0);
+        assert_eq!(global_number(&vm, "nested"), 6.0);
+    }
+
+    #[test]
+    fn test_for_in_user_iterator() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Countdown { n }
+            trait Iterator { fn next(); }
+            impl Iterator for Countdown {
+                fn next() {
+                    if (self.n == 0) { return nil; }
+                    self.n = self.n - 1;
+                    return self.n + 1;
+                }
+            }
+            var heap = new Countdown { n = 3 };
+            var order = "";
+            for (i in heap) { order = order + (i == 3 ? "3" : i == 2 ? "2" : "1"); }
+            var left = heap.n;
+
+            fn total() {
+                var t = 0;
+                for (i in Countdown { n = 4 }) { t = t + i; }
+                return t;
+            }
+            var from_stack = total();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "order"), "321");
+        assert_eq!(global_number(&vm, "left"), 0.0);
+        assert_eq!(global_number(&vm, "from_stack"), 10.0);
+    }
+
+    #[test]
+    fn test_for_in_closures_capture_each_iteration() {
+        let mut vm = VM::new();
+        let script = r#"
+            fn make() {
+                var fs = [nil, nil, nil];
+                var i = 0;
+                for (v in [1, 10, 100]) { fs[i] = || v; i = i + 1; }
+                return fs;
+            }
+            var fs = make();
+            var total = fs[0]() + fs[1]() + fs[2]();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "total"), 111.0);
+    }
+
+    #[test]
+    fn test_for_in_non_iterable_errors() {
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("for (x in 3) print x;"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        let script = r#"
+            struct P { a }
+            for (x in new P { a = 1 }) print x;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretRuntimeError);
+    }
+
+    #[test]
+    fn test_for_in_gc_pressure() {
+        for mode in GC_MODES {
+            let mut vm = gc_stress_vm(mode, 1024);
+            let script = r#"
+                var words = ["alpha", "beta", "gamma"];
+                var joined = "";
+                for (round in [1, 2, 3, 4, 5, 6, 7, 8]) {
+                    for (w in words) { joined = joined + w; }
+                    words[0] = "alpha" + joined;
+                }
+                var last = words[2];
+            "#;
+            assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+            assert_eq!(global_string(&vm, "last"), "gamma");
+        }
+    }
+
+    #[test]
+    fn test_range_iteration() {
+        let mut vm = VM::new();
+        let script = r#"
+            var n = 4;
+            var exclusive = 0;
+            for (i in 0..n - 1) { exclusive = exclusive + i; }
+            var inclusive = 0;
+            for (i in 1..=n) { inclusive = inclusive + i; }
+            var empty = 0;
+            for (i in 3..1) { empty = empty + 1; }
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "exclusive"), 3.0);
+        assert_eq!(global_number(&vm, "inclusive"), 10.0);
+        assert_eq!(global_number(&vm, "empty"), 0.0);
+    }
+
+    #[test]
+    fn test_string_and_list_slicing() {
+        let mut vm = VM::new();
+        let script = r#"
+            var s = "latte"[1..3];
+            var whole = "latte"[0..=4];
+            var unicode = "héllo"[1..=2];
+            var xs = [1, 2, 3, 4];
+            var middle = xs[1..=2];
+            var sum = middle[0] + middle[1];
+            var tail = xs[2..4];
+            var last = tail[1];
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "s"), "at");
+        assert_eq!(global_string(&vm, "whole"), "latte");
+        assert_eq!(global_string(&vm, "unicode"), "él");
+        assert_eq!(global_number(&vm, "sum"), 5.0);
+        assert_eq!(global_number(&vm, "last"), 4.0);
+    }
+
+    #[test]
+    fn test_slice_out_of_bounds_errors() {
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret(r#"print "abc"[1..4];"#), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var xs = [1, 2]; print xs[0..=2];"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var xs = [1, 2]; print xs[2..1];"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret(r#"var r = "a".."b";"#), InterpretResult::InterpretRuntimeError);
+    }
+    #[test]
+    fn test_enum_construct_and_match() {
+        let mut vm = VM::new();
+        let script = r#"
+            enum Shape { Circle(r), Rect(w, h), Empty }
+            fn area(s) {
+                var result = -1;
+                match (s) {
+                    Shape.Circle(r) => { result = 3 * r * r; }
+                    Shape.Rect(w, h) => { result = w * h; }
+                    Shape.Empty => { result = 0; }
+                }
+                return result;
+            }
+            var circle = area(Shape.Circle(2));
+            var rect = area(Shape.Rect(3, 4));
+            var empty = area(Shape.Empty);
+            var same = Shape.Rect(1, 2) == Shape.Rect(1, 2) ? 1 : 0;
+            var different = Shape.Empty == Shape.Circle(1) ? 1 : 0;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "circle"), 12.0);
+        assert_eq!(global_number(&vm, "rect"), 12.0);
+        assert_eq!(global_number(&vm, "empty"), 0.0);
+        assert_eq!(global_number(&vm, "same"), 1.0);
+        assert_eq!(global_number(&vm, "different"), 0.0);
+    }
+
+    #[test]
+    fn test_match_wildcard_and_state_machine() {
+        let mut vm = VM::new();
+        let script = r#"
+            enum State { Idle, Running(ticks), Done }
+            fn step(s) {
+                var next = s;
+                match (s) {
+                    State.Idle => next = State.Running(0),
+                    State.Running(t) => next = t < 2 ? State.Running(t + 1) : State.Done,
+                    _ => next = State.Done,
+                }
+                return next;
+            }
+            var s = State.Idle;
+            var steps = 0;
+            while (s != State.Done) { s = step(s); steps = steps + 1; }
+            var label = "";
+            match (s) {
+                State.Idle => label = "idle",
+                _ => label = "other",
+            }
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "steps"), 4.0);
+        assert_eq!(global_string(&vm, "label"), "other");
+    }
+
+    #[test]
+    fn test_match_errors() {
+        // Non-exhaustive matches only warn at compile time, but fail when no arm applies.
+        let mut vm = VM::new();
+        let script = r#"
+            enum Light { Red, Green }
+            match (Light.Green) { Light.Red => print "stop"; }
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        let script = r#"
+            enum Light { Red, Green }
+            var seen = 0;
+            match (Light.Red) { Light.Red => seen = 1, }
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "seen"), 1.0);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("enum Light { Red } match (Light.Red) { Light.Blue => print 1; }"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("enum Light { Red } match (Light.Red) { _ => print 1; Light.Red => print 2; }"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("enum Shape { Circle(r) } var c = Shape.Circle(1, 2);"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("enum Shape { Circle(r) } var c = Shape.Circle;"), InterpretResult::InterpretRuntimeError);
+    }
+    #[test]
+    fn test_struct_destructuring() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Point { x, y }
+            var heap = new Point { x = 1, y = 2 };
+            var Point { x, y } = heap;
+            fn length_squared() {
+                var p = Point { x = 3, y = 4 };
+                var Point { y, x } = p;
+                return x * x + y * y;
+            }
+            var len2 = length_squared();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "x"), 1.0);
+        assert_eq!(global_number(&vm, "y"), 2.0);
+        assert_eq!(global_number(&vm, "len2"), 25.0);
+    }
+
+    #[test]
+    fn test_list_destructuring() {
+        let mut vm = VM::new();
+        let script = r#"
+            var [a, b, ...rest] = [1, 2, 3, 4];
+            var rest_sum = rest[0] + rest[1];
+            fn first_two(xs) {
+                var [head, next] = xs;
+                var [only, ...empty] = [head];
+                return head + next + only;
+            }
+            var total = first_two([10, 20, 30]);
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "a"), 1.0);
+        assert_eq!(global_number(&vm, "b"), 2.0);
+        assert_eq!(global_number(&vm, "rest_sum"), 7.0);
+        assert_eq!(global_number(&vm, "total"), 40.0);
+    }
+
+    #[test]
+    fn test_destructuring_errors() {
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var [a, b] = [1];"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("struct P { x } var P { x, z } = new P { x = 1 };"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("struct P { x } struct Q { x } var Q { x } = new P { x = 1 };"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var [a] = 5;"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("var [...rest, a] = [1];"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f() { var [a, a] = [1, 2]; }"), InterpretResult::InterpretCompileError);
+    }
+    #[test]
+    fn test_const_bindings() {
+        let mut vm = VM::new();
+        let script = r#"
+            const limit = 3;
+            let name = "latte";
+            const [lo, hi] = [1, 5];
+            fn scaled() {
+                const factor = 2;
+                var capture = || factor * limit;
+                return capture();
+            }
+            var result = scaled() + lo + hi;
+            const items = [1, 2];
+            items[0] = 10;
+            var first = items[0];
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "result"), 12.0);
+        assert_eq!(global_number(&vm, "first"), 10.0);
+        assert_eq!(global_string(&vm, "name"), "latte");
+    }
+
+    #[test]
+    fn test_const_reassignment_errors() {
+        // Compile time: locals, upvalues and globals declared in the same script.
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f() { const x = 1; x = 2; }"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f() { let x = 1; var g = fn () { x = 2; }; }"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("const x = 1; x = 2;"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("const x;"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f() { const Point { x } = nil; x = 1; }"), InterpretResult::InterpretCompileError);
+
+        // Run time: constants defined by an earlier interpret call, as in a REPL session.
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("const port = 8080;"), InterpretResult::InterpretOk);
+        assert_eq!(vm.interpret("port = 9000;"), InterpretResult::InterpretRuntimeError);
+        assert_eq!(vm.interpret("var port = 9000;"), InterpretResult::InterpretRuntimeError);
+        assert_eq!(global_number(&vm, "port"), 8080.0);
+    }
+    #[test]
+    fn test_default_and_rest_parameters() {
+        let mut vm = VM::new();
+        let script = r#"
+            fn greet(name, greeting = "hello", punctuation = greeting == "hello" ? "!" : ".") {
+                return greeting + " " + name + punctuation;
+            }
+            var a = greet("ada");
+            var b = greet("alan", "bye");
+            var c = greet("grace", "hi", "?");
+            fn sum(first, ...rest) {
+                var total = first;
+                for (x in rest) total = total + x;
+                return total;
+            }
+            var none = sum(1);
+            var many = sum(1, 2, 3, 4);
+            var scale = |x, by = 10| x * by;
+            var scaled = scale(2) + scale(2, 3);
+            fn fresh(xs = []) { return xs; }
+            var distinct = fresh() == fresh() ? 1 : 0;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "a"), "hello ada!");
+        assert_eq!(global_string(&vm, "b"), "bye alan.");
+        assert_eq!(global_string(&vm, "c"), "hi grace?");
+        assert_eq!(global_number(&vm, "none"), 1.0);
+        assert_eq!(global_number(&vm, "many"), 10.0);
+        assert_eq!(global_number(&vm, "scaled"), 26.0);
+        assert_eq!(global_number(&vm, "distinct"), 0.0);
+    }
+
+    #[test]
+    fn test_named_arguments() {
+        let mut vm = VM::new();
+        let script = r#"
+            fn connect(host, port = 80, secure = false) {
+                return host + ":" + (secure ? "s" : "") + (port == 80 ? "80" : "other");
+            }
+            var plain = connect("a");
+            var named = connect("b", secure: true);
+            var all_named = connect(port: 81, host: "c");
+            struct Counter { n }
+            trait Step { fn step(by); }
+            impl Step for Counter { fn step(by = 1) { self.n = self.n + by; return self.n; } }
+            var counter = new Counter { n = 0 };
+            counter.step();
+            var stepped = counter.step(5);
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "plain"), "a:80");
+        assert_eq!(global_string(&vm, "named"), "b:s80");
+        assert_eq!(global_string(&vm, "all_named"), "c:other");
+        assert_eq!(global_number(&vm, "stepped"), 6.0);
+    }
+
+    #[test]
+    fn test_parameter_errors() {
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a, b = 1) {} f();"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a, b = 1) {} f(1, 2, 3);"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a, ...rest) {} f();"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a) {} f(b: 1);"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a) {} f(1, a: 2);"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a, b = 1) {} f(b: 2);"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a = 1, b) {}"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(...rest, a) {}"), InterpretResult::InterpretCompileError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("fn f(a, b) {} f(a: 1, 2);"), InterpretResult::InterpretCompileError);
+    }
+    #[test]
+    fn test_struct_field_defaults() {
+        let mut vm = VM::new();
+        let script = r#"
+            var made = 0;
+            fn next_id() { made = made + 1; return made; }
+            struct Config { host = "localhost", port = 8080, id = next_id(), name }
+            var heap = new Config { name = "a" };
+            fn local_port() {
+                var c = Config { name = "b", port = 1 };
+                return c.port + c.id;
+            }
+            var stack_sum = local_port();
+            var host = heap.host;
+            var port = heap.port;
+            var second = new Config { name = "c", id = 99 };
+            var ids = heap.id * 100 + second.id;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "host"), "localhost");
+        assert_eq!(global_number(&vm, "port"), 8080.0);
+        assert_eq!(global_number(&vm, "stack_sum"), 3.0);
+        assert_eq!(global_number(&vm, "ids"), 199.0);
+        // The explicit id skipped the default, so next_id() ran only twice.
+        assert_eq!(global_number(&vm, "made"), 2.0);
+    }
+
+    #[test]
+    fn test_struct_update_syntax() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Config { host = "localhost", port = 8080 }
+            var base = new Config { host = "example.org" };
+            var moved = new Config { port = 9000, ..base };
+            fn on_stack() {
+                var local = Config { port = 1 };
+                var copy = Config { ..local };
+                copy.port = 2;
+                return local.port * 10 + copy.port;
+            }
+            var ports = on_stack();
+            var host = moved.host;
+            var port = moved.port;
+            var base_port = base.port;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "host"), "example.org");
+        assert_eq!(global_number(&vm, "port"), 9000.0);
+        assert_eq!(global_number(&vm, "base_port"), 8080.0);
+        assert_eq!(global_number(&vm, "ports"), 12.0);
+    }
+
+    #[test]
+    fn test_struct_default_and_update_errors() {
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("struct A { x = 1, y } var a = new A { x = 2 };"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("struct A { x } struct B { x } var b = new B { x = 1 }; var a = new A { ..b };"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("struct A { x } var a = new A { ..5 };"), InterpretResult::InterpretRuntimeError);
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret("struct A { x } var b = new A { x = 1 }; var a = new A { ..b, x = 2 };"), InterpretResult::InterpretCompileError);
+    }
+    #[test]
+    fn test_private_and_readonly_fields() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Account { readonly owner, private balance = 0 }
+            trait Bank { fn deposit(amount); fn balance(); fn richer(other); }
+            impl Bank for Account {
+                fn deposit(amount) {
+                    var add = |x| self.balance = self.balance + x;
+                    add(amount);
+                    return self.balance;
+                }
+                fn balance() { return self.balance; }
+                fn richer(other) { return self.balance > other.balance; }
+            }
+            var acct = new Account { owner = "ada" };
+            acct.deposit(5);
+            var total = acct.deposit(10);
+            var owner = acct.owner;
+            var poorer = new Account { owner = "alan", balance = 1 };
+            var richer = acct.richer(poorer) ? 1 : 0;
+            fn stack_owner() {
+                var local = Account { owner = "grace" };
+                return local.owner;
+            }
+            var stack = stack_owner();
+            // 'private' and 'readonly' are only modifiers when a field name follows them.
+            struct Flags { private, readonly = 1 }
+            var flags = new Flags { private = 2 };
+            var plain = flags.private + flags.readonly;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "total"), 15.0);
+        assert_eq!(global_string(&vm, "owner"), "ada");
+        assert_eq!(global_number(&vm, "richer"), 1.0);
+        assert_eq!(global_string(&vm, "stack"), "grace");
+        assert_eq!(global_number(&vm, "plain"), 3.0);
+    }
+
+    #[test]
+    fn test_field_access_errors() {
+        let declaration = "struct Account { readonly owner, private balance = 0 } ";
+        for body in [
+            "var a = new Account { owner = 1 }; print a.balance;",
+            "var a = new Account { owner = 1 }; a.balance = 5;",
+            "var a = new Account { owner = 1 }; a.owner = 2;",
+            "fn f() { var a = Account { owner = 1 }; a.owner = 2; } f();",
+            "var a = new Account { owner = 1 }; var Account { balance } = a;",
+        ] {
+            let mut vm = VM::new();
+            let script = format!("{}{}", declaration, body);
+            assert_eq!(vm.interpret(&script), InterpretResult::InterpretRuntimeError, "{}", body);
+        }
+        // Methods of another type don't get access either.
+        let mut vm = VM::new();
+        let script = r#"
+            struct Account { private balance = 0 }
+            struct Thief { name }
+            trait Steal { fn steal(a); }
+            impl Steal for Thief { fn steal(a) { return a.balance; } }
+            var t = new Thief { name = "x" };
+            t.steal(new Account {});
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretRuntimeError);
+    }
+
+    #[test]
+    fn test_trait_membership() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Circle { r }
+            struct Square { s }
+            trait Shape { fn area(); }
+            trait Named { fn name(); }
+            impl Shape for Circle { fn area() { return 3 * self.r * self.r; } }
+            impl Named for Circle { fn name() { return "circle"; } }
+            impl Shape for Square { fn area() { return self.s * self.s; } }
+            impl Named for number { fn name() { return "number"; } }
+            var c = new Circle { r = 2 };
+            var shape = c is Shape ? 1 : 0;
+            var named = Square { s = 1 } is Named ? 1 : 0;
+            var by_name = implements(new Square { s = 1 }, "Shape") ? 1 : 0;
+            var builtin = 7 is Named and !("x" is Named) ? 1 : 0;
+            fn stack_check() { var local = Circle { r = 1 }; return local is Named; }
+            var stack = stack_check() ? 1 : 0;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "shape"), 1.0);
+        assert_eq!(global_number(&vm, "named"), 0.0);
+        assert_eq!(global_number(&vm, "by_name"), 1.0);
+        assert_eq!(global_number(&vm, "builtin"), 1.0);
+        assert_eq!(global_number(&vm, "stack"), 1.0);
+    }
+
+    #[test]
+    fn test_trait_qualified_calls() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Card { rank }
+            trait Named { fn name(); }
+            trait Drawable { fn name(); fn scale(by); }
+            impl Named for Card { fn name() { return "card"; } }
+            impl Drawable for Card {
+                fn name() { return "sprite"; }
+                fn scale(by) { return self.rank * by; }
+            }
+            var card = new Card { rank = 3 };
+            var named = Named.name(card);
+            var drawn = Drawable.name(card);
+            var scaled = card.scale(2) + Drawable.scale(card, 10);
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "named"), "card");
+        assert_eq!(global_string(&vm, "drawn"), "sprite");
+        assert_eq!(global_number(&vm, "scaled"), 36.0);
+    }
+
+    #[test]
+    fn test_trait_membership_errors() {
+        let declaration = r#"
+            struct Card { rank }
+            trait Named { fn name(); }
+            trait Drawable { fn name(); }
+            trait Empty { fn nothing(); }
+            impl Named for Card { fn name() { return "card"; } }
+            impl Drawable for Card { fn name() { return "sprite"; } }
+            var card = new Card { rank = 1 };
+        "#;
+        for body in [
+            "card.name();",
+            "print card is Missing;",
+            "print implements(card, \"Missing\");",
+            "print implements(card, 1);",
+            "Empty.nothing(card);",
+            "Named.name();",
+            "Named.other(card);",
+        ] {
+            let mut vm = VM::new();
+            let script = format!("{}{}", declaration, body);
+            assert_eq!(vm.interpret(&script), InterpretResult::InterpretRuntimeError, "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_supertraits() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Circle { r }
+            trait Display { fn describe(); fn label(); }
+            trait Eq { fn equals(other); }
+            trait Shape: Display + Eq { fn area(); fn describe(); }
+            trait Solid: Shape { fn volume(); }
+            impl Display for Circle {
+                fn describe() { return "circle"; }
+                fn label() { return "C"; }
+            }
+            impl Eq for Circle { fn equals(other) { return self.r == other.r; } }
+            impl Shape for Circle {
+                fn area() { return 3 * self.r * self.r; }
+                fn describe() {
+                    var prefix = || "shape ";
+                    return prefix() + super.describe();
+                }
+            }
+            impl Solid for Circle { fn volume() { return self.area() * 2; } }
+            var c = new Circle { r = 2 };
+            var described = c.describe();
+            var parent = Display.describe(c);
+            var label = Solid.label(c);
+            var same = Shape.equals(c, new Circle { r = 2 }) ? 1 : 0;
+            var volume = c.volume();
+            var display = c is Display ? 1 : 0;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_string(&vm, "described"), "shape circle");
+        assert_eq!(global_string(&vm, "parent"), "circle");
+        assert_eq!(global_string(&vm, "label"), "C");
+        assert_eq!(global_number(&vm, "same"), 1.0);
+        assert_eq!(global_number(&vm, "volume"), 24.0);
+        assert_eq!(global_number(&vm, "display"), 1.0);
+    }
+
+    #[test]
+    fn test_supertrait_errors() {
+        let declaration = r#"
+            struct Sq { s }
+            trait Display { fn describe(); }
+            trait Shape: Display { fn area(); }
+        "#;
+        for body in [
+            "impl Shape for Sq { fn area() { return 1; } }",
+            "trait Broken: Missing { fn f(); }",
+            "impl Display for Sq { fn describe() { return super.describe(); } } new Sq { s = 1 }.describe();",
+        ] {
+            let mut vm = VM::new();
+            let script = format!("{}{}", declaration, body);
+            assert_eq!(vm.interpret(&script), InterpretResult::InterpretRuntimeError, "{}", body);
+        }
+        for body in [
+            "fn f() { return super.describe(); }",
+            "trait Loop: Loop { fn f(); }",
+            "trait Twice: Display + Display { fn f(); }",
+        ] {
+            let mut vm = VM::new();
+            let script = format!("{}{}", declaration, body);
+            assert_eq!(vm.interpret(&script), InterpretResult::InterpretCompileError, "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_inline_caches_polymorphic_sites() {
+        // One GetField and one Invoke site see several struct types with different field layouts,
+        // on the heap and on the stack.
+        let mut vm = VM::new();
+        let script = r#"
+            struct Square { name, side }
+            struct Rect { w, h, name }
+            trait Area { fn area(); }
+            impl Area for Square { fn area() { return self.side * self.side; } }
+            impl Area for Rect { fn area() { return self.w * self.h; } }
+            fn measure(shape) { return shape.area(); }
+            fn label(shape) { return shape.name; }
+            var shapes = [new Square { name = "sq", side = 3 }, new Rect { w = 2, h = 5, name = "re" }];
+            var total = 0;
+            var names = "";
+            for (round in 0..3) {
+                for (shape in shapes) {
+                    total = total + measure(shape);
+                    names = names + label(shape);
+                    shape.name = shape.name;
+                }
+            }
+            fn on_stack() {
+                var sum = 0;
+                for (i in 1..4) {
+                    var s = Square { name = "s", side = i };
+                    var r = Rect { w = i, h = 1, name = "r" };
+                    sum = sum + s.side + r.w;
+                    s.side = r.w;
+                }
+                return sum;
+            }
+            var stack_sum = on_stack();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "total"), 57.0);
+        assert_eq!(global_string(&vm, "names"), "sqresqresqre");
+        assert_eq!(global_number(&vm, "stack_sum"), 12.0);
+    }
+
+    #[test]
+    fn test_inline_cache_sees_later_impls() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct P { x }
+            trait Base { fn f(); }
+            impl Base for P { fn f() { return 1; } }
+            fn call(p) { return p.f(); }
+            var p = new P { x = 1 };
+            var before = call(p);
+            trait Sub: Base { fn f(); }
+            impl Sub for P { fn f() { return 2; } }
+            var after = call(p);
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "before"), 1.0);
+        assert_eq!(global_number(&vm, "after"), 2.0);
+
+        // A second unrelated trait makes the cached method ambiguous.
+        let mut vm = VM::new();
+        let script = r#"
+            struct P { x }
+            trait A { fn f(); }
+            impl A for P { fn f() { return 1; } }
+            fn call(p) { return p.f(); }
+            var p = new P { x = 1 };
+            var first = call(p);
+            trait B { fn f(); }
+            impl B for P { fn f() { return 2; } }
+            call(p);
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretRuntimeError);
+        assert_eq!(global_number(&vm, "first"), 1.0);
+    }
+
+    #[test]
+    fn test_bound_methods() {
+        let mut vm = VM::new();
+        let script = r#"
+            struct Counter { n }
+            trait Count { fn inc(by); fn get(); }
+            impl Count for Counter {
+                fn inc(by) { self.n = self.n + by; return self.n; }
+                fn get() { return self.n; }
+            }
+            var c = new Counter { n = 0 };
+            var inc = c.inc;
+            inc(2);
+            inc(by: 3);
+            fn apply(callback, x) { return callback(x); }
+            var applied = apply(c.inc, 10);
+            var total = c.get();
+            var same = c.inc == c.inc ? 1 : 0;
+            var other = c.inc == new Counter { n = 0 }.inc ? 1 : 0;
+            fn from_stack() {
+                var local = Counter { n = 7 };
+                var get = local.get;
+                return get;
+            }
+            var escaped = from_stack()();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "applied"), 15.0);
+        assert_eq!(global_number(&vm, "total"), 15.0);
+        assert_eq!(global_number(&vm, "same"), 1.0);
+        assert_eq!(global_number(&vm, "other"), 0.0);
+        assert_eq!(global_number(&vm, "escaped"), 7.0);
+    }
+
+    #[test]
+    fn test_bound_methods_survive_gc() {
+        for mode in GC_MODES {
+            let mut vm = gc_stress_vm(mode, 0);
+            let script = r#"
+                struct Box { v }
+                trait Get { fn get(); }
+                impl Get for Box { fn get() { return self.v; } }
+                struct Node { get, next }
+                var head = nil;
+                var i = 0;
+                while (i < 50) {
+                    head = new Node { get = new Box { v = i }.get, next = head };
+                    i = i + 1;
+                }
+                var sum = 0;
+                while (head != nil) {
+                    var get = head.get;
+                    sum = sum + get();
+                    head = head.next;
+                }
+            "#;
+            assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+            assert_eq!(global_number(&vm, "sum"), 1225.0);
+            assert!(vm.gc.stats().cycles > 0);
+        }
+    }
+
+    #[test]
+    fn test_incremental_gc_write_barriers() {
+        let mut vm = VM::new();
+        vm.set_gc_mode(GcMode::Incremental);
+        vm.set_gc_threshold(0);
+        // Enough live objects that a cycle needs several marking steps, with values moved between
+        // already-scanned and unscanned holders while it runs.
+        let script = r#"
+            struct Cell { v }
+            struct Node { v, next }
+            // Two separate chains, so one is usually traced long before the other.
+            var left = nil;
+            var right = nil;
+            var i = 0;
+            while (i < 300) {
+                left = new Node { v = nil, next = left };
+                right = new Node { v = new Cell { v = i }, next = right };
+                i = i + 1;
+            }
+            var held = [new Cell { v = "s" }];
+            var keys = {};
+            var captured = nil;
+            fn keep() { return captured; }
+            var round = 0;
+            while (round < 20) {
+                var l = left;
+                var r = right;
+                while (l != nil) {
+                    if (l.v == nil) { l.v = r.v; r.v = nil; } else { r.v = l.v; l.v = nil; }
+                    var junk = new Cell { v = l };
+                    l = l.next;
+                    r = r.next;
+                }
+                keys["k"] = held[0];
+                held[0] = nil;
+                captured = keys["k"];
+                keys["k"] = nil;
+                held[0] = keep();
+                round = round + 1;
+            }
+            // Reuse the memory of anything wrongly freed before reading the payloads back.
+            var filler = nil;
+            i = 0;
+            while (i < 2000) { filler = new Node { v = new Cell { v = -1000 }, next = filler }; i = i + 1; }
+            var total = 0;
+            while (right != nil) { total = total + right.v.v; right = right.next; }
+            var last = held[0].v;
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "total"), 44850.0);
+        assert_eq!(global_string(&vm, "last"), "s");
+        assert!(vm.gc.stats().cycles > 0);
+    }
+
+    #[test]
+    fn test_generational_gc_remembered_set() {
+        // Only minor collections: the holders get promoted, then receive young values.
+        let mut vm = gc_stress_vm(GcMode::Generational, 64 * 1024 * 1024);
+        let script = r#"
+            struct Cell { v }
+            struct Node { v, next }
+            var holders = nil;
+            var i = 0;
+            while (i < 50) { holders = new Node { v = nil, next = holders }; i = i + 1; }
+            var slots = [nil];
+            var named = {};
+            fn make_box() {
+                var boxed = nil;
+                fn put(x) { boxed = x; }
+                fn take() { return boxed; }
+                return [put, take];
+            }
+            var box = make_box();
+            // Each node is linked from its older neighbour while that one is still young, so a node can
+            // be promoted while it still points at young nodes.
+            var head = new Node { v = 0, next = nil };
+            var tail = head;
+            i = 0;
+            while (i < 2000) { tail.next = new Node { v = i, next = nil }; tail = tail.next; i = i + 1; }
+            var round = 0;
+            while (round < 10) {
+                var h = holders;
+                var k = 0;
+                while (h != nil) {
+                    h.v = new Cell { v = round * 100 + k };
+                    slots[0] = new Cell { v = k };
+                    named["last"] = new Cell { v = round };
+                    box[0](new Cell { v = round + k });
+                    var junk = new Cell { v = h };
+                    h = h.next;
+                    k = k + 1;
+                }
+                round = round + 1;
+            }
+            i = 0;
+            while (i < 2000) { var junk = new Cell { v = -1 }; i = i + 1; }
+            var total = 0;
+            var h = holders;
+            while (h != nil) { total = total + h.v.v; h = h.next; }
+            var slot = slots[0].v;
+            var last = named["last"].v;
+            var boxed = box[1]().v;
+            var chained = 0;
+            var n = head;
+            while (n != nil) { chained = chained + n.v; n = n.next; }
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "total"), 46225.0);
+        assert_eq!(global_number(&vm, "slot"), 49.0);
+        assert_eq!(global_number(&vm, "last"), 9.0);
+        assert_eq!(global_number(&vm, "boxed"), 58.0);
+        assert_eq!(global_number(&vm, "chained"), 1999000.0);
+        let stats = vm.gc.stats();
+        assert!(stats.minor_cycles > 0);
+        assert_eq!(stats.major_cycles, 0);
+        assert!(stats.total_promoted_bytes > 0);
+        assert_eq!(stats.cycles, stats.minor_cycles + stats.major_cycles);
+    }
+
+    #[test]
+    fn test_bound_method_errors() {
+        let declaration = r#"
+            struct Card { rank }
+            trait Named { fn name(); }
+            trait Drawable { fn name(); }
+            impl Named for Card { fn name() { return "card"; } }
+            impl Drawable for Card { fn name() { return "sprite"; } }
+            var card = new Card { rank = 1 };
+        "#;
+        for body in [
+            "var f = card.name;",
+            "var f = card.missing;",
+            "var n = 1; var f = n.name;",
+        ] {
+            let mut vm = VM::new();
+            let script = format!("{}{}", declaration, body);
+            assert_eq!(vm.interpret(&script), InterpretResult::InterpretRuntimeError, "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_tuples() {
+        let mut vm = VM::new();
+        vm.set_gc_threshold(0);
+        let script = r#"
+            fn divmod(a, b) { return (a / b, a - b * 2); }
+            var t = divmod(10, 4);
+            var first = t.0;
+            var nested = ((1, 2), "x", (3,));
+            var inner = nested.0.1 + nested.2.0;
+            var grouped = (1 + 2) * 3;
+            var equal = (1, "a", nil) == (1, "a", nil) and (1, 2) != (2, 1) and () == () ? 1 : 0;
+            var (q, r) = divmod(9, 3);
+            fn local() {
+                let (a, b,) = ("p", "q");
+                return a + b;
+            }
+            var joined = local();
+            struct P { x }
+            fn escape() { var p = P { x = 4 }; return (p, 1); }
+            var escaped = escape().0.x;
+            trait Pair { fn sum(); }
+            impl Pair for tuple { fn sum() { return self.0 + self.1; } }
+            var summed = (2, 5).sum();
+        "#;
+        assert_eq!(vm.interpret(script), InterpretResult::InterpretOk);
+        assert_eq!(global_number(&vm, "first"), 2.5);
+        assert_eq!(global_number(&vm, "inner"), 5.0);
+        assert_eq!(global_number(&vm, "grouped"), 9.0);
+        assert_eq!(global_number(&vm, "equal"), 1.0);
+        assert_eq!(global_number(&vm, "q"), 3.0);
+        assert_eq!(global_number(&vm, "r"), 3.0);
+        assert_eq!(global_string(&vm, "joined"), "pq");
+        assert_eq!(global_number(&vm, "escaped"), 4.0);
+        assert_eq!(global_number(&vm, "summed"), 7.0);
+    }
+
+    #[test]
+    fn test_tuple_errors() {
+        for script in [
+            "var t = (1, 2); print t.2;",
+            "var n = 1; print n.0;",
+            "var (a, b) = (1, 2, 3);",
+            "var (a, b) = [1, 2];",
+            "var t = (1, 2); print t.x;",
+        ] {
+            let mut vm = VM::new();
+            assert_eq!(vm.interpret(script), InterpretResult::InterpretRuntimeError, "{}", script);
+        }
+        for script in [
+            "var t = (1, 2); t.0 = 5;",
+            "var () = ();",
+        ] {
+            let mut vm = VM::new();
+            assert_eq!(vm.interpret(script), InterpretResult::InterpretCompileError, "{}", script);
+        }
+    }
+}